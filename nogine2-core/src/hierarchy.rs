@@ -0,0 +1,257 @@
+use crate::{math::{mat3x3::mat3, transform::Transform2D}, slotmap::{SlotKey, SlotMap}};
+
+#[derive(Debug, Clone)]
+struct Node {
+    local: Transform2D,
+    world: Transform2D,
+    world_mat: mat3,
+    parent: SlotKey,
+    children: Vec<SlotKey>,
+    /// If set, every descendant is also dirty.
+    dirty: bool,
+}
+
+/// Parent-child tree of transforms. World transforms are cached and only recomputed after a node or any of its ancestors changes. Extra data can be attached to nodes with a `SecondaryMap`.
+#[derive(Debug, Clone)]
+pub struct TransformHierarchy {
+    nodes: SlotMap<Node>,
+}
+
+impl TransformHierarchy {
+    pub const fn new() -> Self {
+        Self { nodes: SlotMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, node: SlotKey) -> bool {
+        self.nodes.contains(node)
+    }
+
+    /// Adds a node. `parent` may be `SlotKey::NULL` for root nodes. Invalid parents are treated as `SlotKey::NULL`.
+    pub fn insert(&mut self, local: Transform2D, parent: SlotKey) -> SlotKey {
+        let parent = if self.nodes.contains(parent) { parent } else { SlotKey::NULL };
+        let key = self.nodes.insert(Node { local, world: local, world_mat: local.to_mat3(), parent, children: Vec::new(), dirty: true });
+
+        if let Some(p) = self.nodes.get_mut(parent) {
+            p.children.push(key);
+        }
+        return key;
+    }
+
+    /// Removes a node and all its descendants. Returns the amount of removed nodes.
+    pub fn remove(&mut self, node: SlotKey) -> usize {
+        let Some(parent) = self.nodes.get(node).map(|x| x.parent) else { return 0 };
+        if let Some(p) = self.nodes.get_mut(parent) {
+            p.children.retain(|x| *x != node);
+        }
+
+        let mut removed = 0;
+        let mut stack = vec![node];
+        while let Some(key) = stack.pop() {
+            if let Some(n) = self.nodes.remove(key) {
+                stack.extend(n.children);
+                removed += 1;
+            }
+        }
+        return removed;
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    /// Returns `SlotKey::NULL` for root nodes and `None` for invalid nodes.
+    pub fn parent(&self, node: SlotKey) -> Option<SlotKey> {
+        self.nodes.get(node).map(|x| x.parent)
+    }
+
+    pub fn children(&self, node: SlotKey) -> &[SlotKey] {
+        self.nodes.get(node).map(|x| x.children.as_slice()).unwrap_or(&[])
+    }
+
+    /// Returns if `ancestor` is `node` or any of its ancestors.
+    pub fn is_ancestor(&self, ancestor: SlotKey, node: SlotKey) -> bool {
+        let mut current = node;
+        while let Some(n) = self.nodes.get(current) {
+            if current == ancestor {
+                return true;
+            }
+            current = n.parent;
+        }
+        return false;
+    }
+
+    /// Changes the parent of a node. If `keep_world` is set, the local transform is changed so that the node doesn't move. Will return `false` if the node is invalid or the change would create a cycle.
+    pub fn set_parent(&mut self, node: SlotKey, parent: SlotKey, keep_world: bool) -> bool {
+        let parent = if self.nodes.contains(parent) { parent } else { SlotKey::NULL };
+        if !self.nodes.contains(node) || self.is_ancestor(node, parent) {
+            return false;
+        }
+
+        if keep_world {
+            let world = self.world(node).unwrap_or_default();
+            let parent_world = self.world(parent).unwrap_or_default();
+            self.nodes[node].local = world.relative_to(&parent_world).unwrap_or(world);
+        }
+
+        let old_parent = self.nodes[node].parent;
+        if let Some(p) = self.nodes.get_mut(old_parent) {
+            p.children.retain(|x| *x != node);
+        }
+        if let Some(p) = self.nodes.get_mut(parent) {
+            p.children.push(node);
+        }
+
+        self.nodes[node].parent = parent;
+        self.mark_dirty(node);
+        return true;
+    }
+
+    pub fn local(&self, node: SlotKey) -> Option<Transform2D> {
+        self.nodes.get(node).map(|x| x.local)
+    }
+
+    pub fn set_local(&mut self, node: SlotKey, local: Transform2D) {
+        self.modify_local(node, |x| *x = local);
+    }
+
+    /// Modifies the local transform of a node, marking it and its descendants as dirty.
+    pub fn modify_local(&mut self, node: SlotKey, f: impl FnOnce(&mut Transform2D)) {
+        let Some(n) = self.nodes.get_mut(node) else { return };
+        f(&mut n.local);
+        self.mark_dirty(node);
+    }
+
+    /// Moves a node so that its world transform becomes `world`.
+    pub fn set_world(&mut self, node: SlotKey, world: Transform2D) {
+        let Some(parent) = self.parent(node) else { return };
+        let parent_world = self.world(parent).unwrap_or_default();
+        self.set_local(node, world.relative_to(&parent_world).unwrap_or(world));
+    }
+
+    /// Returns the world transform of a node, updating it if needed.
+    pub fn world(&mut self, node: SlotKey) -> Option<Transform2D> {
+        self.update_node(node)?;
+        return Some(self.nodes[node].world);
+    }
+
+    /// Returns the world matrix of a node, updating it if needed.
+    pub fn world_mat(&mut self, node: SlotKey) -> Option<mat3> {
+        self.update_node(node)?;
+        return Some(self.nodes[node].world_mat);
+    }
+
+    /// Returns the cached world transform of a node, which may be outdated. Call `update` first to make sure it isn't.
+    pub fn cached_world(&self, node: SlotKey) -> Option<Transform2D> {
+        self.nodes.get(node).map(|x| x.world)
+    }
+
+    /// Recomputes every dirty world transform.
+    pub fn update(&mut self) {
+        let roots = self.nodes.iter().filter(|(_, n)| n.parent.is_null()).map(|(k, _)| k).collect::<Vec<_>>();
+
+        let mut stack = roots.into_iter().map(|x| (x, Transform2D::IDENTITY, false)).collect::<Vec<_>>();
+        while let Some((key, parent_world, parent_changed)) = stack.pop() {
+            let n = &mut self.nodes[key];
+            let changed = n.dirty || parent_changed;
+            if changed {
+                n.world = parent_world.compose(&n.local);
+                n.world_mat = n.world.to_mat3();
+                n.dirty = false;
+            }
+
+            let world = n.world;
+            stack.extend(n.children.iter().map(|c| (*c, world, changed)));
+        }
+    }
+
+    fn mark_dirty(&mut self, node: SlotKey) {
+        let mut stack = vec![node];
+        while let Some(key) = stack.pop() {
+            let Some(n) = self.nodes.get_mut(key) else { continue };
+            if n.dirty && key != node {
+                continue; // Descendants are already dirty
+            }
+
+            n.dirty = true;
+            stack.extend_from_slice(&n.children);
+        }
+    }
+
+    /// Updates a node and its dirty ancestors.
+    fn update_node(&mut self, node: SlotKey) -> Option<()> {
+        if !self.nodes.get(node)?.dirty {
+            return Some(());
+        }
+
+        // Topmost dirty ancestor, everything below it is dirty too
+        let mut chain = vec![node];
+        loop {
+            let parent = self.nodes[*chain.last().unwrap()].parent;
+            match self.nodes.get(parent) {
+                Some(p) if p.dirty => chain.push(parent),
+                _ => break,
+            }
+        }
+
+        for key in chain.into_iter().rev() {
+            let parent_world = self.nodes.get(self.nodes[key].parent).map(|x| x.world).unwrap_or_default();
+            let n = &mut self.nodes[key];
+            n.world = parent_world.compose(&n.local);
+            n.world_mat = n.world.to_mat3();
+            n.dirty = false;
+        }
+        return Some(());
+    }
+}
+
+impl Default for TransformHierarchy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{math::{transform::Transform2D, vector2::vec2}, slotmap::SlotKey};
+
+    use super::TransformHierarchy;
+
+    fn approx(a: vec2, b: vec2) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4
+    }
+
+    #[test]
+    fn propagation() {
+        let mut tree = TransformHierarchy::new();
+        let tank = tree.insert(Transform2D::from_pos(vec2(10.0, 0.0)), SlotKey::NULL);
+        let turret = tree.insert(Transform2D::from_pos(vec2(0.0, 1.0)), tank);
+        let barrel = tree.insert(Transform2D::from_pos(vec2(2.0, 0.0)), turret);
+
+        assert!(approx(tree.world(barrel).unwrap().pos, vec2(12.0, 1.0)));
+
+        tree.modify_local(turret, |x| x.rot = std::f32::consts::FRAC_PI_2);
+        tree.update();
+        assert!(approx(tree.cached_world(barrel).unwrap().pos, vec2(10.0, 3.0)));
+
+        tree.set_local(tank, Transform2D::from_pos(vec2(0.0, 0.0)));
+        assert!(approx(tree.world(barrel).unwrap().pos, vec2(0.0, 3.0)));
+
+        assert!(!tree.set_parent(tank, barrel, false));
+        assert!(tree.set_parent(barrel, SlotKey::NULL, true));
+        assert!(approx(tree.world(barrel).unwrap().pos, vec2(0.0, 3.0)));
+        assert!(tree.children(turret).is_empty());
+
+        assert_eq!(tree.remove(tank), 2);
+        assert!(!tree.contains(turret));
+        assert_eq!(tree.len(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{panic::AssertUnwindSafe, sync::{mpsc::{channel, Receiver, Sender}, Arc, Condvar, Mutex, RwLock}, time::Duration};
+
+use crate::{assert_expr, crash, log_error, log_info, log_warn, main_thread::{is_main_thread, test_main_thread}};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+static POOL: RwLock<Option<JobPool>> = RwLock::new(None);
+static MAIN_THREAD_QUEUE: Mutex<Vec<Job>> = Mutex::new(Vec::new());
+
+struct JobPool {
+    sender: Sender<Job>,
+    workers: usize,
+}
+
+/// Starts the worker threads. If it is not called, the pool is started on the first `spawn` with one worker less than the available parallelism. Will panic if `workers == 0`.
+pub fn init_jobs(workers: usize) {
+    assert_expr!(workers > 0, "The job system needs at least one worker!");
+
+    let Ok(mut pool) = POOL.write() else { crash!("Couldn't access Job Pool singleton!") };
+    if pool.is_some() {
+        log_warn!("NOGINE2: Job system was already initialized");
+        return;
+    }
+
+    let (sender, receiver) = channel::<Job>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for i in 0..workers {
+        let receiver = receiver.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("nogine2-worker-{i}"))
+            .spawn(move || worker_loop(receiver));
+
+        if let Err(e) = spawned {
+            crash!("Couldn't spawn worker thread: {e}");
+        }
+    }
+
+    *pool = Some(JobPool { sender, workers });
+    log_info!("NOGINE2: Job system initialized with {workers} workers");
+}
+
+/// Returns the number of worker threads, `0` if the pool hasn't started yet.
+pub fn worker_count() -> usize {
+    let Ok(pool) = POOL.read() else { crash!("Couldn't access Job Pool singleton!") };
+    return pool.as_ref().map(|x| x.workers).unwrap_or(0);
+}
+
+/// Runs `f` in a worker thread.
+pub fn spawn<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> JobHandle<T> {
+    if worker_count() == 0 {
+        let parallelism = std::thread::available_parallelism().map(|x| x.get()).unwrap_or(2);
+        init_jobs(parallelism.saturating_sub(1).max(1));
+    }
+
+    let (handle, job) = JobHandle::wrap(f);
+
+    let Ok(pool) = POOL.read() else { crash!("Couldn't access Job Pool singleton!") };
+    let Some(pool) = pool.as_ref() else { crash!("Job system is not initialized!") };
+    if pool.sender.send(job).is_err() {
+        crash!("Couldn't send job to the worker threads!");
+    }
+    return handle;
+}
+
+/// Queues `f` to run on the main thread, during the next `run_main_thread_jobs` (called by `Window::pre_tick`).
+pub fn run_on_main_thread<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> JobHandle<T> {
+    let (handle, job) = JobHandle::wrap(f);
+
+    let Ok(mut queue) = MAIN_THREAD_QUEUE.lock() else { crash!("Couldn't access Main Thread Queue singleton!") };
+    queue.push(job);
+    return handle;
+}
+
+/// Runs every job queued for the main thread. Jobs queued while running are left for the next call.
+pub fn run_main_thread_jobs() {
+    test_main_thread();
+
+    let jobs = {
+        let Ok(mut queue) = MAIN_THREAD_QUEUE.lock() else { crash!("Couldn't access Main Thread Queue singleton!") };
+        std::mem::take(&mut *queue)
+    };
+
+    for job in jobs {
+        job();
+    }
+}
+
+fn worker_loop(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = {
+            let Ok(receiver) = receiver.lock() else { return };
+            receiver.recv()
+        };
+
+        match job {
+            Ok(job) => job(),
+            Err(_) => return, // Pool was dropped
+        }
+    }
+}
+
+
+enum JobState<T> {
+    Pending,
+    Done(T),
+    Taken,
+    Failed,
+}
+
+struct JobShared<T> {
+    state: Mutex<JobState<T>>,
+    cond: Condvar,
+}
+
+/// Handle to the result of a job.
+pub struct JobHandle<T> {
+    shared: Arc<JobShared<T>>,
+}
+
+impl<T: Send + 'static> JobHandle<T> {
+    /// Creates a handle that is already ready.
+    pub fn ready(value: T) -> Self {
+        Self { shared: Arc::new(JobShared { state: Mutex::new(JobState::Done(value)), cond: Condvar::new() }) }
+    }
+
+    /// Returns if the result is available to be taken.
+    pub fn is_ready(&self) -> bool {
+        matches!(*self.lock(), JobState::Done(_))
+    }
+
+    /// Returns if the job panicked.
+    pub fn is_failed(&self) -> bool {
+        matches!(*self.lock(), JobState::Failed)
+    }
+
+    /// Takes the result if the job is done. Will return `None` after the result has been taken.
+    pub fn poll(&self) -> Option<T> {
+        let mut state = self.lock();
+        if !matches!(*state, JobState::Done(_)) {
+            return None;
+        }
+
+        match std::mem::replace(&mut *state, JobState::Taken) {
+            JobState::Done(x) => Some(x),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Blocks until the job is done and takes the result. If called from the main thread, main thread jobs keep running while waiting. Will panic if the job panicked or the result was already taken.
+    pub fn wait(self) -> T {
+        let on_main_thread = is_main_thread();
+        let mut state = self.lock();
+        loop {
+            match std::mem::replace(&mut *state, JobState::Taken) {
+                JobState::Done(x) => return x,
+                JobState::Pending => *state = JobState::Pending,
+                JobState::Taken => crash!("Job result was already taken!"),
+                JobState::Failed => crash!("Waited for a job that panicked!"),
+            }
+
+            if on_main_thread {
+                drop(state);
+                run_main_thread_jobs();
+                state = self.lock();
+                if !matches!(*state, JobState::Pending) {
+                    continue;
+                }
+            }
+
+            state = match self.shared.cond.wait_timeout(state, Duration::from_millis(1)) {
+                Ok((x, _)) => x,
+                Err(_) => crash!("Couldn't access job state!"),
+            };
+        }
+    }
+
+    /// Returns the job to run and the handle to its result.
+    fn wrap(f: impl FnOnce() -> T + Send + 'static) -> (Self, Job) {
+        let shared = Arc::new(JobShared { state: Mutex::new(JobState::Pending), cond: Condvar::new() });
+        let job_shared = shared.clone();
+
+        let job = Box::new(move || {
+            let res = std::panic::catch_unwind(AssertUnwindSafe(f));
+            let Ok(mut state) = job_shared.state.lock() else { return };
+            *state = match res {
+                Ok(x) => JobState::Done(x),
+                Err(_) => {
+                    log_error!("A job panicked!");
+                    JobState::Failed
+                },
+            };
+            job_shared.cond.notify_all();
+        });
+
+        return (Self { shared }, job);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JobState<T>> {
+        let Ok(state) = self.shared.state.lock() else { crash!("Couldn't access job state!") };
+        return state;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{init_jobs, spawn, JobHandle};
+
+    #[test]
+    fn spawn_and_wait() {
+        init_jobs(2);
+
+        let handles = (0..8u64).map(|i| spawn(move || (0..=i).sum::<u64>())).collect::<Vec<_>>();
+        let results = handles.into_iter().map(|x| x.wait()).collect::<Vec<_>>();
+        assert_eq!(results, vec![0, 1, 3, 6, 10, 15, 21, 28]);
+    }
+
+    #[test]
+    fn poll_takes_once() {
+        let handle = JobHandle::ready(5);
+        assert!(handle.is_ready());
+        assert_eq!(handle.poll(), Some(5));
+        assert_eq!(handle.poll(), None);
+        assert!(!handle.is_ready());
+    }
+}
@@ -7,5 +7,9 @@ pub mod event;
 pub mod main_thread;
 pub mod heap;
 pub mod lazy;
+pub mod spatial;
+pub mod slotmap;
+pub mod jobs;
+pub mod hierarchy;
 
 pub use native_dialog;
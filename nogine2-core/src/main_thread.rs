@@ -14,3 +14,9 @@ pub fn test_main_thread() {
     assert_expr!(Some(std::thread::current().id()) == *main_thread, "This function may only be called from the main thread!");
 
 }
+
+/// Returns if the current thread is the main thread.
+pub fn is_main_thread() -> bool {
+    let Ok(main_thread) = MAIN_THREAD.read() else { crash!("Couldn't access Main Thread singleton!") };
+    return Some(std::thread::current().id()) == *main_thread;
+}
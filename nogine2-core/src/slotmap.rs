@@ -0,0 +1,467 @@
+use std::ops::{Index, IndexMut};
+
+/// Generational key for a `SlotMap`. Keys from removed items are never valid again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlotKey {
+    index: u32,
+    generation: u32,
+}
+
+impl SlotKey {
+    /// Key that is never valid.
+    pub const NULL: Self = Self { index: u32::MAX, generation: 0 };
+
+    /// Returns the slot index of the key.
+    pub const fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the generation of the key.
+    pub const fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub const fn is_null(&self) -> bool {
+        self.index == u32::MAX
+    }
+}
+
+impl Default for SlotKey {
+    fn default() -> Self {
+        Self::NULL
+    }
+}
+
+
+#[derive(Debug, Clone)]
+struct Slot {
+    /// Odd if occupied.
+    generation: u32,
+    /// Index in the dense arrays if occupied, next free slot otherwise.
+    next: u32,
+}
+
+impl Slot {
+    fn occupied(&self) -> bool {
+        self.generation % 2 == 1
+    }
+}
+
+/// Container with stable generational keys. Values are stored contiguously, so iteration is as fast as for a `Vec`.
+#[derive(Debug, Clone)]
+pub struct SlotMap<T> {
+    slots: Vec<Slot>,
+    free_head: u32,
+    values: Vec<T>,
+    keys: Vec<SlotKey>,
+}
+
+impl<T> SlotMap<T> {
+    const NO_FREE: u32 = u32::MAX;
+
+    /// Creates an empty `SlotMap`.
+    pub const fn new() -> Self {
+        Self { slots: Vec::new(), free_head: Self::NO_FREE, values: Vec::new(), keys: Vec::new() }
+    }
+
+    /// Creates an empty `SlotMap` with space for at least `capacity` items.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { slots: Vec::with_capacity(capacity), free_head: Self::NO_FREE, values: Vec::with_capacity(capacity), keys: Vec::with_capacity(capacity) }
+    }
+
+    /// Returns the number of items in the map.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Inserts an item and returns its key.
+    pub fn insert(&mut self, value: T) -> SlotKey {
+        return self.insert_with_key(|_| value);
+    }
+
+    /// Inserts an item built from its own key and returns the key.
+    pub fn insert_with_key(&mut self, f: impl FnOnce(SlotKey) -> T) -> SlotKey {
+        let dense = self.values.len() as u32;
+
+        let key = if self.free_head != Self::NO_FREE {
+            let index = self.free_head;
+            let slot = &mut self.slots[index as usize];
+            self.free_head = slot.next;
+            slot.generation = slot.generation.wrapping_add(1);
+            slot.next = dense;
+            SlotKey { index, generation: slot.generation }
+        } else {
+            let index = self.slots.len() as u32;
+            assert!(index != u32::MAX, "SlotMap is full!");
+            self.slots.push(Slot { generation: 1, next: dense });
+            SlotKey { index, generation: 1 }
+        };
+
+        self.values.push(f(key));
+        self.keys.push(key);
+        return key;
+    }
+
+    /// Removes an item and returns it, if the key is valid.
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let dense = self.dense_index(key)?;
+
+        let slot = &mut self.slots[key.index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.next = self.free_head;
+        self.free_head = key.index;
+
+        // Keep the dense arrays packed
+        self.keys.swap_remove(dense);
+        let value = self.values.swap_remove(dense);
+        if let Some(moved) = self.keys.get(dense) {
+            self.slots[moved.index as usize].next = dense as u32;
+        }
+        return Some(value);
+    }
+
+    /// Keeps only the items for which `f` returns `true`.
+    pub fn retain(&mut self, mut f: impl FnMut(SlotKey, &mut T) -> bool) {
+        let mut i = 0;
+        while i < self.values.len() {
+            if f(self.keys[i], &mut self.values[i]) {
+                i += 1;
+            } else {
+                self.remove(self.keys[i]);
+            }
+        }
+    }
+
+    /// Removes every item. All previous keys become invalid.
+    pub fn clear(&mut self) {
+        for key in std::mem::take(&mut self.keys) {
+            let slot = &mut self.slots[key.index as usize];
+            slot.generation = slot.generation.wrapping_add(1);
+            slot.next = self.free_head;
+            self.free_head = key.index;
+        }
+        self.values.clear();
+    }
+
+    /// Returns if the key points to an item.
+    pub fn contains(&self, key: SlotKey) -> bool {
+        self.dense_index(key).is_some()
+    }
+
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        let dense = self.dense_index(key)?;
+        return Some(&self.values[dense]);
+    }
+
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        let dense = self.dense_index(key)?;
+        return Some(&mut self.values[dense]);
+    }
+
+    /// Returns mutable references to two different items. Will return `None` if any key is invalid or both are equal.
+    pub fn get2_mut(&mut self, a: SlotKey, b: SlotKey) -> Option<(&mut T, &mut T)> {
+        let (ia, ib) = (self.dense_index(a)?, self.dense_index(b)?);
+        if ia == ib {
+            return None;
+        }
+
+        let [x, y] = self.values.get_disjoint_mut([ia, ib]).ok()?;
+        return Some((x, y));
+    }
+
+    /// Returns an iterator over every key and item. The order is not stable across removals.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.keys.iter().copied().zip(self.values.iter())
+    }
+
+    /// Returns a mutable iterator over every key and item. The order is not stable across removals.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SlotKey, &mut T)> {
+        self.keys.iter().copied().zip(self.values.iter_mut())
+    }
+
+    /// Returns every key as a contiguous slice, in the same order as `values`.
+    pub fn keys(&self) -> &[SlotKey] {
+        &self.keys
+    }
+
+    /// Returns every item as a contiguous slice.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Returns every item as a contiguous mutable slice.
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    fn dense_index(&self, key: SlotKey) -> Option<usize> {
+        let slot = self.slots.get(key.index as usize)?;
+        if !slot.occupied() || slot.generation != key.generation {
+            return None;
+        }
+        return Some(slot.next as usize);
+    }
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<SlotKey> for SlotMap<T> {
+    type Output = T;
+
+    /// Will panic if the key is not valid.
+    fn index(&self, key: SlotKey) -> &Self::Output {
+        match self.get(key) {
+            Some(x) => x,
+            None => panic!("Invalid SlotMap key {key:?}!"),
+        }
+    }
+}
+
+impl<T> IndexMut<SlotKey> for SlotMap<T> {
+    /// Will panic if the key is not valid.
+    fn index_mut(&mut self, key: SlotKey) -> &mut Self::Output {
+        match self.get_mut(key) {
+            Some(x) => x,
+            None => panic!("Invalid SlotMap key {key:?}!"),
+        }
+    }
+}
+
+
+/// Associates extra data to the keys of a `SlotMap`. Entries with outdated keys are treated as missing.
+#[derive(Debug, Clone)]
+pub struct SecondaryMap<T> {
+    slots: Vec<Option<(u32, T)>>,
+    len: usize,
+}
+
+impl<T> SecondaryMap<T> {
+    /// Creates an empty `SecondaryMap`.
+    pub const fn new() -> Self {
+        Self { slots: Vec::new(), len: 0 }
+    }
+
+    /// Returns the number of entries, outdated ones included.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts a value for `key`, returning the previous one if it belonged to the same key. Will panic if `key` is null.
+    pub fn insert(&mut self, key: SlotKey, value: T) -> Option<T> {
+        assert!(!key.is_null(), "Null keys can't be inserted in a SecondaryMap!");
+
+        let index = key.index as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+
+        let prev = self.slots[index].replace((key.generation, value));
+        match prev {
+            Some((generation, prev)) => (generation == key.generation).then_some(prev),
+            None => {
+                self.len += 1;
+                None
+            },
+        }
+    }
+
+    /// Removes the value for `key`, if there is one.
+    pub fn remove(&mut self, key: SlotKey) -> Option<T> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if !matches!(slot, Some((generation, _)) if *generation == key.generation) {
+            return None;
+        }
+
+        self.len -= 1;
+        return slot.take().map(|x| x.1);
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    /// Removes the entries whose keys are no longer valid in `map`.
+    pub fn prune<U>(&mut self, map: &SlotMap<U>) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let Some((generation, _)) = slot else { continue };
+            if !map.contains(SlotKey { index: i as u32, generation: *generation }) {
+                *slot = None;
+                self.len -= 1;
+            }
+        }
+    }
+
+    pub fn contains(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: SlotKey) -> Option<&T> {
+        match self.slots.get(key.index as usize)? {
+            Some((generation, value)) if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: SlotKey) -> Option<&mut T> {
+        match self.slots.get_mut(key.index as usize)? {
+            Some((generation, value)) if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over every entry, outdated ones included.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotKey, &T)> {
+        self.slots.iter().enumerate().filter_map(|(i, x)| {
+            let (generation, value) = x.as_ref()?;
+            Some((SlotKey { index: i as u32, generation: *generation }, value))
+        })
+    }
+
+    /// Returns a mutable iterator over every entry, outdated ones included.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (SlotKey, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(i, x)| {
+            let (generation, value) = x.as_mut()?;
+            Some((SlotKey { index: i as u32, generation: *generation }, value))
+        })
+    }
+}
+
+impl<T> Default for SecondaryMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::RwLock;
+
+    use super::{SecondaryMap, SlotKey, SlotMap};
+
+    static STATIC_MAP: RwLock<SlotMap<&'static str>> = RwLock::new(SlotMap::new());
+
+    #[test]
+    fn insert_get_remove() {
+        let mut map = SlotMap::new();
+        let a = map.insert("a");
+        let b = map.insert("b");
+        let c = map.insert("c");
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(a), Some(&"a"));
+        assert_eq!(map[b], "b");
+
+        assert_eq!(map.remove(a), Some("a"));
+        assert_eq!(map.remove(a), None);
+        assert_eq!(map.get(a), None);
+        assert_eq!(map.get(c), Some(&"c"));
+        assert_eq!(map.len(), 2);
+
+        *map.get_mut(c).unwrap() = "cc";
+        assert_eq!(map[c], "cc");
+    }
+
+    #[test]
+    fn stale_keys() {
+        let mut map = SlotMap::new();
+        let a = map.insert(0);
+        map.remove(a);
+
+        let b = map.insert(1);
+        assert_eq!(a.index(), b.index());
+        assert_ne!(a, b);
+        assert!(!map.contains(a));
+        assert_eq!(map.get(b), Some(&1));
+        assert!(!map.contains(SlotKey::NULL));
+
+        map.clear();
+        assert!(!map.contains(b));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn dense_iteration() {
+        let mut map = SlotMap::new();
+        let keys = (0..10).map(|i| map.insert(i)).collect::<Vec<_>>();
+        for k in keys.iter().step_by(2) {
+            map.remove(*k);
+        }
+
+        assert_eq!(map.values().len(), 5);
+        let mut values = map.iter().map(|(k, v)| {
+            assert_eq!(map[k], *v);
+            *v
+        }).collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, vec![1, 3, 5, 7, 9]);
+
+        map.retain(|_, v| *v > 4);
+        let mut values = map.values().to_vec();
+        values.sort();
+        assert_eq!(values, vec![5, 7, 9]);
+
+        for (_, v) in map.iter_mut() {
+            *v *= 2;
+        }
+        assert_eq!(map[keys[5]], 10);
+    }
+
+    #[test]
+    fn get2_mut() {
+        let mut map = SlotMap::new();
+        let a = map.insert(1);
+        let b = map.insert(2);
+
+        let (x, y) = map.get2_mut(a, b).unwrap();
+        std::mem::swap(x, y);
+        assert_eq!((map[a], map[b]), (2, 1));
+        assert!(map.get2_mut(a, a).is_none());
+    }
+
+    #[test]
+    fn secondary() {
+        let mut map = SlotMap::new();
+        let mut names = SecondaryMap::new();
+        let a = map.insert(0);
+        let b = map.insert(1);
+
+        assert_eq!(names.insert(a, "a"), None);
+        assert_eq!(names.insert(a, "aa"), Some("a"));
+        names.insert(b, "b");
+        assert_eq!(names.get(a), Some(&"aa"));
+
+        map.remove(a);
+        let c = map.insert(2);
+        assert_eq!(names.get(c), None);
+        assert_eq!(names.insert(c, "c"), None); // Replaces the outdated entry
+        assert_eq!(names.get(a), None);
+        assert_eq!(names.len(), 2);
+
+        map.remove(b);
+        names.prune(&map);
+        assert_eq!(names.len(), 1);
+        assert_eq!(names.remove(c), Some("c"));
+        assert!(names.is_empty());
+    }
+
+    #[test]
+    fn const_static() {
+        let key = STATIC_MAP.write().unwrap().insert("static");
+        assert_eq!(STATIC_MAP.read().unwrap().get(key), Some(&"static"));
+    }
+}
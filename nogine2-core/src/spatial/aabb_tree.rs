@@ -0,0 +1,478 @@
+use crate::{heap::Heap, math::{rect::Rect, vector2::vec2}};
+
+use super::{point_rect_sqr_dist, ray_rect, rect_contains, rect_cost, rect_overlaps, rect_union};
+
+const NULL: u32 = u32::MAX;
+
+/// Handle to an item inside an `AabbTree`. Handles of removed items are never valid again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TreeHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// Dynamic bounding volume hierarchy. Leaves are stored with a fattened rect, so small movements don't restructure the tree. Best fit for items of very different sizes or sparse worlds.
+#[derive(Debug, Clone)]
+pub struct AabbTree<T> {
+    nodes: Vec<Node<T>>,
+    root: u32,
+    free: Vec<u32>,
+    margin: f32,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    /// Fat rect for leaves, union of children for branches.
+    bounds: Rect,
+    parent: u32,
+    children: [u32; 2],
+    height: i32,
+    leaf: Option<Leaf<T>>,
+    /// Increased every time the node is freed, so stale handles can be detected.
+    generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Leaf<T> {
+    rect: Rect,
+    data: T,
+}
+
+impl<T> Node<T> {
+    fn is_leaf(&self) -> bool {
+        self.children[0] == NULL
+    }
+}
+
+impl<T> AabbTree<T> {
+    /// Creates an empty tree. `margin` is how much leaves are fattened in every direction.
+    pub const fn new(margin: f32) -> Self {
+        Self { nodes: Vec::new(), root: NULL, free: Vec::new(), margin, len: 0 }
+    }
+
+    /// Returns the number of items in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the height of the tree, `0` if it's empty.
+    pub fn height(&self) -> i32 {
+        if self.root == NULL { 0 } else { self.nodes[self.root as usize].height + 1 }
+    }
+
+    /// Removes every item from the tree. Nodes are kept, so handles from before stay invalid.
+    pub fn clear(&mut self) {
+        for node in &mut self.nodes {
+            node.leaf = None;
+            node.generation = node.generation.wrapping_add(1);
+        }
+        self.free = (0..self.nodes.len() as u32).rev().collect();
+        self.root = NULL;
+        self.len = 0;
+    }
+
+    /// Inserts an item covering `rect` and returns its handle.
+    pub fn insert(&mut self, rect: Rect, data: T) -> TreeHandle {
+        let leaf = self.alloc(Node {
+            bounds: self.fatten(rect),
+            parent: NULL,
+            children: [NULL; 2],
+            height: 0,
+            leaf: Some(Leaf { rect, data }),
+            generation: 0,
+        });
+
+        self.insert_leaf(leaf);
+        self.len += 1;
+        return self.handle(leaf);
+    }
+
+    /// Removes an item and returns its data, if the handle is still valid.
+    pub fn remove(&mut self, handle: TreeHandle) -> Option<T> {
+        if !self.is_valid(handle) {
+            return None;
+        }
+
+        self.remove_leaf(handle.index);
+        let leaf = self.nodes[handle.index as usize].leaf.take();
+        self.free(handle.index);
+        self.len -= 1;
+        return leaf.map(|x| x.data);
+    }
+
+    /// Moves an item to a new `rect`. The tree is only restructured if `rect` leaves the fattened bounds. Returns `false` if the handle is not valid.
+    pub fn set_rect(&mut self, handle: TreeHandle, rect: Rect) -> bool {
+        if !self.is_valid(handle) {
+            return false;
+        }
+
+        let node = &mut self.nodes[handle.index as usize];
+        node.leaf.as_mut().unwrap().rect = rect;
+        if rect_contains(node.bounds, rect) {
+            return true;
+        }
+
+        self.remove_leaf(handle.index);
+        self.nodes[handle.index as usize].bounds = self.fatten(rect);
+        self.insert_leaf(handle.index);
+        return true;
+    }
+
+    pub fn rect(&self, handle: TreeHandle) -> Option<Rect> {
+        self.leaf(handle).map(|x| x.rect)
+    }
+
+    pub fn get(&self, handle: TreeHandle) -> Option<&T> {
+        self.leaf(handle).map(|x| &x.data)
+    }
+
+    pub fn get_mut(&mut self, handle: TreeHandle) -> Option<&mut T> {
+        if !self.is_valid(handle) {
+            return None;
+        }
+        return self.nodes[handle.index as usize].leaf.as_mut().map(|x| &mut x.data);
+    }
+
+    /// Returns an iterator over every item in the tree.
+    pub fn iter(&self) -> impl Iterator<Item = (TreeHandle, &T)> {
+        self.nodes.iter().enumerate().filter_map(|(i, x)| Some((TreeHandle { index: i as u32, generation: x.generation }, &x.leaf.as_ref()?.data)))
+    }
+
+    /// Calls `f` for every item overlapping `rect`.
+    pub fn query_with(&self, rect: Rect, mut f: impl FnMut(TreeHandle, &T)) {
+        if self.root == NULL {
+            return;
+        }
+
+        let mut stack = vec![self.root];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i as usize];
+            if !rect_overlaps(node.bounds, rect) {
+                continue;
+            }
+
+            match &node.leaf {
+                Some(leaf) => if rect_overlaps(leaf.rect, rect) {
+                    f(self.handle(i), &leaf.data);
+                },
+                None => stack.extend_from_slice(&node.children),
+            }
+        }
+    }
+
+    /// Returns the handles of every item overlapping `rect`.
+    pub fn query(&self, rect: Rect) -> Vec<TreeHandle> {
+        let mut res = Vec::new();
+        self.query_with(rect, |h, _| res.push(h));
+        return res;
+    }
+
+    /// Returns every item hit by the ray from `origin` towards `dir` (in `dir` units, up to `max_t`), sorted by distance.
+    pub fn raycast(&self, origin: vec2, dir: vec2, max_t: f32) -> Vec<(TreeHandle, f32)> {
+        let mut res = Vec::new();
+        if self.root == NULL {
+            return res;
+        }
+
+        let mut stack = vec![self.root];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i as usize];
+            if ray_rect(origin, dir, max_t, node.bounds).is_none() {
+                continue;
+            }
+
+            match &node.leaf {
+                Some(leaf) => if let Some(t) = ray_rect(origin, dir, max_t, leaf.rect) {
+                    res.push((self.handle(i), t));
+                },
+                None => stack.extend_from_slice(&node.children),
+            }
+        }
+
+        res.sort_by(|a, b| a.1.total_cmp(&b.1));
+        return res;
+    }
+
+    /// Returns the `k` items closest to `point` with their distances, sorted by distance.
+    pub fn nearest(&self, point: vec2, k: usize) -> Vec<(TreeHandle, f32)> {
+        let mut res = Vec::with_capacity(k);
+        if self.root == NULL || k == 0 {
+            return res;
+        }
+
+        // Best-first search. Branch distances are lower bounds, so a leaf popped from the heap is
+        // always closer than anything left unexplored.
+        let mut heap = Heap::new();
+        heap.push((point_rect_sqr_dist(point, self.nodes[self.root as usize].bounds), self.root, false));
+        while let Some((sqr_dist, i, exact)) = heap.pop() {
+            let node = &self.nodes[i as usize];
+            match &node.leaf {
+                Some(_) if exact => {
+                    res.push((self.handle(i), sqr_dist.sqrt()));
+                    if res.len() == k {
+                        break;
+                    }
+                },
+                Some(leaf) => heap.push((point_rect_sqr_dist(point, leaf.rect), i, true)),
+                None => for c in node.children {
+                    heap.push((point_rect_sqr_dist(point, self.nodes[c as usize].bounds), c, false));
+                },
+            }
+        }
+        return res;
+    }
+
+    fn is_valid(&self, handle: TreeHandle) -> bool {
+        self.nodes.get(handle.index as usize).map(|x| x.leaf.is_some() && x.generation == handle.generation).unwrap_or(false)
+    }
+
+    fn leaf(&self, handle: TreeHandle) -> Option<&Leaf<T>> {
+        if !self.is_valid(handle) {
+            return None;
+        }
+        return self.nodes[handle.index as usize].leaf.as_ref();
+    }
+
+    fn fatten(&self, rect: Rect) -> Rect {
+        Rect { start: rect.start - vec2::one(self.margin), end: rect.end + vec2::one(self.margin) }
+    }
+
+    fn handle(&self, index: u32) -> TreeHandle {
+        TreeHandle { index, generation: self.nodes[index as usize].generation }
+    }
+
+    /// Allocates a node, keeping the generation of reused ones.
+    fn alloc(&mut self, node: Node<T>) -> u32 {
+        match self.free.pop() {
+            Some(i) => {
+                let generation = self.nodes[i as usize].generation;
+                self.nodes[i as usize] = Node { generation, ..node };
+                i
+            },
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            },
+        }
+    }
+
+    fn free(&mut self, index: u32) {
+        let node = &mut self.nodes[index as usize];
+        node.generation = node.generation.wrapping_add(1);
+        self.free.push(index);
+    }
+
+    fn insert_leaf(&mut self, leaf: u32) {
+        if self.root == NULL {
+            self.root = leaf;
+            self.nodes[leaf as usize].parent = NULL;
+            return;
+        }
+
+        // Find the best sibling by descending with the perimeter heuristic
+        let leaf_bounds = self.nodes[leaf as usize].bounds;
+        let mut index = self.root;
+        while !self.nodes[index as usize].is_leaf() {
+            let node = &self.nodes[index as usize];
+            let cost = rect_cost(node.bounds);
+            let combined_cost = rect_cost(rect_union(node.bounds, leaf_bounds));
+
+            // Cost of creating a new parent here and pushing the leaf down from above
+            let this_cost = 2.0 * combined_cost;
+            let inheritance_cost = 2.0 * (combined_cost - cost);
+
+            let child_cost = |c: u32| {
+                let child = &self.nodes[c as usize];
+                let union = rect_cost(rect_union(child.bounds, leaf_bounds));
+                if child.is_leaf() { union + inheritance_cost } else { union - rect_cost(child.bounds) + inheritance_cost }
+            };
+
+            let [c0, c1] = node.children;
+            let (cost0, cost1) = (child_cost(c0), child_cost(c1));
+            if this_cost < cost0 && this_cost < cost1 {
+                break;
+            }
+            index = if cost0 < cost1 { c0 } else { c1 };
+        }
+
+        let sibling = index;
+        let old_parent = self.nodes[sibling as usize].parent;
+        let new_parent = self.alloc(Node {
+            bounds: rect_union(leaf_bounds, self.nodes[sibling as usize].bounds),
+            parent: old_parent,
+            children: [sibling, leaf],
+            height: self.nodes[sibling as usize].height + 1,
+            leaf: None,
+            generation: 0,
+        });
+        self.nodes[sibling as usize].parent = new_parent;
+        self.nodes[leaf as usize].parent = new_parent;
+
+        if old_parent == NULL {
+            self.root = new_parent;
+        } else {
+            let children = &mut self.nodes[old_parent as usize].children;
+            if children[0] == sibling { children[0] = new_parent } else { children[1] = new_parent }
+        }
+
+        self.refit_from(self.nodes[leaf as usize].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: u32) {
+        if leaf == self.root {
+            self.root = NULL;
+            return;
+        }
+
+        let parent = self.nodes[leaf as usize].parent;
+        let grand_parent = self.nodes[parent as usize].parent;
+        let [c0, c1] = self.nodes[parent as usize].children;
+        let sibling = if c0 == leaf { c1 } else { c0 };
+
+        if grand_parent == NULL {
+            self.root = sibling;
+            self.nodes[sibling as usize].parent = NULL;
+        } else {
+            let children = &mut self.nodes[grand_parent as usize].children;
+            if children[0] == parent { children[0] = sibling } else { children[1] = sibling }
+            self.nodes[sibling as usize].parent = grand_parent;
+            self.refit_from(grand_parent);
+        }
+
+        self.free(parent);
+    }
+
+    /// Walks up the tree from `index` rebalancing and updating bounds and heights.
+    fn refit_from(&mut self, mut index: u32) {
+        while index != NULL {
+            index = self.balance(index);
+
+            let [c0, c1] = self.nodes[index as usize].children;
+            let (b0, b1) = (self.nodes[c0 as usize].bounds, self.nodes[c1 as usize].bounds);
+            let height = 1 + self.nodes[c0 as usize].height.max(self.nodes[c1 as usize].height);
+
+            let node = &mut self.nodes[index as usize];
+            node.bounds = rect_union(b0, b1);
+            node.height = height;
+            index = node.parent;
+        }
+    }
+
+    /// Performs a tree rotation if `a` is imbalanced. Returns the index of the new subtree root.
+    fn balance(&mut self, a: u32) -> u32 {
+        if self.nodes[a as usize].is_leaf() || self.nodes[a as usize].height < 2 {
+            return a;
+        }
+
+        let [b, c] = self.nodes[a as usize].children;
+        let diff = self.nodes[c as usize].height - self.nodes[b as usize].height;
+
+        if diff > 1 {
+            return self.rotate(a, c);
+        }
+        if diff < -1 {
+            return self.rotate(a, b);
+        }
+        return a;
+    }
+
+    /// Promotes `high` (child of `a`) above `a`.
+    fn rotate(&mut self, a: u32, high: u32) -> u32 {
+        let [f, g] = self.nodes[high as usize].children;
+
+        // `high` takes the place of `a`
+        let a_parent = self.nodes[a as usize].parent;
+        self.nodes[high as usize].parent = a_parent;
+        self.nodes[a as usize].parent = high;
+        if a_parent == NULL {
+            self.root = high;
+        } else {
+            let children = &mut self.nodes[a_parent as usize].children;
+            if children[0] == a { children[0] = high } else { children[1] = high }
+        }
+
+        // The taller grandchild stays with `high`, the other one moves to `a`
+        let (keep, give) = if self.nodes[f as usize].height > self.nodes[g as usize].height { (f, g) } else { (g, f) };
+        self.nodes[high as usize].children = [a, keep];
+        {
+            let children = &mut self.nodes[a as usize].children;
+            if children[0] == high { children[0] = give } else { children[1] = give }
+        }
+        self.nodes[give as usize].parent = a;
+
+        for i in [a, high] {
+            let [c0, c1] = self.nodes[i as usize].children;
+            let bounds = rect_union(self.nodes[c0 as usize].bounds, self.nodes[c1 as usize].bounds);
+            let height = 1 + self.nodes[c0 as usize].height.max(self.nodes[c1 as usize].height);
+            self.nodes[i as usize].bounds = bounds;
+            self.nodes[i as usize].height = height;
+        }
+
+        return high;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::{rect::Rect, vector2::vec2};
+
+    use super::AabbTree;
+
+    fn square(x: f32, y: f32, size: f32) -> Rect {
+        Rect { start: vec2(x, y), end: vec2(x + size, y + size) }
+    }
+
+    #[test]
+    fn query_move_remove() {
+        let mut tree = AabbTree::new(0.1);
+        let handles = (0..64).map(|i| tree.insert(square(i as f32 * 2.0, 0.0, 1.0), i)).collect::<Vec<_>>();
+        assert!(tree.height() <= 8);
+
+        let mut hits = tree.query(square(9.5, 0.5, 3.0));
+        hits.sort();
+        assert_eq!(hits, vec![handles[5], handles[6]]);
+
+        tree.set_rect(handles[0], square(100.0, 100.0, 1.0));
+        assert_eq!(tree.query(square(99.0, 99.0, 5.0)), vec![handles[0]]);
+        assert!(tree.query(square(-1.0, -1.0, 1.5)).is_empty());
+
+        assert_eq!(tree.remove(handles[6]), Some(6));
+        assert_eq!(tree.remove(handles[6]), None);
+        assert_eq!(tree.query(square(9.5, 0.5, 3.0)), vec![handles[5]]);
+        assert_eq!(tree.len(), 63);
+
+        // The slot is reused, but the old handle stays invalid
+        let reused = tree.insert(square(0.0, 10.0, 1.0), 64);
+        assert_eq!(tree.get(handles[6]), None);
+        assert_eq!(tree.get(reused), Some(&64));
+
+        tree.clear();
+        let after_clear = tree.insert(square(0.0, 0.0, 1.0), 65);
+        assert!(handles.iter().chain([&reused]).all(|h| tree.get(*h).is_none()));
+        assert_eq!(tree.get(after_clear), Some(&65));
+
+        // Touching edges and points count as overlapping
+        assert_eq!(tree.query(square(1.0, 1.0, 1.0)), vec![after_clear]);
+        assert_eq!(tree.query(Rect { start: vec2(0.5, 0.5), end: vec2(0.5, 0.5) }), vec![after_clear]);
+    }
+
+    #[test]
+    fn raycast_and_nearest() {
+        let mut tree = AabbTree::new(0.0);
+        let a = tree.insert(square(2.0, -0.5, 1.0), 'a');
+        let b = tree.insert(square(6.0, -0.5, 1.0), 'b');
+        let c = tree.insert(square(0.0, 4.0, 1.0), 'c');
+
+        let hits = tree.raycast(vec2::ZERO, vec2(1.0, 0.0), 10.0);
+        assert_eq!(hits.iter().map(|x| x.0).collect::<Vec<_>>(), vec![a, b]);
+        assert!((hits[1].1 - 6.0).abs() < 1e-5);
+
+        let nearest = tree.nearest(vec2(0.5, 3.0), 2);
+        assert_eq!(nearest.iter().map(|x| x.0).collect::<Vec<_>>(), vec![c, a]);
+    }
+}
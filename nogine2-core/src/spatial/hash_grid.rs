@@ -0,0 +1,426 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{assert_expr, math::{rect::Rect, vector2::vec2}};
+
+use super::{point_rect_sqr_dist, ray_rect, rect_overlaps};
+
+/// Handle to an item inside a `SpatialHashGrid`. Handles of removed items are never valid again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GridHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// Uniform grid that buckets items by the cells their rect overlaps. Best fit for many similarly sized items.
+#[derive(Debug, Clone)]
+pub struct SpatialHashGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<GridHandle>>,
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    item: Option<GridItem<T>>,
+    /// Increased every time the slot is freed, so stale handles can be detected.
+    generation: u32,
+}
+
+#[derive(Debug, Clone)]
+struct GridItem<T> {
+    rect: Rect,
+    data: T,
+}
+
+impl<T> SpatialHashGrid<T> {
+    /// Creates an empty grid. Will panic if `cell_size <= 0.0`.
+    pub fn new(cell_size: f32) -> Self {
+        assert_expr!(cell_size > 0.0, "'cell_size' must be greater than 0!");
+        return Self { cell_size, cells: HashMap::new(), slots: Vec::new(), free: Vec::new(), len: 0 };
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns the number of items in the grid.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every item from the grid. Slots are kept, so handles from before stay invalid.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        for slot in self.slots.iter_mut().filter(|x| x.item.is_some()) {
+            slot.item = None;
+            slot.generation = slot.generation.wrapping_add(1);
+        }
+        self.free = (0..self.slots.len() as u32).rev().collect();
+        self.len = 0;
+    }
+
+    /// Inserts an item covering `rect` and returns its handle.
+    pub fn insert(&mut self, rect: Rect, data: T) -> GridHandle {
+        let index = match self.free.pop() {
+            Some(i) => {
+                self.slots[i as usize].item = Some(GridItem { rect, data });
+                i
+            },
+            None => {
+                self.slots.push(Slot { item: Some(GridItem { rect, data }), generation: 0 });
+                (self.slots.len() - 1) as u32
+            },
+        };
+
+        let handle = GridHandle { index, generation: self.slots[index as usize].generation };
+        self.link(handle, rect);
+        self.len += 1;
+        return handle;
+    }
+
+    /// Removes an item and returns its data, if the handle is still valid.
+    pub fn remove(&mut self, handle: GridHandle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize).filter(|x| x.generation == handle.generation)?;
+        let item = slot.item.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+
+        self.unlink(handle, item.rect);
+        return Some(item.data);
+    }
+
+    /// Moves an item to a new `rect`. Returns `false` if the handle is not valid.
+    pub fn set_rect(&mut self, handle: GridHandle, rect: Rect) -> bool {
+        let Some(item) = self.item_mut(handle) else {
+            return false;
+        };
+
+        let old = item.rect;
+        item.rect = rect;
+        if self.cell_range(old) != self.cell_range(rect) {
+            self.unlink(handle, old);
+            self.link(handle, rect);
+        }
+        return true;
+    }
+
+    pub fn rect(&self, handle: GridHandle) -> Option<Rect> {
+        self.item(handle).map(|x| x.rect)
+    }
+
+    pub fn get(&self, handle: GridHandle) -> Option<&T> {
+        self.item(handle).map(|x| &x.data)
+    }
+
+    pub fn get_mut(&mut self, handle: GridHandle) -> Option<&mut T> {
+        self.item_mut(handle).map(|x| &mut x.data)
+    }
+
+    /// Returns an iterator over every item in the grid.
+    pub fn iter(&self) -> impl Iterator<Item = (GridHandle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(i, x)| Some((GridHandle { index: i as u32, generation: x.generation }, &x.item.as_ref()?.data)))
+    }
+
+    /// Appends to `out` the handles of every item overlapping `rect`, without duplicates.
+    pub fn query_into(&self, rect: Rect, out: &mut Vec<GridHandle>) {
+        let first = out.len();
+        let ((x0, y0), (x1, y1)) = self.cell_range(rect);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let Some(cell) = self.cells.get(&(x, y)) else { continue };
+                for h in cell {
+                    if rect_overlaps(self.rect_of(*h), rect) {
+                        out.push(*h);
+                    }
+                }
+            }
+        }
+
+        out[first..].sort_unstable();
+        let mut unique = first;
+        for i in first..out.len() {
+            if i == first || out[i] != out[unique - 1] {
+                out[unique] = out[i];
+                unique += 1;
+            }
+        }
+        out.truncate(unique);
+    }
+
+    /// Returns the handles of every item overlapping `rect`.
+    pub fn query(&self, rect: Rect) -> Vec<GridHandle> {
+        let mut res = Vec::new();
+        self.query_into(rect, &mut res);
+        return res;
+    }
+
+    /// Returns the handles of every item containing `point`.
+    pub fn query_point(&self, point: vec2) -> Vec<GridHandle> {
+        self.query(Rect { start: point, end: point })
+    }
+
+    /// Returns every item hit by the ray from `origin` towards `dir` (in `dir` units, up to `max_t`), sorted by distance.
+    pub fn raycast(&self, origin: vec2, dir: vec2, max_t: f32) -> Vec<(GridHandle, f32)> {
+        let mut res: Vec<(GridHandle, f32)> = Vec::new();
+        if self.is_empty() || dir == vec2::ZERO {
+            return res;
+        }
+
+        // Amanatides & Woo grid traversal
+        let mut cell = self.cell_of(origin);
+        let sign = |x: f32| if x > 0.0 { 1 } else if x < 0.0 { -1 } else { 0 };
+        let step = (sign(dir.0), sign(dir.1));
+        let next_boundary = |c: i32, s: i32| (c + if s > 0 { 1 } else { 0 }) as f32 * self.cell_size;
+        let mut t_max = (
+            if step.0 != 0 { (next_boundary(cell.0, step.0) - origin.0) / dir.0 } else { f32::INFINITY },
+            if step.1 != 0 { (next_boundary(cell.1, step.1) - origin.1) / dir.1 } else { f32::INFINITY },
+        );
+        let t_delta = (
+            if step.0 != 0 { self.cell_size / dir.0.abs() } else { f32::INFINITY },
+            if step.1 != 0 { self.cell_size / dir.1.abs() } else { f32::INFINITY },
+        );
+
+        let bounds = self.occupied_bounds();
+        let mut visited = HashSet::new();
+        let mut t = 0.0;
+        while t <= max_t && in_bounds_along(cell, step, bounds) {
+            if let Some(items) = self.cells.get(&cell) {
+                for h in items {
+                    if !visited.insert(*h) {
+                        continue;
+                    }
+                    if let Some(hit) = ray_rect(origin, dir, max_t, self.rect_of(*h)) {
+                        res.push((*h, hit));
+                    }
+                }
+            }
+
+            if t_max.0 < t_max.1 {
+                t = t_max.0;
+                t_max.0 += t_delta.0;
+                cell.0 += step.0;
+            } else {
+                t = t_max.1;
+                t_max.1 += t_delta.1;
+                cell.1 += step.1;
+            }
+        }
+
+        res.sort_by(|a, b| a.1.total_cmp(&b.1));
+        return res;
+    }
+
+    /// Returns the `k` items closest to `point` with their distances, sorted by distance.
+    pub fn nearest(&self, point: vec2, k: usize) -> Vec<(GridHandle, f32)> {
+        let mut res: Vec<(GridHandle, f32)> = Vec::new();
+        if k == 0 || self.is_empty() {
+            return res;
+        }
+
+        // Rings are walked in i64, cells of far away points saturate to the i32 limits
+        let center = self.cell_of(point);
+        let center = (center.0 as i64, center.1 as i64);
+        let ((bx0, by0), (bx1, by1)) = self.occupied_bounds();
+        let (bx0, by0, bx1, by1) = (bx0 as i64, by0 as i64, bx1 as i64, by1 as i64);
+
+        // Rings closer than the occupied bounds are empty
+        let first_ring = (bx0 - center.0).max(center.0 - bx1).max(by0 - center.1).max(center.1 - by1).max(0);
+        let last_ring = (center.0 - bx0).max(bx1 - center.0).max(center.1 - by0).max(by1 - center.1);
+
+        let mut visited = HashSet::new();
+        for ring in first_ring..=last_ring {
+            let mut visit = |cell: &Vec<GridHandle>| {
+                for h in cell {
+                    if visited.insert(*h) {
+                        res.push((*h, point_rect_sqr_dist(point, self.rect_of(*h)).sqrt()));
+                    }
+                }
+            };
+
+            // Big rings are cheaper to find by scanning the occupied cells
+            let (x0, x1) = ((center.0 - ring).max(bx0), (center.0 + ring).min(bx1));
+            let (y0, y1) = ((center.1 - ring).max(by0), (center.1 + ring).min(by1));
+            if (x1 - x0 + 1) * 2 + (y1 - y0 + 1) * 2 > self.cells.len() as i64 {
+                for (cell, items) in &self.cells {
+                    let dist = (cell.0 as i64 - center.0).abs().max((cell.1 as i64 - center.1).abs());
+                    if dist == ring {
+                        visit(items);
+                    }
+                }
+            } else {
+                for y in y0..=y1 {
+                    let mut check = |x: i64| if let Some(items) = self.cells.get(&(x as i32, y as i32)) { visit(items) };
+                    if (y - center.1).abs() == ring {
+                        (x0..=x1).for_each(&mut check);
+                    } else {
+                        [center.0 - ring, center.0 + ring].into_iter().filter(|x| (x0..=x1).contains(x)).for_each(&mut check);
+                    }
+                }
+            }
+
+            // Everything not visited yet is at least `ring` cells away
+            if res.len() >= k {
+                res.sort_by(|a, b| a.1.total_cmp(&b.1));
+                if res[k - 1].1 <= ring as f32 * self.cell_size {
+                    break;
+                }
+            }
+        }
+
+        res.sort_by(|a, b| a.1.total_cmp(&b.1));
+        res.truncate(k);
+        return res;
+    }
+
+    fn item(&self, handle: GridHandle) -> Option<&GridItem<T>> {
+        self.slots.get(handle.index as usize).filter(|x| x.generation == handle.generation)?.item.as_ref()
+    }
+
+    fn item_mut(&mut self, handle: GridHandle) -> Option<&mut GridItem<T>> {
+        self.slots.get_mut(handle.index as usize).filter(|x| x.generation == handle.generation)?.item.as_mut()
+    }
+
+    /// Rect of a linked handle, which is always valid.
+    fn rect_of(&self, handle: GridHandle) -> Rect {
+        self.slots[handle.index as usize].item.as_ref().unwrap().rect
+    }
+
+    fn link(&mut self, handle: GridHandle, rect: Rect) {
+        let ((x0, y0), (x1, y1)) = self.cell_range(rect);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                self.cells.entry((x, y)).or_default().push(handle);
+            }
+        }
+    }
+
+    fn unlink(&mut self, handle: GridHandle, rect: Rect) {
+        let ((x0, y0), (x1, y1)) = self.cell_range(rect);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let Some(cell) = self.cells.get_mut(&(x, y)) else { continue };
+                if let Some(i) = cell.iter().position(|h| *h == handle) {
+                    cell.swap_remove(i);
+                }
+                if cell.is_empty() {
+                    self.cells.remove(&(x, y));
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, point: vec2) -> (i32, i32) {
+        ((point.0 / self.cell_size).floor() as i32, (point.1 / self.cell_size).floor() as i32)
+    }
+
+    fn cell_range(&self, rect: Rect) -> ((i32, i32), (i32, i32)) {
+        (self.cell_of(rect.start), self.cell_of(rect.end))
+    }
+
+    fn occupied_bounds(&self) -> ((i32, i32), (i32, i32)) {
+        let mut min = (i32::MAX, i32::MAX);
+        let mut max = (i32::MIN, i32::MIN);
+        for (x, y) in self.cells.keys() {
+            min = (min.0.min(*x), min.1.min(*y));
+            max = (max.0.max(*x), max.1.max(*y));
+        }
+        return (min, max);
+    }
+}
+
+/// Returns if a ray walking with `step` from `cell` may still reach the occupied `bounds`.
+fn in_bounds_along(cell: (i32, i32), step: (i32, i32), bounds: ((i32, i32), (i32, i32))) -> bool {
+    let ((x0, y0), (x1, y1)) = bounds;
+    let x_ok = match step.0 {
+        s if s > 0 => cell.0 <= x1,
+        s if s < 0 => cell.0 >= x0,
+        _ => cell.0 >= x0 && cell.0 <= x1,
+    };
+    let y_ok = match step.1 {
+        s if s > 0 => cell.1 <= y1,
+        s if s < 0 => cell.1 >= y0,
+        _ => cell.1 >= y0 && cell.1 <= y1,
+    };
+    return x_ok && y_ok;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::{rect::Rect, vector2::vec2};
+
+    use super::SpatialHashGrid;
+
+    fn square(x: f32, y: f32, size: f32) -> Rect {
+        Rect { start: vec2(x, y), end: vec2(x + size, y + size) }
+    }
+
+    #[test]
+    fn query_and_move() {
+        let mut grid = SpatialHashGrid::new(1.0);
+        let a = grid.insert(square(0.0, 0.0, 0.5), 'a');
+        let b = grid.insert(square(3.0, 3.0, 2.5), 'b');
+
+        assert_eq!(grid.query(square(-1.0, -1.0, 2.0)), vec![a]);
+        assert_eq!(grid.query(square(4.0, 4.0, 0.5)), vec![b]);
+
+        grid.set_rect(a, square(4.5, 4.5, 0.2));
+        assert_eq!(grid.query(square(4.0, 4.0, 1.0)), vec![a, b]);
+
+        assert_eq!(grid.remove(b), Some('b'));
+        assert_eq!(grid.remove(b), None);
+        assert_eq!(grid.query(square(4.0, 4.0, 1.0)), vec![a]);
+    }
+
+    #[test]
+    fn raycast_and_nearest() {
+        let mut grid = SpatialHashGrid::new(2.0);
+        let near = grid.insert(square(3.0, -0.5, 1.0), 0);
+        let far = grid.insert(square(8.0, -0.5, 1.0), 1);
+        let _off = grid.insert(square(5.0, 5.0, 1.0), 2);
+
+        let hits = grid.raycast(vec2::ZERO, vec2(1.0, 0.0), 100.0);
+        assert_eq!(hits.iter().map(|x| x.0).collect::<Vec<_>>(), vec![near, far]);
+        assert!((hits[0].1 - 3.0).abs() < 1e-5);
+
+        let nearest = grid.nearest(vec2(9.5, 0.0), 2);
+        assert_eq!(nearest.iter().map(|x| x.0).collect::<Vec<_>>(), vec![far, near]);
+    }
+
+    #[test]
+    fn far_queries_and_stale_handles() {
+        let mut grid = SpatialHashGrid::new(1.0);
+        let a = grid.insert(square(0.0, 0.0, 0.5), 'a');
+
+        // Starts at the ring touching the occupied cells
+        let nearest = grid.nearest(vec2(20000.0, -1e30), 1);
+        assert_eq!(nearest[0].0, a);
+
+        assert_eq!(grid.remove(a), Some('a'));
+        let b = grid.insert(square(0.0, 0.0, 0.5), 'b');
+        assert_eq!(grid.get(a), None);
+        assert_eq!(grid.get(b), Some(&'b'));
+
+        grid.clear();
+        let c = grid.insert(square(0.0, 0.0, 0.5), 'c');
+        assert_eq!(grid.get(b), None);
+        assert_eq!(grid.get(c), Some(&'c'));
+    }
+
+    #[test]
+    fn points_and_edges() {
+        let mut grid = SpatialHashGrid::new(1.0);
+        let a = grid.insert(square(0.5, 0.5, 2.0), 'a');
+
+        assert_eq!(grid.query_point(vec2(1.0, 1.5)), vec![a]);
+        assert_eq!(grid.query_point(vec2(2.5, 2.5)), vec![a]);
+        assert_eq!(grid.query(square(2.5, 0.0, 1.0)), vec![a]);
+        assert!(grid.query_point(vec2(3.0, 1.0)).is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::math::{rect::Rect, vector2::vec2};
+
+pub mod hash_grid;
+pub mod aabb_tree;
+
+/// Smallest rect containing both `a` and `b`.
+pub(crate) fn rect_union(a: Rect, b: Rect) -> Rect {
+    return Rect { start: a.start.min(b.start), end: a.end.max(b.end) };
+}
+
+/// Returns if `inner` is completely inside `outer`.
+pub(crate) fn rect_contains(outer: Rect, inner: Rect) -> bool {
+    return outer.start.0 <= inner.start.0 && outer.start.1 <= inner.start.1
+        && inner.end.0 <= outer.end.0 && inner.end.1 <= outer.end.1;
+}
+
+/// Returns if both rects overlap, touching edges included.
+pub(crate) fn rect_overlaps(a: Rect, b: Rect) -> bool {
+    return a.start.0 <= b.end.0 && b.start.0 <= a.end.0
+        && a.start.1 <= b.end.1 && b.start.1 <= a.end.1;
+}
+
+/// Half the perimeter of a rect, used as the insertion cost heuristic.
+pub(crate) fn rect_cost(rect: Rect) -> f32 {
+    let size = rect.end - rect.start;
+    return size.0 + size.1;
+}
+
+/// Squared distance from `point` to the closest point of `rect`. `0.0` if it is inside.
+pub(crate) fn point_rect_sqr_dist(point: vec2, rect: Rect) -> f32 {
+    let dx = (rect.start.0 - point.0).max(0.0).max(point.0 - rect.end.0);
+    let dy = (rect.start.1 - point.1).max(0.0).max(point.1 - rect.end.1);
+    return dx * dx + dy * dy;
+}
+
+/// Slab test. Returns the distance along the ray (in `dir` units) at which it enters `rect`, if it does before `max_t`.
+pub(crate) fn ray_rect(origin: vec2, dir: vec2, max_t: f32, rect: Rect) -> Option<f32> {
+    let mut t_min = 0.0f32;
+    let mut t_max = max_t;
+
+    for (o, d, lo, hi) in [(origin.0, dir.0, rect.start.0, rect.end.0), (origin.1, dir.1, rect.start.1, rect.end.1)] {
+        if d.abs() < f32::EPSILON {
+            if o < lo || o > hi {
+                return None;
+            }
+            continue;
+        }
+
+        let inv = 1.0 / d;
+        let (t0, t1) = if inv >= 0.0 { ((lo - o) * inv, (hi - o) * inv) } else { ((hi - o) * inv, (lo - o) * inv) };
+        t_min = t_min.max(t0);
+        t_max = t_max.min(t1);
+        if t_min > t_max {
+            return None;
+        }
+    }
+
+    return Some(t_min);
+}
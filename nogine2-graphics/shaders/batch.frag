@@ -1,6 +1,8 @@
 #version 330 core
 
-#define MAX_TEXTURES 16
+// Generated at startup. MAX_TEXTURES, MAX_TEXTURE_ARRAYS and the cases depend on GL_MAX_TEXTURE_IMAGE_UNITS.
+#define MAX_TEXTURES $MAX_TEXTURES
+#define MAX_TEXTURE_ARRAYS $MAX_TEXTURE_ARRAYS
 
 layout(location = 0) out vec4 fCol;
 
@@ -10,26 +12,14 @@ flat in uint vTexID;
 flat in int vUserData;
 
 uniform sampler2D uTextures[MAX_TEXTURES];
+uniform sampler2DArray uTextureArrays[MAX_TEXTURE_ARRAYS];
 
+// The lower 16 bits of the texture ID select the sampler and the upper 16 bits the layer of texture arrays.
 #define TEX_CASE(x) case uint(x): fCol = texture(uTextures[x], vUV) * vTint; break
+#define TEX_ARRAY_CASE(x) case uint(MAX_TEXTURES + x): fCol = texture(uTextureArrays[x], vec3(vUV, float(vTexID >> 16u))) * vTint; break
 void main() {
-    switch (vTexID) {
-        TEX_CASE(0);
-        TEX_CASE(1);
-        TEX_CASE(2);
-        TEX_CASE(3);
-        TEX_CASE(4);
-        TEX_CASE(5);
-        TEX_CASE(6);
-        TEX_CASE(7);
-        TEX_CASE(8);
-        TEX_CASE(9);
-        TEX_CASE(10);
-        TEX_CASE(11);
-        TEX_CASE(12);
-        TEX_CASE(13);
-        TEX_CASE(14);
-        TEX_CASE(15);
+    switch (vTexID & 0xFFFFu) {
+$TEX_CASES
         default: fCol = vec4(1.0, 0.0, 1.0, 1.0); break;
     }
 }
@@ -7,6 +7,15 @@ layout(location = 3) in vec2 aUV1;
 layout(location = 4) in uint aTexID;
 layout(location = 5) in int aUserData;
 
+// Instanced quads
+layout(location = 6) in vec2 aInstOrigin;
+layout(location = 7) in vec2 aInstRight;
+layout(location = 8) in vec2 aInstUp;
+layout(location = 9) in vec2 aInstUVStart;
+layout(location = 10) in vec2 aInstUVEnd;
+layout(location = 11) in vec4 aInstTint;
+layout(location = 12) in int aInstUserData;
+
 out vec4 vTint;
 out vec2 vUV;
 out vec2 vUV1;
@@ -15,7 +24,25 @@ flat out int vUserData;
 
 uniform mat3 uViewMat;
 
+uniform bool uInstanced;
+uniform uint uInstTexID;
+
+const vec2 CORNERS[4] = vec2[4](vec2(0.0, 0.0), vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0));
+
 void main() {
+    if (uInstanced) {
+        vec2 corner = CORNERS[gl_VertexID];
+        vec2 pos = aInstOrigin + aInstRight * corner.x + aInstUp * corner.y;
+        gl_Position = vec4((uViewMat * vec3(pos, 1.0)).xy, 0.0, 1.0);
+
+        vTint = aInstTint;
+        vUV = mix(aInstUVStart, aInstUVEnd, corner);
+        vUV1 = vec2(corner.x, 1.0 - corner.y);
+        vTexID = uInstTexID;
+        vUserData = aInstUserData;
+        return;
+    }
+
     gl_Position = vec4((uViewMat * vec3(aPos, 1.0)).xy, 0.0, 1.0);
 
     vTint = aTint;
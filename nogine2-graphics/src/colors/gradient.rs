@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+
+use nogine2_core::{assert_expr, math::lerp::Lerp};
+
+use super::{rgba::RGBA32, spaces::OKLABA, Color};
+
+/// Space in which a `Gradient` interpolates between stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GradientSpace {
+    /// Interpolates the sRGB components directly.
+    #[default]
+    SRGB,
+    /// Interpolates in linear space, which avoids darkened midpoints.
+    Linear,
+    /// Interpolates in OKLab space, which is perceptually uniform.
+    OKLab,
+}
+
+/// Multi-stop color gradient. Stops are `(t, color)` pairs sorted by `t`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Cow<'static, [(f32, RGBA32)]>,
+    space: GradientSpace,
+}
+
+impl Gradient {
+    /// Creates a gradient. Stops are sorted by `t`. Will panic if `stops` is empty.
+    pub fn new(stops: impl Into<Cow<'static, [(f32, RGBA32)]>>) -> Self {
+        let mut stops = stops.into();
+        assert_expr!(!stops.is_empty(), "A gradient must have at least one stop!");
+
+        if !stops.is_sorted_by(|a, b| a.0 <= b.0) {
+            stops.to_mut().sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        return Self { stops, space: GradientSpace::SRGB };
+    }
+
+    /// Creates a gradient from static stops. `stops` must be sorted by `t`. Will panic if `stops` is empty.
+    pub const fn from_static(stops: &'static [(f32, RGBA32)]) -> Self {
+        // `assert_expr!` can't be used in const contexts, where this fails to compile instead
+        assert!(!stops.is_empty(), "A gradient must have at least one stop!");
+        return Self { stops: Cow::Borrowed(stops), space: GradientSpace::SRGB };
+    }
+
+    /// Creates a gradient between two colors, from `t = 0.0` to `t = 1.0`.
+    pub fn two(from: RGBA32, to: RGBA32) -> Self {
+        Self::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    /// Returns the same gradient interpolating in another space.
+    pub const fn with_space(mut self, space: GradientSpace) -> Self {
+        self.space = space;
+        return self;
+    }
+
+    pub fn space(&self) -> GradientSpace {
+        self.space
+    }
+
+    pub fn set_space(&mut self, space: GradientSpace) {
+        self.space = space;
+    }
+
+    pub fn stops(&self) -> &[(f32, RGBA32)] {
+        &self.stops
+    }
+
+    /// Adds a stop, keeping the stops sorted.
+    pub fn add_stop(&mut self, t: f32, color: RGBA32) {
+        let stops = self.stops.to_mut();
+        let index = stops.partition_point(|x| x.0 <= t);
+        stops.insert(index, (t, color));
+    }
+
+    /// Returns the gradient mirrored over `t = 0.5`, assuming stops in the `[0.0, 1.0]` range.
+    pub fn reversed(&self) -> Self {
+        let stops = self.stops.iter().rev().map(|(t, c)| (1.0 - t, *c)).collect::<Vec<_>>();
+        return Self { stops: Cow::Owned(stops), space: self.space };
+    }
+
+    /// Samples the gradient. Values of `t` outside the stops are clamped.
+    pub fn sample(&self, t: f32) -> RGBA32 {
+        let index = self.stops.partition_point(|x| x.0 <= t);
+        if index == 0 {
+            return self.stops[0].1;
+        }
+        if index == self.stops.len() {
+            return self.stops[index - 1].1;
+        }
+
+        let (t0, c0) = self.stops[index - 1];
+        let (t1, c1) = self.stops[index];
+        let local_t = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+
+        return match self.space {
+            GradientSpace::SRGB => c0.lerp(c1, local_t),
+            GradientSpace::Linear => c0.to_linear().lerp(c1.to_linear(), local_t).to_srgb(),
+            GradientSpace::OKLab => {
+                let (a, b) = (OKLABA::from(c0), OKLABA::from(c1));
+                RGBA32::from(OKLABA(a.0.lerp(b.0, local_t), a.1.lerp(b.1, local_t), a.2.lerp(b.2, local_t), a.3.lerp(b.3, local_t)))
+            },
+        };
+    }
+}
+
+impl From<RGBA32> for Gradient {
+    fn from(value: RGBA32) -> Self {
+        Self::new(vec![(0.0, value)])
+    }
+}
+
+macro_rules! solid_gradients_impl {
+    ($($name:ident),* $(,)?) => {
+        impl Color for Gradient {
+            $(const $name: Self = Self::from_static(&[(0.0, RGBA32::$name)]);)*
+        }
+    };
+}
+
+solid_gradients_impl!(
+    BLACK, DARK_RED, RED, DARK_GREEN, DARK_YELLOW, ORANGE, GREEN, LIME, YELLOW,
+    DARK_BLUE, DARK_MAGENTA, ROSE, DARK_CYAN, GRAY, LIGHT_RED, SPRING_GREEN, LIGHT_GREEN, LIGHT_YELLOW,
+    BLUE, VIOLET, MAGENTA, AZURE, LIGHT_BLUE, LIGHT_MAGENTA, CYAN, LIGHT_CYAN, WHITE,
+);
+
+
+#[cfg(test)]
+mod test {
+    use crate::colors::{rgba::RGBA32, Color};
+
+    use super::{Gradient, GradientSpace};
+
+    #[test]
+    fn sample() {
+        let g = Gradient::new(vec![(1.0, RGBA32::BLUE), (0.0, RGBA32::RED), (0.5, RGBA32::GREEN)]);
+        assert_eq!(g.stops()[0].1, RGBA32::RED);
+        assert_eq!(g.sample(-1.0), RGBA32::RED);
+        assert_eq!(g.sample(0.5), RGBA32::GREEN);
+        assert_eq!(g.sample(0.25), RGBA32(0.5, 0.5, 0.0, 1.0));
+        assert_eq!(g.sample(2.0), RGBA32::BLUE);
+        assert_eq!(g.reversed().sample(0.0), RGBA32::BLUE);
+
+        assert_eq!(Gradient::WHITE.sample(0.7), RGBA32::WHITE);
+        let linear = Gradient::two(RGBA32::BLACK, RGBA32::WHITE).with_space(GradientSpace::Linear);
+        assert!(linear.sample(0.5).0 > 0.5);
+    }
+}
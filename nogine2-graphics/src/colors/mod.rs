@@ -1,4 +1,6 @@
 pub mod rgba;
+pub mod spaces;
+pub mod gradient;
 
 pub trait Color {
     /// #000000
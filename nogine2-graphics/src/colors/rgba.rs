@@ -1,3 +1,7 @@
+use std::{ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign}, str::FromStr};
+
+use nogine2_core::math::lerp::Lerp;
+
 use super::Color;
 
 #[repr(C)]
@@ -38,6 +42,7 @@ impl RGBA32 {
     pub const CLEAR: Self = Self(0.0, 0.0, 0.0, 0.0);
 }
 
+/// 8 bit color. Arithmetic operators are only implemented for `RGBA32`, convert it for color math.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RGBA8(pub u8, pub u8, pub u8, pub u8);
@@ -75,3 +80,258 @@ impl Color for RGBA8 {
 impl RGBA8 {
     pub const CLEAR: Self = Self(000, 000, 000, 000);
 }
+
+impl RGBA32 {
+    /// Returns the same color with a different alpha.
+    pub const fn with_alpha(self, alpha: f32) -> Self {
+        Self(self.0, self.1, self.2, alpha)
+    }
+
+    /// Clamps every component to the `[0.0, 1.0]` range.
+    pub fn clamped(self) -> Self {
+        Self(self.0.clamp(0.0, 1.0), self.1.clamp(0.0, 1.0), self.2.clamp(0.0, 1.0), self.3.clamp(0.0, 1.0))
+    }
+
+    /// Multiplies the color components by alpha.
+    pub fn premultiplied(self) -> Self {
+        Self(self.0 * self.3, self.1 * self.3, self.2 * self.3, self.3)
+    }
+
+    /// Divides the color components by alpha. Fully transparent colors become `CLEAR`.
+    pub fn unpremultiplied(self) -> Self {
+        if self.3 <= 0.0 {
+            return Self::CLEAR;
+        }
+        return Self(self.0 / self.3, self.1 / self.3, self.2 / self.3, self.3);
+    }
+
+    /// Converts the color components from sRGB to linear space. Alpha is left untouched.
+    pub fn to_linear(self) -> Self {
+        Self(srgb_to_linear(self.0), srgb_to_linear(self.1), srgb_to_linear(self.2), self.3)
+    }
+
+    /// Converts the color components from linear to sRGB space. Alpha is left untouched.
+    pub fn to_srgb(self) -> Self {
+        Self(linear_to_srgb(self.0), linear_to_srgb(self.1), linear_to_srgb(self.2), self.3)
+    }
+
+    /// Parses a hex color. See `RGBA8::from_hex`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        RGBA8::from_hex(hex).map(Self::from)
+    }
+
+    /// Formats the color as `#RRGGBB`, or `#RRGGBBAA` if it is not opaque.
+    pub fn to_hex(&self) -> String {
+        RGBA8::from(*self).to_hex()
+    }
+}
+
+impl RGBA8 {
+    /// Returns the same color with a different alpha.
+    pub const fn with_alpha(self, alpha: u8) -> Self {
+        Self(self.0, self.1, self.2, alpha)
+    }
+
+    /// Multiplies the color components by alpha.
+    pub fn premultiplied(self) -> Self {
+        let mul = |x: u8| ((x as u32 * self.3 as u32 + 127) / 255) as u8;
+        Self(mul(self.0), mul(self.1), mul(self.2), self.3)
+    }
+
+    /// Divides the color components by alpha. Fully transparent colors become `CLEAR`.
+    pub fn unpremultiplied(self) -> Self {
+        if self.3 == 0 {
+            return Self::CLEAR;
+        }
+
+        let div = |x: u8| ((x as u32 * 255 + self.3 as u32 / 2) / self.3 as u32).min(255) as u8;
+        return Self(div(self.0), div(self.1), div(self.2), self.3);
+    }
+
+    /// Parses a hex color in the `RGB`, `RGBA`, `RRGGBB` or `RRGGBBAA` formats, with an optional `#` prefix.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // `from_str_radix` would also accept signs
+        if !hex.bytes().all(|x| x.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let short = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|x| x * 17);
+        let long = |i: usize| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok();
+
+        return match hex.len() {
+            3 => Some(Self(short(0)?, short(1)?, short(2)?, 255)),
+            4 => Some(Self(short(0)?, short(1)?, short(2)?, short(3)?)),
+            6 => Some(Self(long(0)?, long(1)?, long(2)?, 255)),
+            8 => Some(Self(long(0)?, long(1)?, long(2)?, long(3)?)),
+            _ => None,
+        };
+    }
+
+    /// Formats the color as `#RRGGBB`, or `#RRGGBBAA` if it is not opaque.
+    pub fn to_hex(&self) -> String {
+        if self.3 == 255 {
+            return format!("#{:02X}{:02X}{:02X}", self.0, self.1, self.2);
+        }
+        return format!("#{:02X}{:02X}{:02X}{:02X}", self.0, self.1, self.2, self.3);
+    }
+}
+
+impl From<RGBA8> for RGBA32 {
+    fn from(value: RGBA8) -> Self {
+        Self(value.0 as f32 / 255.0, value.1 as f32 / 255.0, value.2 as f32 / 255.0, value.3 as f32 / 255.0)
+    }
+}
+
+impl From<RGBA32> for RGBA8 {
+    /// Components are clamped to the `[0.0, 1.0]` range.
+    fn from(value: RGBA32) -> Self {
+        let conv = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Self(conv(value.0), conv(value.1), conv(value.2), conv(value.3))
+    }
+}
+
+impl From<[f32; 4]> for RGBA32 {
+    fn from(value: [f32; 4]) -> Self {
+        Self(value[0], value[1], value[2], value[3])
+    }
+}
+
+impl From<RGBA32> for [f32; 4] {
+    fn from(value: RGBA32) -> Self {
+        [value.0, value.1, value.2, value.3]
+    }
+}
+
+impl From<[u8; 4]> for RGBA8 {
+    fn from(value: [u8; 4]) -> Self {
+        Self(value[0], value[1], value[2], value[3])
+    }
+}
+
+impl From<RGBA8> for [u8; 4] {
+    fn from(value: RGBA8) -> Self {
+        [value.0, value.1, value.2, value.3]
+    }
+}
+
+impl FromStr for RGBA32 {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s).ok_or(ParseColorError)
+    }
+}
+
+impl FromStr for RGBA8 {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s).ok_or(ParseColorError)
+    }
+}
+
+/// Error returned when a hex color couldn't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseColorError;
+
+impl std::fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid hex color")
+    }
+}
+
+impl std::error::Error for ParseColorError { }
+
+impl Lerp for RGBA32 {
+    type Factor = f32;
+
+    fn lerp(self, other: Self, t: Self::Factor) -> Self {
+        self * (1.0 - t) + other * t
+    }
+
+    fn clamped_lerp(self, other: Self, t: Self::Factor) -> Self {
+        self.lerp(other, t.clamp(0.0, 1.0))
+    }
+}
+
+macro_rules! rgba32_op_impl {
+    ($trait:ident, $fn:ident, $assign_trait:ident, $assign_fn:ident, $op:tt) => {
+        impl $trait for RGBA32 {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self::Output {
+                Self(self.0 $op rhs.0, self.1 $op rhs.1, self.2 $op rhs.2, self.3 $op rhs.3)
+            }
+        }
+
+        impl $trait<f32> for RGBA32 {
+            type Output = Self;
+
+            fn $fn(self, rhs: f32) -> Self::Output {
+                Self(self.0 $op rhs, self.1 $op rhs, self.2 $op rhs, self.3 $op rhs)
+            }
+        }
+
+        impl $assign_trait for RGBA32 {
+            fn $assign_fn(&mut self, rhs: Self) {
+                *self = *self $op rhs;
+            }
+        }
+
+        impl $assign_trait<f32> for RGBA32 {
+            fn $assign_fn(&mut self, rhs: f32) {
+                *self = *self $op rhs;
+            }
+        }
+    };
+}
+
+rgba32_op_impl!(Add, add, AddAssign, add_assign, +);
+rgba32_op_impl!(Sub, sub, SubAssign, sub_assign, -);
+rgba32_op_impl!(Mul, mul, MulAssign, mul_assign, *);
+rgba32_op_impl!(Div, div, DivAssign, div_assign, /);
+
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 { x / 12.92 } else { ((x + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 { x * 12.92 } else { 1.055 * x.powf(1.0 / 2.4) - 0.055 }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::colors::Color;
+
+    use super::{RGBA32, RGBA8};
+
+    #[test]
+    fn hex() {
+        assert_eq!(RGBA8::from_hex("#FF7F00"), Some(RGBA8::ORANGE));
+        assert_eq!(RGBA8::from_hex("f0f8"), Some(RGBA8(255, 0, 255, 136)));
+        assert_eq!(RGBA8::from_hex("#12345"), None);
+        assert_eq!(RGBA8::from_hex("#GG0000"), None);
+        assert_eq!(RGBA8::from_hex("#+F+F+F"), None);
+        assert_eq!(RGBA8::from_hex("+F+"), None);
+        assert_eq!(RGBA8::ORANGE.to_hex(), "#FF7F00");
+        assert_eq!(RGBA8(1, 2, 3, 4).to_hex(), "#01020304");
+        assert_eq!("#7F7F7F".parse::<RGBA8>(), Ok(RGBA8::GRAY));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(RGBA32::from(RGBA8::WHITE), RGBA32::WHITE);
+        assert_eq!(RGBA8::from(RGBA32(2.0, -1.0, 0.5, 1.0)), RGBA8(255, 0, 128, 255));
+        assert_eq!(RGBA32(1.0, 0.5, 0.0, 0.5).premultiplied(), RGBA32(0.5, 0.25, 0.0, 0.5));
+        assert_eq!(RGBA32(0.5, 0.25, 0.0, 0.5).unpremultiplied(), RGBA32(1.0, 0.5, 0.0, 0.5));
+        assert_eq!(RGBA8(255, 128, 0, 128).premultiplied(), RGBA8(128, 64, 0, 128));
+        assert_eq!(RGBA8(128, 64, 0, 128).unpremultiplied(), RGBA8(255, 128, 0, 128));
+        assert_eq!(RGBA8::RED.with_alpha(0).unpremultiplied(), RGBA8::CLEAR);
+
+        let c = RGBA32(0.2, 0.5, 0.8, 1.0);
+        let back = c.to_linear().to_srgb();
+        assert!((back.0 - c.0).abs() < 1e-5 && (back.1 - c.1).abs() < 1e-5 && (back.2 - c.2).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,149 @@
+use super::rgba::RGBA32;
+
+/// Color in HSV space. Hue is in degrees (`[0.0, 360.0)`), saturation, value and alpha are in the `[0.0, 1.0]` range.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HSVA(pub f32, pub f32, pub f32, pub f32);
+
+/// Color in HSL space. Hue is in degrees (`[0.0, 360.0)`), saturation, lightness and alpha are in the `[0.0, 1.0]` range.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HSLA(pub f32, pub f32, pub f32, pub f32);
+
+/// Color in OKLab space (lightness, green-red axis, blue-yellow axis, alpha). Perceptually uniform, so it's fit for blending and gradients.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OKLABA(pub f32, pub f32, pub f32, pub f32);
+
+/// Returns hue, max and min components of an RGB color.
+fn hue_max_min(c: RGBA32) -> (f32, f32, f32) {
+    let max = c.0.max(c.1).max(c.2);
+    let min = c.0.min(c.1).min(c.2);
+    let delta = max - min;
+
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == c.0 {
+        60.0 * ((c.1 - c.2) / delta).rem_euclid(6.0)
+    } else if max == c.1 {
+        60.0 * ((c.2 - c.0) / delta + 2.0)
+    } else {
+        60.0 * ((c.0 - c.1) / delta + 4.0)
+    };
+    return (hue, max, min);
+}
+
+/// Builds an RGB color from hue, chroma and the amount to add to every component.
+fn from_hue_chroma(hue: f32, chroma: f32, m: f32, alpha: f32) -> RGBA32 {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    return RGBA32(r + m, g + m, b + m, alpha);
+}
+
+impl From<RGBA32> for HSVA {
+    fn from(value: RGBA32) -> Self {
+        let (h, max, min) = hue_max_min(value);
+        let s = if max <= 0.0 { 0.0 } else { (max - min) / max };
+        return Self(h, s, max, value.3);
+    }
+}
+
+impl From<HSVA> for RGBA32 {
+    fn from(value: HSVA) -> Self {
+        let chroma = value.2 * value.1;
+        return from_hue_chroma(value.0, chroma, value.2 - chroma, value.3);
+    }
+}
+
+impl From<RGBA32> for HSLA {
+    fn from(value: RGBA32) -> Self {
+        let (h, max, min) = hue_max_min(value);
+        let l = (max + min) * 0.5;
+        let s = if max - min <= 0.0 { 0.0 } else { (max - min) / (1.0 - (2.0 * l - 1.0).abs()) };
+        return Self(h, s, l, value.3);
+    }
+}
+
+impl From<HSLA> for RGBA32 {
+    fn from(value: HSLA) -> Self {
+        let chroma = (1.0 - (2.0 * value.2 - 1.0).abs()) * value.1;
+        return from_hue_chroma(value.0, chroma, value.2 - chroma * 0.5, value.3);
+    }
+}
+
+impl From<RGBA32> for OKLABA {
+    /// `value` is expected to be in sRGB space.
+    fn from(value: RGBA32) -> Self {
+        let c = value.to_linear();
+
+        let l = (0.4122214708 * c.0 + 0.5363325363 * c.1 + 0.0514459929 * c.2).cbrt();
+        let m = (0.2119034982 * c.0 + 0.6806995451 * c.1 + 0.1073969566 * c.2).cbrt();
+        let s = (0.0883024619 * c.0 + 0.2817188376 * c.1 + 0.6299787005 * c.2).cbrt();
+
+        return Self(
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+            value.3,
+        );
+    }
+}
+
+impl From<OKLABA> for RGBA32 {
+    /// The result is in sRGB space.
+    fn from(value: OKLABA) -> Self {
+        let l = (value.0 + 0.3963377774 * value.1 + 0.2158037573 * value.2).powi(3);
+        let m = (value.0 - 0.1055613458 * value.1 - 0.0638541728 * value.2).powi(3);
+        let s = (value.0 - 0.0894841775 * value.1 - 1.2914855480 * value.2).powi(3);
+
+        return RGBA32(
+             4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+            value.3,
+        ).to_srgb();
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::colors::{rgba::RGBA32, Color};
+
+    use super::{HSLA, HSVA, OKLABA};
+
+    fn approx(a: RGBA32, b: RGBA32) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4 && (a.2 - b.2).abs() < 1e-4 && (a.3 - b.3).abs() < 1e-4
+    }
+
+    #[test]
+    fn hsv_hsl() {
+        assert_eq!(HSVA::from(RGBA32::RED), HSVA(0.0, 1.0, 1.0, 1.0));
+        assert_eq!(HSVA::from(RGBA32::BLUE), HSVA(240.0, 1.0, 1.0, 1.0));
+        assert_eq!(HSLA::from(RGBA32::WHITE), HSLA(0.0, 0.0, 1.0, 1.0));
+        assert!(approx(RGBA32::from(HSLA(120.0, 1.0, 0.5, 1.0)), RGBA32::GREEN));
+
+        for c in [RGBA32::ORANGE, RGBA32::VIOLET, RGBA32::DARK_CYAN, RGBA32(0.3, 0.6, 0.2, 0.5)] {
+            assert!(approx(RGBA32::from(HSVA::from(c)), c));
+            assert!(approx(RGBA32::from(HSLA::from(c)), c));
+        }
+    }
+
+    #[test]
+    fn oklab() {
+        let white = OKLABA::from(RGBA32::WHITE);
+        assert!((white.0 - 1.0).abs() < 1e-3 && white.1.abs() < 1e-3 && white.2.abs() < 1e-3);
+
+        for c in [RGBA32::ORANGE, RGBA32::AZURE, RGBA32(0.3, 0.6, 0.2, 0.5)] {
+            assert!(approx(RGBA32::from(OKLABA::from(c)), c));
+        }
+    }
+}
@@ -72,11 +72,30 @@ impl GlBuffer {
         }
     }
 
+    /// Reallocates the storage and writes `data` from the start, so the driver doesn't have to wait for draws still using the old storage. Grows the storage if `data` doesn't fit.
+    pub fn orphan_set(&mut self, data: &[u8]) {
+        test_main_thread();
+        if data.len() as isize > self.size {
+            self.size = data.len().next_power_of_two() as isize;
+        }
+
+        unsafe {
+            gl::BindBuffer(self.target as u32, self.id);
+            gl::BufferData(self.target as u32, self.size, std::ptr::null(), self.usage as u32);
+            gl::BufferSubData(self.target as u32, 0, data.len() as isize, data.as_ptr() as *const c_void);
+        }
+    }
+
     pub fn bind(&self) {
         test_main_thread();
         unsafe { gl::BindBuffer(self.target as u32, self.id) }
     }
 
+    /// Returns the size of the storage in bytes.
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
     pub fn target(&self) -> GlBufferTarget {
         self.target
     }
@@ -53,6 +53,14 @@ pub fn gl_multiplicative_blend() {
     }
 }
 
+/// Returns the amount of texture units available to fragment shaders.
+pub fn gl_max_texture_units() -> u32 {
+    test_main_thread();
+    let mut units = 0;
+    unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_IMAGE_UNITS, &mut units) };
+    return units.max(0) as u32;
+}
+
 pub fn gl_clear(col: RGBA32) {
     //test_main_thread(); // not needed
     unsafe {
@@ -66,15 +74,23 @@ pub fn gl_clear(col: RGBA32) {
 #[derive(Debug, Clone, Copy)]
 pub enum GlRenderMode {
     GlTriangles = gl::TRIANGLES,
+    GlTriangleStrip = gl::TRIANGLE_STRIP,
     GlLines = gl::LINES,
     GlPoints = gl::POINTS,
 }
 
-pub fn gl_render_elements(mode: GlRenderMode, indices_count: i32) {
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlIndexType {
+    U16 = gl::UNSIGNED_SHORT,
+    U32 = gl::UNSIGNED_INT,
+}
+
+pub fn gl_render_elements(mode: GlRenderMode, indices_count: i32, index_type: GlIndexType) {
     //test_main_thread(); // not needed
     assert_expr!(indices_count >= 0);
     unsafe {
-        gl::DrawElements(mode as u32, indices_count, gl::UNSIGNED_SHORT, std::ptr::null());
+        gl::DrawElements(mode as u32, indices_count, index_type as u32, std::ptr::null());
     }
 }
 
@@ -85,6 +101,13 @@ pub fn gl_render_array(mode: GlRenderMode, verts_count: i32) {
     }
 }
 
+pub fn gl_render_array_instanced(mode: GlRenderMode, verts_count: i32, instances: i32) {
+    assert_expr!(verts_count >= 0 && instances >= 0);
+    unsafe {
+        gl::DrawArraysInstanced(mode as u32, 0, verts_count, instances);
+    }
+}
+
 pub fn gl_viewport(rect: IRect) {
     //test_main_thread(); // not needed
     assert_expr!(rect.start.0 < rect.end.0 && rect.start.1 < rect.end.1);
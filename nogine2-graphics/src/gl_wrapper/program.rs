@@ -59,6 +59,23 @@ impl GlProgram {
         self.id
     }
 
+    /// Returns the length of an active array uniform, or `0` if there's no such uniform.
+    pub fn uniform_array_len(&self, name: &CStr) -> usize {
+        //test_main_thread() not needed because this is only called when creating a shader
+        unsafe {
+            let names = [name.as_ptr()];
+            let mut index = gl::INVALID_INDEX;
+            gl::GetUniformIndices(self.id, 1, names.as_ptr(), &mut index);
+            if index == gl::INVALID_INDEX {
+                return 0;
+            }
+
+            let mut size = 0;
+            gl::GetActiveUniformsiv(self.id, 1, &index, gl::UNIFORM_SIZE, &mut size);
+            return size.max(0) as usize;
+        }
+    }
+
     pub fn get_samplers(&self) -> Vec<u32> {
         //test_main_thread() not needed because this is only called when creating a shader
         let mut res = Vec::new();
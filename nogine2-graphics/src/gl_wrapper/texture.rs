@@ -31,6 +31,7 @@ pub enum GlTextureFormat {
 pub struct GlTexture {
     id: gl_uint,
     dims: uvec2,
+    layers: Option<u32>,
 }
 
 impl GlTexture {
@@ -48,17 +49,53 @@ impl GlTexture {
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrapping as i32);
 
             gl::TexImage2D(gl::TEXTURE_2D, 0, format as i32, dims.0 as i32, dims.1 as i32, 0, format as u32, gl::UNSIGNED_BYTE, data);
-            return Self { id, dims };
+            return Self { id, dims, layers: None };
+        }
+    }
+
+    /// Creates a `GL_TEXTURE_2D_ARRAY` with `layers` layers of `dims` pixels. `data` must hold every layer, one after the other.
+    pub fn new_array(format: GlTextureFormat, dims: uvec2, layers: u32, filtering: GlTextureFiltering, wrapping: GlTextureWrapping, data: *const c_void) -> Self {
+        test_main_thread();
+        assert_expr!(dims.0 > 0 && dims.0 < gl::MAX_TEXTURE_SIZE && dims.1 > 0 && dims.1 < gl::MAX_TEXTURE_SIZE);
+        assert_expr!(layers > 0);
+
+        unsafe {
+            let mut id = 0;
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, filtering as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, filtering as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, wrapping as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, wrapping as i32);
+
+            gl::TexImage3D(gl::TEXTURE_2D_ARRAY, 0, format as i32, dims.0 as i32, dims.1 as i32, layers as i32, 0, format as u32, gl::UNSIGNED_BYTE, data);
+            return Self { id, dims, layers: Some(layers) };
         }
     }
 
     pub fn set(&self, offset: uvec2, dims: uvec2, format: GlTextureFormat, data: *const c_void) {
         test_main_thread();
+        assert_expr!(self.layers.is_none());
         assert_expr!(offset.0 + dims.0 <= self.dims.0 && offset.1 + dims.1 <= self.dims.1);
 
         unsafe {
+            let mut alignment = 4;
+            gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut alignment);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1); // rows of regions are tightly packed
             gl::BindTexture(gl::TEXTURE_2D, self.id);
             gl::TexSubImage2D(gl::TEXTURE_2D, 0, offset.0 as i32, offset.1 as i32, dims.0 as i32, dims.1 as i32, format as u32, gl::UNSIGNED_BYTE, data);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+        }
+    }
+
+    /// Replaces the whole `layer` of an array texture.
+    pub fn set_layer(&self, layer: u32, format: GlTextureFormat, data: *const c_void) {
+        test_main_thread();
+        assert_expr!(self.layers.is_some_and(|l| layer < l));
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+            gl::TexSubImage3D(gl::TEXTURE_2D_ARRAY, 0, 0, 0, layer as i32, self.dims.0 as i32, self.dims.1 as i32, 1, format as u32, gl::UNSIGNED_BYTE, data);
         }
     }
 
@@ -68,7 +105,7 @@ impl GlTexture {
 
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0 + target);
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::BindTexture(self.gl_target(), self.id);
         }
     }
 
@@ -79,6 +116,15 @@ impl GlTexture {
     pub fn dims(&self) -> uvec2 {
         self.dims
     }
+
+    /// Returns the layer count if it's an array texture.
+    pub fn layers(&self) -> Option<u32> {
+        self.layers
+    }
+
+    fn gl_target(&self) -> u32 {
+        if self.layers.is_some() { gl::TEXTURE_2D_ARRAY } else { gl::TEXTURE_2D }
+    }
 }
 
 impl PartialEq for GlTexture {
@@ -56,6 +56,16 @@ impl GlVertexArray {
         }
     }
 
+    /// Same as `bind_vbo`, but the attributes advance once per instance instead of once per vertex.
+    pub fn bind_instanced_vbo(&mut self, buffer: &GlBuffer, def: &[GlVertexAttribDefinition]) {
+        self.bind_vbo(buffer, def);
+        unsafe {
+            for att in def {
+                gl::VertexAttribDivisor(att.id, 1);
+            }
+        }
+    }
+
     pub fn bind(&self) {
         unsafe { gl::BindVertexArray(self.id) };
     }
@@ -0,0 +1,396 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use nogine2_core::{assert_expr, math::vector2::ivec2};
+
+use super::texture::sprite::{Sprite, SpriteAtlas};
+
+/// Defines how a clip continues after its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackMode {
+    /// Stops at the last frame.
+    Once,
+    /// Starts over from the first frame.
+    #[default]
+    Loop,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+
+/// Sequence of frames with their durations. Frames are usually `Sprite`s, but any value can be animated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip<T = Sprite> {
+    frames: Vec<T>,
+    durations: Vec<f32>,
+    events: Vec<(usize, Cow<'static, str>)>,
+    mode: PlaybackMode,
+}
+
+impl AnimationClip<Sprite> {
+    /// Creates a clip from the cells of an atlas. Will panic if `cells` is empty or `frame_duration <= 0.0`.
+    pub fn from_atlas(atlas: &SpriteAtlas, cells: &[ivec2], frame_duration: f32, mode: PlaybackMode) -> Self {
+        return Self::new(cells.iter().map(|c| atlas.get(*c)).collect(), frame_duration, mode);
+    }
+}
+
+impl<T> AnimationClip<T> {
+    /// Creates a clip where every frame lasts `frame_duration` seconds. Will panic if `frames` is empty or `frame_duration <= 0.0`.
+    pub fn new(frames: Vec<T>, frame_duration: f32, mode: PlaybackMode) -> Self {
+        assert_expr!(!frames.is_empty(), "A clip must have at least one frame!");
+        assert_expr!(frame_duration > 0.0, "'frame_duration' must be greater than 0!");
+
+        let durations = vec![frame_duration; frames.len()];
+        return Self { frames, durations, events: Vec::new(), mode };
+    }
+
+    /// Sets the duration of every frame, in seconds. Will panic if the length doesn't match the frames or any duration is `<= 0.0`.
+    pub fn with_durations(mut self, durations: Vec<f32>) -> Self {
+        assert_expr!(durations.len() == self.frames.len(), "There must be a duration per frame!");
+        assert_expr!(durations.iter().all(|d| *d > 0.0), "Frame durations must be greater than 0!");
+
+        self.durations = durations;
+        return self;
+    }
+
+    /// Adds an event reported by the `Animator` every time `frame` is shown. Will panic if `frame` is out of bounds.
+    pub fn with_event(mut self, frame: usize, name: impl Into<Cow<'static, str>>) -> Self {
+        assert_expr!(frame < self.frames.len(), "Event frame out of bounds (frame was {frame}, len was {})!", self.frames.len());
+
+        self.events.push((frame, name.into()));
+        return self;
+    }
+
+    pub fn with_mode(mut self, mode: PlaybackMode) -> Self {
+        self.mode = mode;
+        return self;
+    }
+
+    pub fn frames(&self) -> &[T] {
+        &self.frames
+    }
+
+    pub fn durations(&self) -> &[f32] {
+        &self.durations
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the duration of a full cycle, going back and forth for `PlaybackMode::PingPong`.
+    pub fn cycle_duration(&self) -> f32 {
+        let forward = self.durations.iter().sum::<f32>();
+        if self.mode != PlaybackMode::PingPong || self.frames.len() <= 2 {
+            return forward;
+        }
+        return forward + self.durations[1..(self.frames.len() - 1)].iter().sum::<f32>();
+    }
+
+    /// Returns the index of the frame shown after `time` seconds.
+    pub fn frame_at(&self, time: f32) -> usize {
+        let cycle = self.cycle_duration();
+        let time = match self.mode {
+            PlaybackMode::Once if time >= cycle => return self.frames.len() - 1,
+            PlaybackMode::Once => time.max(0.0),
+            PlaybackMode::Loop | PlaybackMode::PingPong => time.rem_euclid(cycle),
+        };
+
+        let mut start = 0.0;
+        for step in 0..self.steps() {
+            start += self.durations[self.step_frame(step)];
+            if time < start {
+                return self.step_frame(step);
+            }
+        }
+        return self.step_frame(self.steps() - 1);
+    }
+
+    /// Calls `f` with every frame that starts after `from` and up to `to`, in order.
+    fn entered_frames(&self, from: f32, to: f32, mut f: impl FnMut(usize)) {
+        let cycle = self.cycle_duration();
+        let mut cycle_start = match self.mode {
+            PlaybackMode::Once => 0.0,
+            PlaybackMode::Loop | PlaybackMode::PingPong => (from / cycle).floor().max(0.0) * cycle,
+        };
+
+        loop {
+            let mut start = cycle_start;
+            for step in 0..self.steps() {
+                if start > to {
+                    return;
+                }
+                if start > from {
+                    f(self.step_frame(step));
+                }
+                start += self.durations[self.step_frame(step)];
+            }
+
+            if self.mode == PlaybackMode::Once {
+                return;
+            }
+            cycle_start += cycle;
+        }
+    }
+
+    /// Returns the frames shown in a cycle.
+    fn steps(&self) -> usize {
+        let len = self.frames.len();
+        return match self.mode {
+            PlaybackMode::PingPong if len > 2 => len * 2 - 2,
+            _ => len,
+        };
+    }
+
+    /// Returns the frame shown at a step of the cycle.
+    fn step_frame(&self, step: usize) -> usize {
+        let len = self.frames.len();
+        return if step < len { step } else { len * 2 - 2 - step };
+    }
+}
+
+
+/// Index of a state inside of an `Animator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnimStateId(usize);
+
+/// Condition for an `Animator` to change states.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionCondition {
+    /// The clip finished, or completed a cycle if it repeats.
+    Finished,
+    /// The bool parameter is `true`.
+    Bool(Cow<'static, str>),
+    /// The bool parameter is `true`. It's reset to `false` when the transition happens.
+    Trigger(Cow<'static, str>),
+}
+
+/// Event of a clip reported by an `Animator`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationEvent {
+    pub state: AnimStateId,
+    pub frame: usize,
+    pub name: Cow<'static, str>,
+}
+
+#[derive(Debug, Clone)]
+struct AnimState<T> {
+    name: Cow<'static, str>,
+    clip: AnimationClip<T>,
+    transitions: Vec<(TransitionCondition, AnimStateId)>,
+}
+
+
+/// Plays clips through a state machine. Call `update` every frame and draw `frame`, or use `Graphics::draw_animated`.
+#[derive(Debug, Clone)]
+pub struct Animator<T = Sprite> {
+    states: Vec<AnimState<T>>,
+    current: AnimStateId,
+    time: f32,
+    speed: f32,
+    params: HashMap<Cow<'static, str>, bool>,
+    events: Vec<AnimationEvent>,
+}
+
+impl<T> Animator<T> {
+    /// Creates an animator that starts at the state `name`.
+    pub fn new(name: impl Into<Cow<'static, str>>, clip: AnimationClip<T>) -> Self {
+        let state = AnimState { name: name.into(), clip, transitions: Vec::new() };
+        let mut animator = Self { states: vec![state], current: AnimStateId(0), time: 0.0, speed: 1.0, params: HashMap::new(), events: Vec::new() };
+        animator.enter(AnimStateId(0));
+        return animator;
+    }
+
+    pub fn add_state(&mut self, name: impl Into<Cow<'static, str>>, clip: AnimationClip<T>) -> AnimStateId {
+        self.states.push(AnimState { name: name.into(), clip, transitions: Vec::new() });
+        return AnimStateId(self.states.len() - 1);
+    }
+
+    /// Adds a transition. Transitions are checked after every update, in insertion order. Will panic if any of the states doesn't exist.
+    pub fn add_transition(&mut self, from: AnimStateId, to: AnimStateId, condition: TransitionCondition) {
+        assert_expr!(from.0 < self.states.len() && to.0 < self.states.len(), "Both states of a transition must exist!");
+        self.states[from.0].transitions.push((condition, to));
+    }
+
+    pub fn state_id(&self, name: &str) -> Option<AnimStateId> {
+        self.states.iter().position(|s| s.name == name).map(AnimStateId)
+    }
+
+    pub fn state_name(&self, state: AnimStateId) -> Option<&str> {
+        self.states.get(state.0).map(|s| s.name.as_ref())
+    }
+
+    pub fn current_state(&self) -> AnimStateId {
+        self.current
+    }
+
+    /// Switches to `state` and plays it from the start, even if it was already playing. Will panic if the state doesn't exist.
+    pub fn play(&mut self, state: AnimStateId) {
+        assert_expr!(state.0 < self.states.len(), "State doesn't exist!");
+        self.events.clear();
+        self.enter(state);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Sets the playback speed factor. Negative values are clamped to `0.0`.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Returns the seconds since the current state started, scaled by the speed.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn param(&self, name: &str) -> bool {
+        self.params.get(name).copied().unwrap_or(false)
+    }
+
+    /// Sets a parameter for the `Bool` and `Trigger` conditions.
+    pub fn set_param(&mut self, name: impl Into<Cow<'static, str>>, value: bool) {
+        self.params.insert(name.into(), value);
+    }
+
+    /// Sets a parameter to `true`, to be consumed by a `Trigger` condition.
+    pub fn trigger(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.set_param(name, true);
+    }
+
+    pub fn clip(&self) -> &AnimationClip<T> {
+        &self.states[self.current.0].clip
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.clip().frame_at(self.time)
+    }
+
+    /// Returns the frame to display.
+    pub fn frame(&self) -> &T {
+        &self.clip().frames[self.frame_index()]
+    }
+
+    /// Returns `true` if the clip finished, or completed a cycle if it repeats.
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.clip().cycle_duration()
+    }
+
+    /// Returns the events of the frames shown during the last update.
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    /// Advances the animation by `dt` seconds and applies the first transition whose condition is met.
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+
+        let from = self.time;
+        self.time += dt * self.speed;
+
+        let state = &self.states[self.current.0];
+        let events = &mut self.events;
+        state.clip.entered_frames(from, self.time, |frame| push_events(events, self.current, &state.clip, frame));
+
+        let finished = self.is_finished();
+        let next = state.transitions.iter().find(|(condition, _)| match condition {
+            TransitionCondition::Finished => finished,
+            TransitionCondition::Bool(name) | TransitionCondition::Trigger(name) => self.params.get(name).copied().unwrap_or(false),
+        }).cloned();
+
+        if let Some((condition, to)) = next {
+            if let TransitionCondition::Trigger(name) = condition {
+                self.params.insert(name, false);
+            }
+            self.enter(to);
+        }
+    }
+
+    fn enter(&mut self, state: AnimStateId) {
+        self.current = state;
+        self.time = 0.0;
+        push_events(&mut self.events, state, &self.states[state.0].clip, 0);
+    }
+}
+
+fn push_events<T>(events: &mut Vec<AnimationEvent>, state: AnimStateId, clip: &AnimationClip<T>, frame: usize) {
+    for (_, name) in clip.events.iter().filter(|(f, _)| *f == frame) {
+        events.push(AnimationEvent { state, frame, name: name.clone() });
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{AnimationClip, Animator, PlaybackMode, TransitionCondition};
+
+    #[test]
+    fn clip_timing() {
+        let clip = AnimationClip::new(vec!['a', 'b', 'c'], 0.1, PlaybackMode::Loop).with_durations(vec![0.5, 1.0, 0.5]);
+        assert_eq!(clip.cycle_duration(), 2.0);
+        assert_eq!(clip.frame_at(0.0), 0);
+        assert_eq!(clip.frame_at(0.75), 1);
+        assert_eq!(clip.frame_at(1.75), 2);
+        assert_eq!(clip.frame_at(2.25), 0);
+
+        let once = clip.clone().with_mode(PlaybackMode::Once);
+        assert_eq!(once.frame_at(10.0), 2);
+
+        let ping_pong = clip.with_mode(PlaybackMode::PingPong);
+        assert_eq!(ping_pong.cycle_duration(), 3.0);
+        assert_eq!(ping_pong.frame_at(2.5), 1);
+        assert_eq!(ping_pong.frame_at(3.25), 0);
+    }
+
+    #[test]
+    fn events() {
+        let clip = AnimationClip::new(vec![0, 1, 2, 3], 0.25, PlaybackMode::Loop).with_event(0, "start").with_event(2, "step");
+        let mut animator = Animator::new("walk", clip);
+        assert_eq!(animator.events().len(), 1);
+
+        animator.update(0.25);
+        assert!(animator.events().is_empty());
+        assert_eq!(*animator.frame(), 1);
+
+        // Skipped frames still report their events
+        animator.update(1.0);
+        let names = animator.events().iter().map(|e| e.name.as_ref()).collect::<Vec<_>>();
+        assert_eq!(names, ["step", "start"]);
+    }
+
+    #[test]
+    fn transitions() {
+        let mut animator = Animator::new("idle", AnimationClip::new(vec![0], 1.0, PlaybackMode::Loop));
+        let idle = animator.current_state();
+        let attack = animator.add_state("attack", AnimationClip::new(vec![1, 2], 0.5, PlaybackMode::Once));
+        let run = animator.add_state("run", AnimationClip::new(vec![3], 1.0, PlaybackMode::Loop));
+        animator.add_transition(idle, attack, TransitionCondition::Trigger("attack".into()));
+        animator.add_transition(idle, run, TransitionCondition::Bool("running".into()));
+        animator.add_transition(attack, idle, TransitionCondition::Finished);
+        animator.add_transition(run, idle, TransitionCondition::Bool("stopped".into()));
+
+        animator.trigger("attack");
+        animator.update(0.1);
+        assert_eq!(animator.current_state(), attack);
+        assert!(!animator.param("attack"));
+
+        animator.update(0.6);
+        assert_eq!(*animator.frame(), 2);
+        animator.update(0.5);
+        assert_eq!(animator.current_state(), idle);
+
+        animator.set_param("running", true);
+        animator.set_speed(2.0);
+        animator.update(0.1);
+        assert_eq!(animator.state_name(animator.current_state()), Some("run"));
+        assert_eq!(animator.state_id("attack"), Some(attack));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::gl_wrapper::{buffer::{GlBuffer, GlBufferTarget, GlBufferUsage}, to_byte_slice, GlIndexType};
+
+/// Indices of a batch, uploaded as `u16` unless the batch has too many vertices.
+pub struct BatchIndices {
+    indices: Vec<u32>,
+    narrow: Vec<u16>,
+    ebo: GlBuffer,
+    index_type: GlIndexType,
+}
+
+impl BatchIndices {
+    const INITIAL_SIZE: isize = 256;
+
+    pub fn new() -> Self {
+        Self {
+            indices: Vec::new(),
+            narrow: Vec::new(),
+            ebo: GlBuffer::preallocated(GlBufferTarget::GlElementArrayBuffer, Self::INITIAL_SIZE, GlBufferUsage::StreamDraw),
+            index_type: GlIndexType::U16,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn push(&mut self, index: u32) {
+        self.indices.push(index);
+    }
+
+    pub fn extend(&mut self, indices: impl IntoIterator<Item = u32>) {
+        self.indices.extend(indices);
+    }
+
+    /// Uploads the indices, using `u16` if all of the `verts_len` vertices can be indexed with them. Returns the uploaded bytes.
+    pub fn upload(&mut self, verts_len: usize) -> usize {
+        if verts_len <= u16::MAX as usize + 1 {
+            self.narrow.clear();
+            self.narrow.extend(self.indices.iter().map(|x| *x as u16));
+            self.ebo.orphan_set(to_byte_slice(&self.narrow));
+            self.index_type = GlIndexType::U16;
+        } else {
+            self.ebo.orphan_set(to_byte_slice(&self.indices));
+            self.index_type = GlIndexType::U32;
+        }
+        return self.on_use_size();
+    }
+
+    pub fn index_type(&self) -> GlIndexType {
+        self.index_type
+    }
+
+    pub fn bind(&self) {
+        self.ebo.bind();
+    }
+
+    pub fn on_use_size(&self) -> usize {
+        self.indices.len() * match self.index_type {
+            GlIndexType::U16 => size_of::<u16>(),
+            GlIndexType::U32 => size_of::<u32>(),
+        }
+    }
+
+    pub fn alloc_size(&self) -> usize {
+        self.ebo.size() + self.indices.capacity() * size_of::<u32>() + self.narrow.capacity() * size_of::<u16>()
+    }
+
+    pub fn clear(&mut self) {
+        self.indices.clear();
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use nogine2_core::{log_error, math::mat3x3::mat3};
+
+use crate::{gl_wrapper::{buffer::{GlBuffer, GlBufferTarget, GlBufferUsage}, gl_render_array_instanced, gl_uniform, to_byte_slice, vao::GlVertexArray, GlRenderMode}, graphics::{blending::BlendingMode, material::Material, texture::TextureHandle, vertex::InstanceVertex}};
+
+pub struct InstBatchRenderCall {
+    buffers: InstBatchBuffers,
+    texture: TextureHandle,
+    blending: BlendingMode,
+    material: Arc<Material>,
+    /// Texture unit the texture is bound to and the ID it's sampled with.
+    unit: u32,
+    tex_id: u32,
+}
+
+impl InstBatchRenderCall {
+    pub fn new(buffers: InstBatchBuffers, texture: TextureHandle, blending: BlendingMode, material: Arc<Material>) -> Self {
+        let (max_textures, _) = material.batch_slots();
+        let (unit, tex_id) = match texture.layer() {
+            None => (material.sampler_count() as u32, material.sampler_count() as u32), // offseted to avoid uniform samplers
+            Some(layer) => (max_textures as u32, max_textures as u32 | (layer << 16)),
+        };
+
+        Self { buffers, texture, blending, material, unit, tex_id }
+    }
+
+    pub fn render(&self, view_mat: &mat3) {
+        let instances = self.buffers.bind_all();
+
+        if !self.material.use_material() {
+            log_error!("GL_ERROR: Couldn't render!");
+            return;
+        }
+
+        let Some(instanced_loc) = self.material.uniform_loc(c"uInstanced") else {
+            log_error!("NOGINE2: Instanced rendering requires a shader made with the default batch vertex subshader!");
+            return;
+        };
+
+        self.texture.bind_to(self.unit);
+
+        if let Some(view_mat_loc) = self.material.uniform_loc(c"uViewMat") {
+            gl_uniform::set_mat3(view_mat_loc, view_mat);
+        }
+
+        if let Some(tex_id_loc) = self.material.uniform_loc(c"uInstTexID") {
+            gl_uniform::set_u32(tex_id_loc, self.tex_id);
+        }
+
+        self.blending.apply();
+
+        gl_uniform::set_i32(instanced_loc, 1);
+        gl_render_array_instanced(GlRenderMode::GlTriangleStrip, 4, instances);
+        gl_uniform::set_i32(instanced_loc, 0); // The rest of the batches share the program
+    }
+
+    /// Clears the buffers first
+    pub fn recycle(mut self) -> InstBatchBuffers {
+        self.buffers.clear();
+        self.buffers
+    }
+
+    pub fn allows(&self, instances_len: usize, texture: &TextureHandle, blending: BlendingMode, material: &Arc<Material>) -> bool {
+        self.buffers.fits(instances_len)
+            && self.texture == *texture
+            && self.blending == blending
+            && *self.material == **material
+    }
+
+    pub fn push(&mut self, instances: &[InstanceVertex]) {
+        self.buffers.push(instances);
+    }
+
+    pub fn on_use_size(&self) -> usize {
+        self.buffers.on_use_size()
+    }
+
+    pub fn alloc_size(&self) -> usize {
+        self.buffers.alloc_size()
+    }
+
+    /// Returns the uploaded bytes.
+    pub fn upload(&mut self) -> usize {
+        self.buffers.upload()
+    }
+}
+
+
+pub struct InstBatchBuffers {
+    instances: Vec<InstanceVertex>,
+    vbo: GlBuffer,
+    vao: GlVertexArray,
+    max_instances: usize,
+}
+
+impl InstBatchBuffers {
+    const INITIAL_INSTANCES: usize = 64;
+
+    pub fn new() -> Self {
+        let mut item = Self {
+            instances: Vec::new(),
+            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::INITIAL_INSTANCES * size_of::<InstanceVertex>()) as isize, GlBufferUsage::StreamDraw),
+            vao: GlVertexArray::new(),
+            max_instances: 0,
+        };
+        item.vao.bind_instanced_vbo(&item.vbo, InstanceVertex::VERT_ATTRIB_DEFINITIONS);
+        return item;
+    }
+
+    /// Sets the limits of the batch. Buffers grow on demand until them.
+    pub fn set_capacity(&mut self, max_instances: usize) {
+        self.max_instances = max_instances;
+    }
+
+    fn on_use_size(&self) -> usize {
+        self.instances.len() * size_of::<InstanceVertex>()
+    }
+
+    pub fn alloc_size(&self) -> usize {
+        self.vbo.size() + self.instances.capacity() * size_of::<InstanceVertex>()
+    }
+
+    fn fits(&self, instances: usize) -> bool {
+        return self.instances.len() + instances <= self.max_instances;
+    }
+
+    fn push(&mut self, instances: &[InstanceVertex]) {
+        if !self.fits(instances.len()) {
+            log_error!("NOGINE2: Instance batch overflow, submission dropped!");
+            return;
+        }
+
+        self.instances.extend_from_slice(instances);
+    }
+
+    /// Uploads the whole batch at once. Returns the uploaded bytes.
+    fn upload(&mut self) -> usize {
+        if self.instances.is_empty() {
+            return 0;
+        }
+
+        self.vbo.orphan_set(to_byte_slice(&self.instances));
+        return self.on_use_size();
+    }
+
+    /// Returns the instances count.
+    fn bind_all(&self) -> i32 {
+        self.vao.bind();
+        return self.instances.len() as i32;
+    }
+
+    fn clear(&mut self) {
+        self.instances.clear();
+    }
+}
@@ -2,7 +2,9 @@ use std::sync::Arc;
 
 use nogine2_core::{log_error, math::{mat3x3::mat3, vector2::vec2}};
 
-use crate::{gl_wrapper::{buffer::{GlBuffer, GlBufferTarget, GlBufferUsage}, gl_render_elements, gl_uniform, to_byte_slice, vao::GlVertexArray, GlRenderMode}, graphics::{blending::BlendingMode, material::Material, vertex::BatchVertex}};
+use crate::{gl_wrapper::{buffer::{GlBuffer, GlBufferTarget, GlBufferUsage}, gl_render_elements, gl_uniform, to_byte_slice, vao::GlVertexArray, GlIndexType, GlRenderMode}, graphics::{blending::BlendingMode, material::Material, vertex::BatchVertex}};
+
+use super::indices::BatchIndices;
 
 pub struct LnsBatchRenderCall {
     buffers: LnsBatchBuffers,
@@ -16,7 +18,7 @@ impl LnsBatchRenderCall {
     }
 
     pub fn render(&self, view_mat: &mat3) {
-        let indices_len = self.buffers.bind_all();
+        let (indices_len, index_type) = self.buffers.bind_all();
 
         if !self.material.use_material() {
             log_error!("GL_ERROR: Couldn't render!");
@@ -29,7 +31,7 @@ impl LnsBatchRenderCall {
 
         self.blending.apply();
 
-        gl_render_elements(GlRenderMode::GlLines, indices_len);
+        gl_render_elements(GlRenderMode::GlLines, indices_len, index_type);
     }
 
     /// Clears the buffers first
@@ -38,8 +40,8 @@ impl LnsBatchRenderCall {
         self.buffers
     }
 
-    pub fn allows(&self, verts_len: usize, indices_len: usize, blending: BlendingMode, material: &Arc<Material>) -> bool {
-        self.buffers.fits(verts_len, indices_len) && self.blending == blending && *self.material == **material
+    pub fn allows(&self, blending: BlendingMode, material: &Arc<Material>) -> bool {
+        self.buffers.fits(1) && self.blending == blending && *self.material == **material
     }
 
     pub fn push(&mut self, mut verts: [BatchVertex; 2]) {
@@ -54,83 +56,92 @@ impl LnsBatchRenderCall {
     pub fn on_use_size(&self) -> usize {
         self.buffers.on_use_size()
     }
+
+    pub fn alloc_size(&self) -> usize {
+        self.buffers.alloc_size()
+    }
+
+    /// Returns the uploaded bytes.
+    pub fn upload(&mut self) -> usize {
+        self.buffers.upload()
+    }
 }
 
 
 pub struct LnsBatchBuffers {
     verts: Vec<BatchVertex>,
+    indices: BatchIndices,
     vbo: GlBuffer,
-    ebo: GlBuffer,
-    vlen: usize,
-    elen: usize,
     vao: GlVertexArray,
+    max_lines: usize,
 }
 
 impl LnsBatchBuffers {
-    const MAX_LINES: usize = 64;
-    pub const MAX_VERTS: usize = Self::MAX_LINES * 2;
-    pub const MAX_INDICES: usize = Self::MAX_LINES * 2;
-    pub const BYTE_SIZE: usize = Self::MAX_VERTS * size_of::<BatchVertex>() + Self::MAX_INDICES * size_of::<u16>();
+    const INITIAL_VERTS: usize = 64;
 
     pub fn new() -> Self {
         let mut item = Self {
             verts: Vec::new(),
-            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::MAX_VERTS * size_of::<BatchVertex>()) as isize, GlBufferUsage::DynamicDraw),
-            ebo: GlBuffer::preallocated(GlBufferTarget::GlElementArrayBuffer, (Self::MAX_INDICES * size_of::<u16>()) as isize, GlBufferUsage::DynamicDraw),
+            indices: BatchIndices::new(),
+            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::INITIAL_VERTS * size_of::<BatchVertex>()) as isize, GlBufferUsage::StreamDraw),
             vao: GlVertexArray::new(),
-            vlen: 0, elen: 0,
+            max_lines: 0,
         };
         item.vao.bind_vbo(&item.vbo, BatchVertex::VERT_ATTRIB_DEFINITIONS);
         return item;
     }
 
+    /// Sets the limits of the batch. Buffers grow on demand until them.
+    pub fn set_capacity(&mut self, max_lines: usize) {
+        self.max_lines = max_lines;
+    }
+
     fn on_use_size(&self) -> usize {
-        self.vlen * size_of::<BatchVertex>() + self.elen * size_of::<u16>()
+        self.verts.len() * size_of::<BatchVertex>() + self.indices.on_use_size()
+    }
+
+    pub fn alloc_size(&self) -> usize {
+        self.vbo.size() + self.verts.capacity() * size_of::<BatchVertex>() + self.indices.alloc_size()
     }
 
-    fn fits(&self, verts: usize, indices: usize) -> bool {
-        return self.vlen + verts <= Self::MAX_VERTS && self.elen + indices <= Self::MAX_INDICES;
+    fn fits(&self, lines: usize) -> bool {
+        return self.indices.len() / 2 + lines <= self.max_lines;
     }
 
     fn push(&mut self, verts: [BatchVertex; 2]) {
-        if !self.fits(verts.len(), 2) {
+        if !self.fits(1) {
             return;
         }
 
-        let first_index = match self.verts.iter().position(|x| x == &verts[0]) {
-            Some(i) => i,
-            None => {
-                self.verts.push(verts[0]);
-                self.vbo.set(to_byte_slice(&[verts[0]]), (self.vlen * size_of::<BatchVertex>()) as isize);
-                self.vlen += 1;
-                self.verts.len() - 1
-            },
-        } as u16;
-
-        let second_index = match self.verts.iter().position(|x| x == &verts[1]) {
-            Some(i) => i,
-            None => {
-                self.verts.push(verts[1]);
-                self.vbo.set(to_byte_slice(&[verts[1]]), (self.vlen * size_of::<BatchVertex>()) as isize);
-                self.vlen += 1;
-                self.verts.len() - 1
-            },
-        } as u16;
-
-        self.ebo.set(to_byte_slice(&[first_index, second_index]), (self.elen * size_of::<u16>()) as isize);
-        self.elen += 2;
-    }
-
-    /// Returns the indices count.
-    fn bind_all(&self) -> i32 {
+        // Consecutive lines usually share a vertex
+        if self.verts.last() != Some(&verts[0]) {
+            self.verts.push(verts[0]);
+        }
+        self.indices.push((self.verts.len() - 1) as u32);
+
+        self.verts.push(verts[1]);
+        self.indices.push((self.verts.len() - 1) as u32);
+    }
+
+    /// Uploads the whole batch at once. Returns the uploaded bytes.
+    fn upload(&mut self) -> usize {
+        if self.indices.is_empty() {
+            return 0;
+        }
+
+        self.vbo.orphan_set(to_byte_slice(&self.verts));
+        return self.verts.len() * size_of::<BatchVertex>() + self.indices.upload(self.verts.len());
+    }
+
+    /// Returns the indices count and type.
+    fn bind_all(&self) -> (i32, GlIndexType) {
         self.vao.bind();
-        self.ebo.bind();
-        return self.elen as i32;
+        self.indices.bind();
+        return (self.indices.len() as i32, self.indices.index_type());
     }
 
     fn clear(&mut self) {
-        self.vlen = 0;
-        self.elen = 0;
         self.verts.clear();
+        self.indices.clear();
     }
 }
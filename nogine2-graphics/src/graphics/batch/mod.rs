@@ -1,26 +1,39 @@
-use std::{mem::size_of, sync::Arc};
+use std::{ops::Range, sync::Arc};
 
+use instances::{InstBatchBuffers, InstBatchRenderCall};
 use lines::{LnsBatchBuffers, LnsBatchRenderCall};
-use nogine2_core::{bytesize::ByteSize, math::{mat3x3::mat3, rect::Rect, vector2::{uvec2, vec2}}};
+use nogine2_core::{bytesize::ByteSize, math::{mat3x3::mat3, rect::{IRect, Rect}, vector2::{ivec2, uvec2, vec2}}};
 use points::{PtsBatchBuffers, PtsBatchRenderCall};
 use triangles::{TriBatchBuffers, TriBatchRenderCall};
 
+use crate::gl_wrapper::gl_viewport;
 
-use super::{blending::BlendingMode, material::Material, pipeline::BatchRenderStats, texture::TextureHandle, vertex::BatchVertex, CameraData};
+use super::{blending::BlendingMode, material::Material, mesh::split_mesh, pipeline::{BatchRenderStats, ViewportRenderStats}, texture::TextureHandle, vertex::{BatchVertex, InstanceVertex}, BatchCapacity, CameraData, Graphics, Viewport};
 
 mod triangles;
 mod points;
 mod lines;
+mod instances;
+mod indices;
 
 pub struct BatchData {
     render_calls: Vec<BatchRenderCall>,
+    /// Bounds of the submissions of each render call.
+    call_bounds: Vec<CallBounds>,
+    merge_out_of_order: bool,
     pooled_buffers: BuffersPool,
+    submissions: Vec<Submission>,
+    depth: f32,
+    capacity: BatchCapacity,
 
-    view_mat: mat3,
+    viewports: Vec<ViewportData>,
+    target_viewport: Option<usize>,
+    calls_start: usize,
+
+    /// Culling rect and snapping for new submissions, which depend on the target viewport.
     cam_rect: Rect,
     snapping: vec2,
     target_res: uvec2,
-    camera: CameraData,
 
     stats: BatchRenderStats,
 }
@@ -28,8 +41,9 @@ pub struct BatchData {
 impl BatchData {
     pub const fn new() -> Self {
         Self {
-            render_calls: Vec::new(), pooled_buffers: BuffersPool::new(),
-            view_mat: mat3::IDENTITY, cam_rect: Rect::IDENT, snapping: vec2::ONE, camera: CameraData { center: vec2::ZERO, extents: vec2::ZERO }, target_res: uvec2::ZERO,
+            render_calls: Vec::new(), call_bounds: Vec::new(), merge_out_of_order: false, pooled_buffers: BuffersPool::new(), submissions: Vec::new(), depth: 0.0, capacity: BatchCapacity::DEFAULT,
+            viewports: Vec::new(), target_viewport: None, calls_start: 0,
+            cam_rect: Rect::IDENT, snapping: vec2::ONE, target_res: uvec2::ZERO,
             stats: BatchRenderStats::new(),
         }
     }
@@ -37,58 +51,45 @@ impl BatchData {
     pub fn push(&mut self, cmd: BatchPushCmd<'_>, culling_enabled: bool) {
         match cmd {
             BatchPushCmd::Triangles { verts, indices, texture, blending, material } => {
-                if culling_enabled {
-                    let bb = calculate_bounding_box(verts);
-                    if !aabb_check(self.cam_rect, bb) {
-                        self.stats.skipped_submissions += 1;
-                        return;
-                    }
+                let bb = calculate_bounding_box(verts);
+                if culling_enabled && !aabb_check(self.cam_rect, bb) {
+                    self.stats.skipped_submissions += 1;
+                    return;
                 }
                 self.stats.rendered_submissions += 1;
 
-                let mut verts = verts.iter().copied().map(|mut x| {
+                let verts = verts.iter().copied().map(|mut x| {
                     x.pos = snap(x.pos, self.snapping);
                     return x;
                 }).collect::<Vec<_>>();
-                let mut indices = indices.to_vec();
 
                 self.stats.verts += verts.len();
                 self.stats.triangles += indices.len() / 3;
 
-                let cursor = self.tri_render_call_cursor(verts.len(), indices.len(), &texture, blending, material);
-                if let BatchRenderCall::Triangles(call) = &mut self.render_calls[cursor] {
-                    call.push(&mut verts, &mut indices, texture);
-                }
+                self.submit(bb, culling_enabled, SubmissionKind::Triangles { verts, indices: indices.to_vec(), texture }, blending, material);
             },
             BatchPushCmd::Points { verts, blending, material } => {
-                if culling_enabled {
-                    let bb = calculate_bounding_box(verts);
-                    if !aabb_check(self.cam_rect, bb) {
-                        self.stats.skipped_submissions += 1;
-                        return;
-                    }
+                let bb = calculate_bounding_box(verts);
+                if culling_enabled && !aabb_check(self.cam_rect, bb) {
+                    self.stats.skipped_submissions += 1;
+                    return;
                 }
                 self.stats.rendered_submissions += 1;
  
-                let mut verts = verts.iter().copied().map(|mut x| {
+                let verts = verts.iter().copied().map(|mut x| {
                     x.pos = snap(x.pos, self.snapping);
                     return x;
                 }).collect::<Vec<_>>();
 
                 self.stats.verts += verts.len();
 
-                let cursor = self.pts_render_call_cursor(verts.len(), blending, material);
-                if let BatchRenderCall::Points(call) = &mut self.render_calls[cursor] {
-                    call.push(&mut verts);
-                }
+                self.submit(bb, culling_enabled, SubmissionKind::Points { verts }, blending, material);
             },
             BatchPushCmd::Lines { mut verts, blending, material } => {
-                if culling_enabled {
-                    let bb = calculate_bounding_box(&verts);
-                    if !aabb_check(self.cam_rect, bb) {
-                        self.stats.skipped_submissions += 1;
-                        return;
-                    }
+                let bb = calculate_bounding_box(&verts);
+                if culling_enabled && !aabb_check(self.cam_rect, bb) {
+                    self.stats.skipped_submissions += 1;
+                    return;
                 }
                 self.stats.rendered_submissions += 1;
 
@@ -98,22 +99,158 @@ impl BatchData {
                 self.stats.verts += verts.len();
                 self.stats.triangles += 2;
 
-                let cursor = self.lns_render_call_cursor(verts.len(), 2, blending, material);
+                self.submit(bb, culling_enabled, SubmissionKind::Lines { verts }, blending, material);
+            },
+            BatchPushCmd::Instances { instances, texture, blending, material } => {
+                if instances.is_empty() {
+                    return;
+                }
+
+                let bb = calculate_instances_bounding_box(instances);
+                if culling_enabled && !aabb_check(self.cam_rect, bb) {
+                    self.stats.skipped_submissions += 1;
+                    return;
+                }
+                self.stats.rendered_submissions += 1;
+
+                let instances = instances.iter().copied().map(|mut x| {
+                    x.origin = snap(x.origin, self.snapping);
+                    return x;
+                }).collect::<Vec<_>>();
+
+                self.stats.instances += instances.len();
+                self.stats.verts += instances.len() * 4;
+                self.stats.triangles += instances.len() * 2;
+
+                self.submit(bb, culling_enabled, SubmissionKind::Instances { instances, texture }, blending, material);
+            },
+        }
+    }
+
+    fn submit(&mut self, bb: Rect, culling_enabled: bool, kind: SubmissionKind, blending: BlendingMode, material: Arc<Material>) {
+        self.submissions.push(Submission {
+            key: SortKey { depth: self.depth, y: bb.start.1 },
+            bb, culling_enabled, viewport: self.target_viewport,
+            kind, blending, material
+        });
+    }
+
+    /// Sorts the pending submissions, builds the render calls of every viewport and uploads them. Must be called before rendering.
+    pub fn finish(&mut self, sorting: BatchSorting) {
+        let mut submissions = std::mem::take(&mut self.submissions);
+        sort_submissions(&mut submissions, sorting);
+        self.merge_out_of_order = sorting.merge_out_of_order;
+
+        for i in 0..self.viewports.len() {
+            self.calls_start = self.render_calls.len();
+            let cam_rect = self.viewports[i].cam_rect;
+            let mut stats = ViewportRenderStats::new();
+
+            let visible = |sub: &Submission, stats: &mut ViewportRenderStats| {
+                if sub.viewport.is_some_and(|x| x != i) {
+                    return false;
+                }
+                if sub.culling_enabled && !aabb_check(cam_rect, sub.bb) {
+                    stats.skipped_submissions += 1;
+                    return false;
+                }
+                stats.rendered_submissions += 1;
+                return true;
+            };
+
+            // The last viewport can take ownership of the submissions
+            if i + 1 == self.viewports.len() {
+                for sub in submissions.drain(..) {
+                    if visible(&sub, &mut stats) {
+                        self.build(sub.kind, sub.bb, sub.blending, sub.material);
+                    }
+                }
+            } else {
+                for sub in &submissions {
+                    if visible(sub, &mut stats) {
+                        self.build(sub.kind.clone(), sub.bb, sub.blending, sub.material.clone());
+                    }
+                }
+            }
+
+            stats.draw_calls = self.render_calls.len() - self.calls_start;
+            self.viewports[i].calls = self.calls_start..self.render_calls.len();
+            self.viewports[i].stats = stats;
+        }
+
+        submissions.clear();
+        self.submissions = submissions; // Keep the allocation
+
+        // Every batch is uploaded once, after all the submissions have been built
+        let uploaded = self.render_calls.iter_mut().map(|x| x.upload()).sum::<usize>();
+        self.stats.uploaded_memory = ByteSize::new(uploaded as u64);
+    }
+
+    fn build(&mut self, kind: SubmissionKind, bb: Rect, blending: BlendingMode, material: Arc<Material>) {
+        match kind {
+            SubmissionKind::Triangles { mut verts, indices, texture } => {
+                let cap = self.capacity;
+                if verts.len() > cap.tri_verts || indices.len() > cap.tri_indices {
+                    for (mut verts, indices) in split_mesh(&verts, &indices, cap.tri_verts, cap.tri_indices) {
+                        let cursor = self.tri_render_call_cursor(bb, verts.len(), indices.len(), &texture, blending, material.clone());
+                        if let BatchRenderCall::Triangles(call) = &mut self.render_calls[cursor] {
+                            call.push(&mut verts, &indices, texture.clone());
+                        }
+                    }
+                    return;
+                }
+
+                let cursor = self.tri_render_call_cursor(bb, verts.len(), indices.len(), &texture, blending, material);
+                if let BatchRenderCall::Triangles(call) = &mut self.render_calls[cursor] {
+                    call.push(&mut verts, &indices, texture);
+                }
+            },
+            SubmissionKind::Points { mut verts } => {
+                let cursor = self.pts_render_call_cursor(bb, verts.len(), blending, material);
+                if let BatchRenderCall::Points(call) = &mut self.render_calls[cursor] {
+                    call.push(&mut verts);
+                }
+            },
+            SubmissionKind::Lines { verts } => {
+                let cursor = self.lns_render_call_cursor(bb, blending, material);
                 if let BatchRenderCall::Lines(call) = &mut self.render_calls[cursor] {
                     call.push(verts);
                 }
             },
+            SubmissionKind::Instances { instances, texture } => {
+                for chunk in instances.chunks(self.capacity.instances) {
+                    let cursor = self.inst_render_call_cursor(bb, chunk.len(), &texture, blending, material.clone());
+                    if let BatchRenderCall::Instances(call) = &mut self.render_calls[cursor] {
+                        call.push(chunk);
+                    }
+                }
+            },
         }
     }
 
-    pub fn setup_frame(&mut self, mut camera: CameraData, target_res: uvec2) {
-        let snapping = vec2::from(target_res).inv_scale(camera.extents);
-        camera.center = snap(camera.center, snapping);
+    pub fn setup_frame(&mut self, viewports: &[Viewport], target_res: uvec2) {
+        self.capacity = Graphics::batch_capacity();
+
+        self.viewports.clear();
+        for viewport in viewports {
+            let mut camera = viewport.camera.clone();
+            let extents = camera.view_extents();
+            let snapping = vec2::from(target_res).scale(viewport.rect.size()).inv_scale(extents);
+            camera.center = snap(camera.center, snapping);
+
+            self.viewports.push(ViewportData {
+                view_mat: mat3::tf_matrix(camera.center, camera.rot(), extents.scale(vec2(1.0, -1.0) * 0.5)).inverse().unwrap_or(mat3::IDENTITY),
+                cam_rect: camera.rect(),
+                px_rect: viewport_px_rect(viewport.rect, target_res),
+                rect: viewport.rect,
+                camera,
+                snapping,
+                calls: 0..0,
+                stats: ViewportRenderStats::new(),
+            });
+        }
 
-        self.snapping = snapping;
-        self.view_mat = mat3::tf_matrix(camera.center, 0.0, camera.extents.scale(vec2(1.0, -1.0) * 0.5)).inverse().unwrap_or(mat3::IDENTITY);
-        self.cam_rect = Rect { start: camera.center - camera.extents * 0.5, end: camera.center + camera.extents * 0.5 };
-        self.camera = camera;
+        self.set_target_viewport(None);
         self.stats = BatchRenderStats::new();
         self.target_res = target_res;
 
@@ -121,22 +258,35 @@ impl BatchData {
     }
 
     fn clear(&mut self) {
+        self.submissions.clear();
+        self.call_bounds.clear();
+        self.calls_start = 0;
         self.pooled_buffers.clear();
         while let Some(call) = self.render_calls.pop() {
             match call {
                 BatchRenderCall::Triangles(call) => self.pooled_buffers.push_tri_buffer(call.recycle()),
                 BatchRenderCall::Points(call) => self.pooled_buffers.push_pts_buffer(call.recycle()),
                 BatchRenderCall::Lines(call) => self.pooled_buffers.push_lns_buffer(call.recycle()),
+                BatchRenderCall::Instances(call) => self.pooled_buffers.push_inst_buffer(call.recycle()),
             }
         }
     }
 
     pub fn render(&self, stats: &mut BatchRenderStats) {
         let mut on_use_size = 0;
-        for call in &self.render_calls {
-            call.render(&self.view_mat);
-            stats.draw_calls += 1;
-            on_use_size += call.on_use_size();
+        for viewport in &self.viewports {
+            let px_rect = viewport.px_rect;
+            if px_rect.start.0 >= px_rect.end.0 || px_rect.start.1 >= px_rect.end.1 {
+                continue;
+            }
+
+            gl_viewport(px_rect);
+            for call in &self.render_calls[viewport.calls.clone()] {
+                call.render(&viewport.view_mat);
+                stats.draw_calls += 1;
+                on_use_size += call.on_use_size();
+            }
+            stats.viewports.push(viewport.stats.clone());
         }
 
         stats.allocated_memory = ByteSize::new((self.render_calls.iter().map(|x| x.alloc_size()).sum::<usize>() + self.pooled_buffers.buffer_sizes()) as u64);
@@ -145,45 +295,112 @@ impl BatchData {
         *stats = stats.clone() + self.stats.clone();
     } 
 
+    /// Returns the camera of the target viewport, or of the first one if there's no target.
     pub fn camera(&self) -> CameraData {
-        self.camera.clone()
+        match self.viewports.get(self.target_viewport.unwrap_or(0)) {
+            Some(x) => x.camera.clone(),
+            None => CameraData::new(vec2::ZERO, vec2::ZERO),
+        }
+    }
+
+    pub fn viewports(&self) -> Vec<Viewport> {
+        self.viewports.iter().map(|x| Viewport::new(x.camera.clone(), x.rect)).collect()
+    }
+
+    pub fn viewport_count(&self) -> usize {
+        self.viewports.len()
+    }
+
+    pub fn target_viewport(&self) -> Option<usize> {
+        self.target_viewport
+    }
+
+    /// Restricts new submissions to a viewport. Snapping follows the target viewport, or the first one if there's no target.
+    pub fn set_target_viewport(&mut self, viewport: Option<usize>) {
+        self.target_viewport = viewport;
+        self.snapping = self.viewports.get(viewport.unwrap_or(0)).map(|x| x.snapping).unwrap_or(vec2::ONE);
+        self.cam_rect = match viewport {
+            Some(i) => self.viewports[i].cam_rect,
+            None => self.viewports.iter().map(|x| x.cam_rect).reduce(|a, b| Rect { start: a.start.min(b.start), end: a.end.max(b.end) }).unwrap_or(Rect::IDENT),
+        };
     }
 
     pub fn target_res(&self) -> uvec2 {
         self.target_res
     }
 
-    fn tri_render_call_cursor(&mut self, verts_len: usize, indices_len: usize, texture: &TextureHandle, blending: BlendingMode, material: Arc<Material>) -> usize {
-        if let Some(BatchRenderCall::Triangles(last)) = self.render_calls.last() {
-            if last.allows(verts_len, indices_len, texture, blending, &material) {
-                return self.render_calls.len() - 1;
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth;
+    }
+
+    /// Returns the call a submission can be merged into. Only the last call is considered unless merging out of order, in which case earlier calls are valid as long as no call in between overlaps `bb`.
+    fn merge_target(&mut self, bb: Rect, compatible: impl Fn(&BatchRenderCall) -> bool) -> Option<usize> {
+        const MERGE_LOOKBACK: usize = 16;
+
+        let lookback = if self.merge_out_of_order { MERGE_LOOKBACK } else { 1 };
+        for i in (self.calls_start..self.render_calls.len()).rev().take(lookback) {
+            if compatible(&self.render_calls[i]) {
+                if i + 1 != self.render_calls.len() {
+                    self.stats.out_of_order_merges += 1;
+                }
+                self.call_bounds[i].push(bb);
+                return Some(i);
+            }
+            if self.call_bounds[i].overlaps(bb) {
+                break;
             }
         }
-        let buffers = self.pooled_buffers.get_tri_buffer();
-        self.render_calls.push(BatchRenderCall::Triangles(TriBatchRenderCall::new(buffers, blending, material)));
+        return None;
+    }
+
+    fn push_call(&mut self, call: BatchRenderCall, bb: Rect) -> usize {
+        self.render_calls.push(call);
+        self.call_bounds.push(CallBounds::new(bb, self.merge_out_of_order));
         return self.render_calls.len() - 1;
     }
 
-    fn pts_render_call_cursor(&mut self, verts_len: usize, blending: BlendingMode, material: Arc<Material>) -> usize {
-        if let Some(BatchRenderCall::Points(last)) = self.render_calls.last() {
-            if last.allows(verts_len, blending, &material) {
-                return self.render_calls.len() - 1;
-            }
+    fn tri_render_call_cursor(&mut self, bb: Rect, verts_len: usize, indices_len: usize, texture: &TextureHandle, blending: BlendingMode, material: Arc<Material>) -> usize {
+        let target = self.merge_target(bb, |call| matches!(call, BatchRenderCall::Triangles(x) if x.allows(verts_len, indices_len, texture, blending, &material)));
+        if let Some(i) = target {
+            return i;
         }
-        let buffers = self.pooled_buffers.get_pts_buffer();
-        self.render_calls.push(BatchRenderCall::Points(PtsBatchRenderCall::new(buffers, blending, material)));
-        return self.render_calls.len() - 1;
+        let mut buffers = self.pooled_buffers.get_tri_buffer();
+        buffers.set_capacity(self.capacity.tri_verts, self.capacity.tri_indices);
+        return self.push_call(BatchRenderCall::Triangles(TriBatchRenderCall::new(buffers, blending, material)), bb);
     }
 
-    fn lns_render_call_cursor(&mut self, verts_len: usize, indices_len: usize, blending: BlendingMode, material: Arc<Material>) -> usize {
-        if let Some(BatchRenderCall::Lines(last)) = self.render_calls.last() {
-            if last.allows(verts_len, indices_len, blending, &material) {
-                return self.render_calls.len() - 1;
-            }
+    fn pts_render_call_cursor(&mut self, bb: Rect, verts_len: usize, blending: BlendingMode, material: Arc<Material>) -> usize {
+        let target = self.merge_target(bb, |call| matches!(call, BatchRenderCall::Points(x) if x.allows(verts_len, blending, &material)));
+        if let Some(i) = target {
+            return i;
         }
-        let buffers = self.pooled_buffers.get_lns_buffer();
-        self.render_calls.push(BatchRenderCall::Lines(LnsBatchRenderCall::new(buffers, blending, material)));
-        return self.render_calls.len() - 1;
+        let mut buffers = self.pooled_buffers.get_pts_buffer();
+        buffers.set_capacity(self.capacity.points);
+        return self.push_call(BatchRenderCall::Points(PtsBatchRenderCall::new(buffers, blending, material)), bb);
+    }
+
+    fn lns_render_call_cursor(&mut self, bb: Rect, blending: BlendingMode, material: Arc<Material>) -> usize {
+        let target = self.merge_target(bb, |call| matches!(call, BatchRenderCall::Lines(x) if x.allows(blending, &material)));
+        if let Some(i) = target {
+            return i;
+        }
+        let mut buffers = self.pooled_buffers.get_lns_buffer();
+        buffers.set_capacity(self.capacity.lines);
+        return self.push_call(BatchRenderCall::Lines(LnsBatchRenderCall::new(buffers, blending, material)), bb);
+    }
+
+    fn inst_render_call_cursor(&mut self, bb: Rect, instances_len: usize, texture: &TextureHandle, blending: BlendingMode, material: Arc<Material>) -> usize {
+        let target = self.merge_target(bb, |call| matches!(call, BatchRenderCall::Instances(x) if x.allows(instances_len, texture, blending, &material)));
+        if let Some(i) = target {
+            return i;
+        }
+        let mut buffers = self.pooled_buffers.get_inst_buffer();
+        buffers.set_capacity(self.capacity.instances);
+        return self.push_call(BatchRenderCall::Instances(InstBatchRenderCall::new(buffers, texture.clone(), blending, material)), bb);
     }
 }
 
@@ -205,7 +422,139 @@ pub enum BatchPushCmd<'a> {
         verts: [BatchVertex; 2],
         blending: BlendingMode,
         material: Arc<Material>,
+    },
+    Instances {
+        instances: &'a [InstanceVertex],
+        texture: TextureHandle,
+        blending: BlendingMode,
+        material: Arc<Material>,
+    },
+}
+
+
+/// How the submissions of a frame are ordered and batched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchSorting {
+    /// Within the same depth, submissions with a higher bottom edge are drawn first.
+    pub y_sort: bool,
+    /// Within the same depth (and y if `y_sort` is set), submissions are grouped by material and texture.
+    pub material_sort: bool,
+    /// Submissions can be merged into any earlier compatible call, unless a submission in between overlaps them.
+    pub merge_out_of_order: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SortKey {
+    depth: f32,
+    y: f32,
+}
+
+struct Submission {
+    key: SortKey,
+    bb: Rect,
+    culling_enabled: bool,
+    /// Restricts the submission to a single viewport.
+    viewport: Option<usize>,
+    kind: SubmissionKind,
+    blending: BlendingMode,
+    material: Arc<Material>,
+}
+
+#[derive(Clone)]
+enum SubmissionKind {
+    Triangles { verts: Vec<BatchVertex>, indices: Vec<u16>, texture: TextureHandle },
+    Points { verts: Vec<BatchVertex> },
+    Lines { verts: [BatchVertex; 2] },
+    Instances { instances: Vec<InstanceVertex>, texture: TextureHandle },
+}
+
+impl Submission {
+    /// Identifies the state a render call depends on, so equal submissions can be grouped together.
+    fn material_key(&self) -> (u8, usize, u8, usize) {
+        let (kind, texture) = match &self.kind {
+            SubmissionKind::Triangles { texture, .. } => (0, texture.sort_id()),
+            SubmissionKind::Points { .. } => (1, 0),
+            SubmissionKind::Lines { .. } => (2, 0),
+            SubmissionKind::Instances { texture, .. } => (3, texture.sort_id()),
+        };
+        return (kind, Arc::as_ptr(&self.material) as usize, self.blending as u8, texture);
+    }
+}
+
+/// Stable sorts the submissions. Higher depth is drawn first.
+fn sort_submissions(submissions: &mut [Submission], sorting: BatchSorting) {
+    let keys_only = |a: &SortKey, b: &SortKey| {
+        let ord = b.depth.total_cmp(&a.depth);
+        return if sorting.y_sort { ord.then(b.y.total_cmp(&a.y)) } else { ord };
+    };
+
+    let needs_sort = sorting.material_sort || submissions.windows(2).any(|x| keys_only(&x[0].key, &x[1].key).is_gt());
+    if !needs_sort {
+        return;
+    }
+
+    if sorting.material_sort {
+        submissions.sort_by(|a, b| keys_only(&a.key, &b.key).then_with(|| a.material_key().cmp(&b.material_key())));
+    } else {
+        submissions.sort_by(|a, b| keys_only(&a.key, &b.key));
+    }
+}
+
+
+/// Bounds of the submissions in a render call, to know if merging into it would change the result. Individual rects are only tracked while merging out of order, and only up to `MAX_RECTS` before falling back to the union.
+struct CallBounds {
+    union: Rect,
+    rects: Vec<Rect>,
+    tracked: bool,
+}
+
+impl CallBounds {
+    const MAX_RECTS: usize = 32;
+
+    fn new(bb: Rect, tracked: bool) -> Self {
+        Self { union: bb, rects: if tracked { vec![bb] } else { Vec::new() }, tracked }
+    }
+
+    fn push(&mut self, bb: Rect) {
+        self.union = Rect { start: self.union.start.min(bb.start), end: self.union.end.max(bb.end) };
+        if !self.tracked {
+            return;
+        }
+
+        if self.rects.len() < Self::MAX_RECTS {
+            self.rects.push(bb);
+        } else {
+            self.tracked = false;
+            self.rects = Vec::new();
+        }
     }
+
+    fn overlaps(&self, bb: Rect) -> bool {
+        if !aabb_check(self.union, bb) {
+            return false;
+        }
+        return !self.tracked || self.rects.iter().any(|x| aabb_check(*x, bb));
+    }
+}
+
+struct ViewportData {
+    camera: CameraData,
+    rect: Rect,
+    view_mat: mat3,
+    cam_rect: Rect,
+    px_rect: IRect,
+    snapping: vec2,
+    /// Render calls of the viewport.
+    calls: Range<usize>,
+    stats: ViewportRenderStats,
+}
+
+/// Converts a normalized viewport rect into pixels, with the origin at the left down corner like OpenGL.
+fn viewport_px_rect(rect: Rect, target_res: uvec2) -> IRect {
+    let res = vec2::from(target_res);
+    let start = vec2(rect.start.0, 1.0 - rect.end.1).scale(res).round();
+    let end = vec2(rect.end.0, 1.0 - rect.start.1).scale(res).round();
+    return IRect { start: ivec2(start.0 as i32, start.1 as i32), end: ivec2(end.0 as i32, end.1 as i32) };
 }
 
 
@@ -213,6 +562,7 @@ enum BatchRenderCall {
     Triangles(TriBatchRenderCall),
     Points(PtsBatchRenderCall),
     Lines(LnsBatchRenderCall),
+    Instances(InstBatchRenderCall),
 }
 
 impl BatchRenderCall {
@@ -221,6 +571,16 @@ impl BatchRenderCall {
             BatchRenderCall::Triangles(call) => call.render(view_mat),
             BatchRenderCall::Points(call) => call.render(view_mat),
             BatchRenderCall::Lines(call) => call.render(view_mat),
+            BatchRenderCall::Instances(call) => call.render(view_mat),
+        }
+    }
+
+    fn upload(&mut self) -> usize {
+        match self {
+            BatchRenderCall::Triangles(call) => call.upload(),
+            BatchRenderCall::Points(call) => call.upload(),
+            BatchRenderCall::Lines(call) => call.upload(),
+            BatchRenderCall::Instances(call) => call.upload(),
         }
     }
 
@@ -229,14 +589,16 @@ impl BatchRenderCall {
             BatchRenderCall::Triangles(call) => call.on_use_size(),
             BatchRenderCall::Points(call) => call.on_use_size(),
             BatchRenderCall::Lines(call) => call.on_use_size(),
+            BatchRenderCall::Instances(call) => call.on_use_size(),
         }
     }
 
     fn alloc_size(&self) -> usize {
         match self {
-            BatchRenderCall::Triangles(_) => TriBatchBuffers::BYTE_SIZE,
-            BatchRenderCall::Points(_) => PtsBatchBuffers::BYTE_SIZE,
-            BatchRenderCall::Lines(_) => LnsBatchBuffers::BYTE_SIZE,
+            BatchRenderCall::Triangles(call) => call.alloc_size(),
+            BatchRenderCall::Points(call) => call.alloc_size(),
+            BatchRenderCall::Lines(call) => call.alloc_size(),
+            BatchRenderCall::Instances(call) => call.alloc_size(),
         }
     }
 }
@@ -246,26 +608,26 @@ struct BuffersPool {
     tri_buffers: Vec<TriBatchBuffers>,
     pts_buffers: Vec<PtsBatchBuffers>,
     lns_buffers: Vec<LnsBatchBuffers>,
+    inst_buffers: Vec<InstBatchBuffers>,
 }
 
 impl BuffersPool {
     const fn new() -> Self {
-        Self { tri_buffers: Vec::new(), pts_buffers: Vec::new(), lns_buffers: Vec::new() }
+        Self { tri_buffers: Vec::new(), pts_buffers: Vec::new(), lns_buffers: Vec::new(), inst_buffers: Vec::new() }
     }
 
     fn clear(&mut self) {
         self.tri_buffers.clear();
         self.pts_buffers.clear();
         self.lns_buffers.clear();
+        self.inst_buffers.clear();
     }
 
     fn buffer_sizes(&self) -> usize {
-        const TRI_BATCH_BUFFER_SIZE: usize = size_of::<BatchVertex>() * TriBatchBuffers::MAX_VERTS + size_of::<u16>() * TriBatchBuffers::MAX_INDICES;
-        const PTS_BATCH_BUFFER_SIZE: usize = size_of::<BatchVertex>() * PtsBatchBuffers::MAX_PTS;
-        const LNS_BATCH_BUFFER_SIZE: usize = size_of::<BatchVertex>() * LnsBatchBuffers::MAX_VERTS + size_of::<u16>() * LnsBatchBuffers::MAX_INDICES;
-        return self.tri_buffers.len() * TRI_BATCH_BUFFER_SIZE +
-            self.pts_buffers.len() * PTS_BATCH_BUFFER_SIZE +
-            self.lns_buffers.len() * LNS_BATCH_BUFFER_SIZE;
+        return self.tri_buffers.iter().map(|x| x.alloc_size()).sum::<usize>() +
+            self.pts_buffers.iter().map(|x| x.alloc_size()).sum::<usize>() +
+            self.lns_buffers.iter().map(|x| x.alloc_size()).sum::<usize>() +
+            self.inst_buffers.iter().map(|x| x.alloc_size()).sum::<usize>();
     }
 
     fn get_tri_buffer(&mut self) -> TriBatchBuffers {
@@ -300,6 +662,17 @@ impl BuffersPool {
     fn push_lns_buffer(&mut self, buf: LnsBatchBuffers) {
         self.lns_buffers.push(buf);
     }
+
+    fn get_inst_buffer(&mut self) -> InstBatchBuffers {
+        match self.inst_buffers.pop() {
+            Some(x) => x,
+            None => InstBatchBuffers::new(),
+        }
+    }
+
+    fn push_inst_buffer(&mut self, buf: InstBatchBuffers) {
+        self.inst_buffers.push(buf);
+    }
 }
 
 
@@ -319,8 +692,70 @@ fn calculate_bounding_box(verts: &[BatchVertex]) -> Rect {
     return Rect { start: min, end: max };
 }
 
+fn calculate_instances_bounding_box(instances: &[InstanceVertex]) -> Rect {
+    let mut min = vec2::one(f32::INFINITY);
+    let mut max = vec2::one(f32::NEG_INFINITY);
+
+    for corner in instances.iter().flat_map(|x| x.corners()) {
+        min = min.min(corner);
+        max = max.max(corner);
+    }
+
+    return Rect { start: min, end: max };
+}
+
 fn aabb_check(a: Rect, b: Rect) -> bool {
     return
         a.start.0 < b.end.0 && b.start.0 < a.end.0 &&
         a.start.1 < b.end.1 && b.start.1 < a.end.1;
 }
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::{rect::Rect, vector2::{ivec2, uvec2, vec2}};
+
+    use crate::graphics::Viewport;
+
+    use super::{viewport_px_rect, CallBounds};
+
+    #[test]
+    fn viewport_rects() {
+        let res = uvec2(640, 480);
+        let px = |rect| {
+            let px = viewport_px_rect(rect, res);
+            return (px.start, px.end);
+        };
+        assert_eq!(px(Viewport::FULL), (ivec2::ZERO, ivec2(640, 480)));
+
+        // Left up quarter of the screen is the left up quarter in GL too, where y goes up
+        let quarters = Viewport::split(4);
+        assert_eq!(quarters.len(), 4);
+        assert_eq!(px(quarters[0]), (ivec2(0, 240), ivec2(320, 480)));
+        assert_eq!(px(quarters[3]), (ivec2(320, 0), ivec2(640, 240)));
+    }
+
+    #[test]
+    fn call_bounds() {
+        let rect = |x: f32, y: f32| Rect { start: vec2(x, y), end: vec2(x + 1.0, y + 1.0) };
+
+        // Gaps between tracked rects don't overlap, but the union does
+        let mut bounds = CallBounds::new(rect(0.0, 0.0), true);
+        bounds.push(rect(4.0, 4.0));
+        assert!(!bounds.overlaps(rect(2.0, 2.0)));
+        assert!(bounds.overlaps(rect(4.5, 4.5)));
+        assert!(!bounds.overlaps(rect(8.0, 8.0)));
+
+        let mut untracked = CallBounds::new(rect(0.0, 0.0), false);
+        untracked.push(rect(4.0, 4.0));
+        assert!(untracked.rects.is_empty());
+        assert!(untracked.overlaps(rect(2.0, 2.0)));
+
+        // Past the cap only the union is kept
+        for i in 0..CallBounds::MAX_RECTS {
+            bounds.push(rect(i as f32 * 2.0, 0.0));
+        }
+        assert!(bounds.rects.len() <= CallBounds::MAX_RECTS);
+        assert!(bounds.overlaps(rect(2.0, 2.0)));
+    }
+}
@@ -53,35 +53,54 @@ impl PtsBatchRenderCall {
     pub fn on_use_size(&self) -> usize {
         self.buffers.on_use_size()
     }
+
+    pub fn alloc_size(&self) -> usize {
+        self.buffers.alloc_size()
+    }
+
+    /// Returns the uploaded bytes.
+    pub fn upload(&mut self) -> usize {
+        self.buffers.upload()
+    }
 }
 
 
 pub struct PtsBatchBuffers {
+    verts: Vec<BatchVertex>,
     vbo: GlBuffer,
-    vlen: usize,
     vao: GlVertexArray,
+    max_pts: usize,
 }
 
 impl PtsBatchBuffers {
-    pub const MAX_PTS: usize = 64;
-    pub const BYTE_SIZE: usize = Self::MAX_PTS * size_of::<BatchVertex>();
+    const INITIAL_PTS: usize = 64;
 
     pub fn new() -> Self {
         let mut item = Self {
-            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::MAX_PTS * size_of::<BatchVertex>()) as isize, GlBufferUsage::DynamicDraw),
+            verts: Vec::new(),
+            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::INITIAL_PTS * size_of::<BatchVertex>()) as isize, GlBufferUsage::StreamDraw),
             vao: GlVertexArray::new(),
-            vlen: 0,
+            max_pts: 0,
         };
         item.vao.bind_vbo(&item.vbo, BatchVertex::VERT_ATTRIB_DEFINITIONS);
         return item;
     }
 
+    /// Sets the limits of the batch. Buffers grow on demand until them.
+    pub fn set_capacity(&mut self, max_pts: usize) {
+        self.max_pts = max_pts;
+    }
+
     fn on_use_size(&self) -> usize {
-        self.vlen * size_of::<BatchVertex>()
+        self.verts.len() * size_of::<BatchVertex>()
+    }
+
+    pub fn alloc_size(&self) -> usize {
+        self.vbo.size() + self.verts.capacity() * size_of::<BatchVertex>()
     }
 
     fn fits(&self, verts: usize) -> bool {
-        return self.vlen + verts <= Self::MAX_PTS;
+        return self.verts.len() + verts <= self.max_pts;
     }
 
     fn push(&mut self, verts: &[BatchVertex]) {
@@ -89,17 +108,26 @@ impl PtsBatchBuffers {
             return;
         }
 
-        self.vbo.set(to_byte_slice(verts), (self.vlen * size_of::<BatchVertex>()) as isize);
-        self.vlen += verts.len();
+        self.verts.extend_from_slice(verts);
+    }
+
+    /// Uploads the whole batch at once. Returns the uploaded bytes.
+    fn upload(&mut self) -> usize {
+        if self.verts.is_empty() {
+            return 0;
+        }
+
+        self.vbo.orphan_set(to_byte_slice(&self.verts));
+        return self.on_use_size();
     }
 
     /// Returns the verts count.
     fn bind_all(&self) -> i32 {
         self.vao.bind();
-        return self.vlen as i32;
+        return self.verts.len() as i32;
     }
 
     fn clear(&mut self) {
-        self.vlen = 0;
+        self.verts.clear();
     }
 }
@@ -2,32 +2,40 @@ use std::sync::Arc;
 
 use nogine2_core::{log_error, math::mat3x3::mat3};
 
-use crate::{gl_wrapper::{buffer::{GlBuffer, GlBufferTarget, GlBufferUsage}, gl_render_elements, gl_uniform, to_byte_slice, vao::GlVertexArray, GlRenderMode}, graphics::{blending::BlendingMode, material::Material, texture::TextureHandle, vertex::BatchVertex}};
+use crate::{gl_wrapper::{buffer::{GlBuffer, GlBufferTarget, GlBufferUsage}, gl_render_elements, gl_uniform, to_byte_slice, vao::GlVertexArray, GlIndexType, GlRenderMode}, graphics::{blending::BlendingMode, material::Material, texture::TextureHandle, vertex::BatchVertex}};
+
+use super::indices::BatchIndices;
 
 pub struct TriBatchRenderCall {
     buffers: TriBatchBuffers,
     textures: Vec<TextureHandle>,
+    arrays: Vec<TextureHandle>,
     blending: BlendingMode,
     material: Arc<Material>,
     tex_offset: usize,
+    /// Slots of `uTextures` and `uTextureArrays`.
+    max_textures: usize,
+    max_arrays: usize,
 }
 
 impl TriBatchRenderCall {
-    const MAX_TEXTURES: usize = 16;
-    const TEXTURES: [i32; Self::MAX_TEXTURES] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
-    
     pub fn new(buffers: TriBatchBuffers, blending: BlendingMode, material: Arc<Material>) -> Self {
+        let (max_textures, max_arrays) = material.batch_slots();
+
         Self {
             buffers,
             textures: Vec::new(),
+            arrays: Vec::new(),
             blending,
             tex_offset: material.sampler_count(),
             material,
+            max_textures,
+            max_arrays,
         }
     }
 
     pub fn render(&self, view_mat: &mat3) {
-        let indices_len = self.buffers.bind_all();
+        let (indices_len, index_type) = self.buffers.bind_all();
 
         if !self.material.use_material() {
             log_error!("GL_ERROR: Couldn't render!");
@@ -38,18 +46,17 @@ impl TriBatchRenderCall {
         for (i, t) in self.textures.iter().enumerate() {
             t.bind_to((i + sampler_count) as u32); // offseted to avoid uniform samplers
         }
+        for (i, t) in self.arrays.iter().enumerate() {
+            t.bind_to((i + self.max_textures) as u32); // arrays go after all the uTextures units
+        }
     
         if let Some(view_mat_loc) = self.material.uniform_loc(c"uViewMat") {
             gl_uniform::set_mat3(view_mat_loc, view_mat);
         }
 
-        if let Some(textures_loc) = self.material.uniform_loc(c"uTextures") {
-            gl_uniform::set_i32_arr(textures_loc, &Self::TEXTURES);
-        }
-
         self.blending.apply();
 
-        gl_render_elements(GlRenderMode::GlTriangles, indices_len);
+        gl_render_elements(GlRenderMode::GlTriangles, indices_len, index_type);
     }
 
     /// Clears the buffers first
@@ -59,23 +66,22 @@ impl TriBatchRenderCall {
     }
 
     pub fn allows(&self, verts_len: usize, indices_len: usize, texture: &TextureHandle, blending: BlendingMode, material: &Arc<Material>) -> bool {
+        let has_slot = match texture.layer() {
+            None => self.textures.len() + self.tex_offset < self.max_textures || self.textures.iter().any(|t| t.same_texture(texture)),
+            Some(_) => self.arrays.len() < self.max_arrays || self.arrays.iter().any(|t| t.same_texture(texture)),
+        };
+
         self.buffers.fits(verts_len, indices_len)
-            && (
-                self.textures.len() + self.tex_offset < Self::MAX_TEXTURES
-                    || self.textures.contains(texture)
-            )
+            && has_slot
             && self.blending == blending
             && *self.material == **material
     }
 
-    pub fn push(&mut self, verts: &mut [BatchVertex], indices: &mut [u16], texture: TextureHandle) {
-        let tex_id = match self.textures.iter().position(|t| t == &texture) {
-            Some(i) => i as u32,
-            None => {
-                self.textures.push(texture);
-                (self.textures.len() - 1) as u32
-            },
-        } + self.tex_offset as u32;
+    pub fn push(&mut self, verts: &mut [BatchVertex], indices: &[u16], texture: TextureHandle) {
+        let tex_id = match texture.layer() {
+            None => slot_of(&mut self.textures, texture) + self.tex_offset as u32,
+            Some(layer) => (slot_of(&mut self.arrays, texture) + self.max_textures as u32) | (layer << 16),
+        };
 
         for v in &mut *verts {
             v.tex_id = tex_id;
@@ -87,66 +93,101 @@ impl TriBatchRenderCall {
     pub fn on_use_size(&self) -> usize {
         self.buffers.on_use_size()
     }
+
+    pub fn alloc_size(&self) -> usize {
+        self.buffers.alloc_size()
+    }
+
+    /// Returns the uploaded bytes.
+    pub fn upload(&mut self) -> usize {
+        self.buffers.upload()
+    }
 }
 
 
+/// Returns the slot of `texture`, adding it if needed.
+fn slot_of(textures: &mut Vec<TextureHandle>, texture: TextureHandle) -> u32 {
+    match textures.iter().position(|t| t.same_texture(&texture)) {
+        Some(i) => i as u32,
+        None => {
+            textures.push(texture);
+            (textures.len() - 1) as u32
+        },
+    }
+}
+
 pub struct TriBatchBuffers {
+    verts: Vec<BatchVertex>,
+    indices: BatchIndices,
     vbo: GlBuffer,
-    ebo: GlBuffer,
-    vlen: usize,
-    elen: usize,
     vao: GlVertexArray,
+    max_verts: usize,
+    max_indices: usize,
 }
 
 impl TriBatchBuffers {
-    const MAX_QUADS: usize = 256;
-    pub const MAX_VERTS: usize = Self::MAX_QUADS * 4;
-    pub const MAX_INDICES: usize = Self::MAX_QUADS * 6;
-    pub const BYTE_SIZE: usize = Self::MAX_VERTS * size_of::<BatchVertex>() + Self::MAX_INDICES * size_of::<u16>();
+    const INITIAL_VERTS: usize = 64;
 
     pub fn new() -> Self {
         let mut item = Self {
-            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::MAX_VERTS * size_of::<BatchVertex>()) as isize, GlBufferUsage::DynamicDraw),
-            ebo: GlBuffer::preallocated(GlBufferTarget::GlElementArrayBuffer, (Self::MAX_INDICES * size_of::<u16>()) as isize, GlBufferUsage::DynamicDraw),
+            verts: Vec::new(),
+            indices: BatchIndices::new(),
+            vbo: GlBuffer::preallocated(GlBufferTarget::GlArrayBuffer, (Self::INITIAL_VERTS * size_of::<BatchVertex>()) as isize, GlBufferUsage::StreamDraw),
             vao: GlVertexArray::new(),
-            vlen: 0, elen: 0,
+            max_verts: 0, max_indices: 0,
         };
         item.vao.bind_vbo(&item.vbo, BatchVertex::VERT_ATTRIB_DEFINITIONS);
         return item;
     }
 
+    /// Sets the limits of the batch. Buffers grow on demand until them.
+    pub fn set_capacity(&mut self, max_verts: usize, max_indices: usize) {
+        self.max_verts = max_verts;
+        self.max_indices = max_indices;
+    }
+
     fn on_use_size(&self) -> usize {
-        self.vlen * size_of::<BatchVertex>() + self.elen * size_of::<u16>()
+        self.verts.len() * size_of::<BatchVertex>() + self.indices.on_use_size()
+    }
+
+    pub fn alloc_size(&self) -> usize {
+        self.vbo.size() + self.verts.capacity() * size_of::<BatchVertex>() + self.indices.alloc_size()
     }
 
     fn fits(&self, verts: usize, indices: usize) -> bool {
-        return self.vlen + verts <= Self::MAX_VERTS && self.elen + indices <= Self::MAX_INDICES;
+        return self.verts.len() + verts <= self.max_verts && self.indices.len() + indices <= self.max_indices;
     }
 
-    fn push(&mut self, verts: &[BatchVertex], indices: &mut [u16]) {
+    fn push(&mut self, verts: &[BatchVertex], indices: &[u16]) {
         if !self.fits(verts.len(), indices.len()) {
+            log_error!("NOGINE2: Triangle batch overflow, submission dropped!");
             return;
         }
 
-        for i in &mut *indices {
-            *i += self.vlen as u16;
+        let offset = self.verts.len() as u32;
+        self.indices.extend(indices.iter().map(|i| *i as u32 + offset));
+        self.verts.extend_from_slice(verts);
+    }
+
+    /// Uploads the whole batch at once. Returns the uploaded bytes.
+    fn upload(&mut self) -> usize {
+        if self.indices.is_empty() {
+            return 0;
         }
-        self.vbo.set(to_byte_slice(verts), (self.vlen * size_of::<BatchVertex>()) as isize);
-        self.ebo.set(to_byte_slice(indices), (self.elen * size_of::<u16>()) as isize);
 
-        self.vlen += verts.len();
-        self.elen += indices.len();
+        self.vbo.orphan_set(to_byte_slice(&self.verts));
+        return self.verts.len() * size_of::<BatchVertex>() + self.indices.upload(self.verts.len());
     }
 
-    /// Returns the indices count.
-    fn bind_all(&self) -> i32 {
+    /// Returns the indices count and type.
+    fn bind_all(&self) -> (i32, GlIndexType) {
         self.vao.bind();
-        self.ebo.bind();
-        return self.elen as i32;
+        self.indices.bind();
+        return (self.indices.len() as i32, self.indices.index_type());
     }
 
     fn clear(&mut self) {
-        self.vlen = 0;
-        self.elen = 0;
+        self.verts.clear();
+        self.indices.clear();
     }
 }
@@ -0,0 +1,232 @@
+use nogine2_core::math::{lerp::Lerp, rect::Rect, vector2::vec2};
+
+use super::CameraData;
+
+/// Configuration for trauma based screen shake.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShakeCfg {
+    /// Offset in units at full trauma.
+    pub max_offset: vec2,
+    /// Rotation in radians at full trauma.
+    pub max_rot: f32,
+    /// Speed of the shake.
+    pub frequency: f32,
+    /// Trauma lost per second.
+    pub decay: f32,
+}
+
+impl Default for ShakeCfg {
+    fn default() -> Self {
+        Self { max_offset: vec2::one(0.5), max_rot: 0.05, frequency: 15.0, decay: 1.0 }
+    }
+}
+
+
+/// Helper for gameplay cameras, with smooth following, deadzones, bounds clamping and screen shake. Call `update` every frame and pass `camera_data` to the frame setup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera2D {
+    /// Center of the camera, without shake.
+    pub position: vec2,
+    /// Size of the view at zoom `1.0`.
+    pub extents: vec2,
+    /// Rotation in radians, counter-clockwise.
+    pub rot: f32,
+    /// Zoom factor. Values over `1.0` zoom in.
+    pub zoom: f32,
+
+    target: Option<vec2>,
+    smoothing: f32,
+    deadzone: vec2,
+    bounds: Option<Rect>,
+
+    shake: ShakeCfg,
+    trauma: f32,
+    time: f32,
+    shake_offset: vec2,
+    shake_rot: f32,
+}
+
+impl Camera2D {
+    pub fn new(position: vec2, extents: vec2) -> Self {
+        Self {
+            position, extents, rot: 0.0, zoom: 1.0,
+            target: None, smoothing: 0.0, deadzone: vec2::ZERO, bounds: None,
+            shake: ShakeCfg::default(), trauma: 0.0, time: 0.0, shake_offset: vec2::ZERO, shake_rot: 0.0,
+        }
+    }
+
+    /// Sets how fast the camera catches up with its target. `0.0` follows instantly.
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.set_smoothing(smoothing);
+        return self;
+    }
+
+    /// Sets the size of the area around the center where the target can move without the camera following.
+    pub fn with_deadzone(mut self, deadzone: vec2) -> Self {
+        self.set_deadzone(deadzone);
+        return self;
+    }
+
+    /// Sets the area the view must stay inside of.
+    pub fn with_bounds(mut self, bounds: Rect) -> Self {
+        self.set_bounds(Some(bounds));
+        return self;
+    }
+
+    pub fn with_shake(mut self, shake: ShakeCfg) -> Self {
+        self.shake = shake;
+        return self;
+    }
+
+    pub fn smoothing(&self) -> f32 {
+        self.smoothing
+    }
+
+    /// Sets how fast the camera catches up with its target. `0.0` follows instantly.
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.max(0.0);
+    }
+
+    pub fn deadzone(&self) -> vec2 {
+        self.deadzone
+    }
+
+    /// Sets the size of the area around the center where the target can move without the camera following.
+    pub fn set_deadzone(&mut self, deadzone: vec2) {
+        self.deadzone = deadzone.max(vec2::ZERO);
+    }
+
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Sets the area the view must stay inside of. If the view is bigger than the bounds, it's centered on them.
+    pub fn set_bounds(&mut self, bounds: Option<Rect>) {
+        self.bounds = bounds;
+    }
+
+    pub fn shake(&self) -> ShakeCfg {
+        self.shake
+    }
+
+    pub fn set_shake(&mut self, shake: ShakeCfg) {
+        self.shake = shake;
+    }
+
+    pub fn target(&self) -> Option<vec2> {
+        self.target
+    }
+
+    /// Sets the position to follow, or `None` to stop following.
+    pub fn follow(&mut self, target: Option<vec2>) {
+        self.target = target;
+    }
+
+    /// Moves the camera to `position` immediately, ignoring smoothing.
+    pub fn teleport(&mut self, position: vec2) {
+        self.position = position;
+        self.position = self.clamp_to_bounds(self.position);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Adds trauma, clamped to `1.0`. Shake grows with the square of the trauma.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Advances the camera by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(target) = self.target {
+            let desired = self.deadzone_target(target);
+            let t = if self.smoothing > 0.0 { 1.0 - (-self.smoothing * dt).exp() } else { 1.0 };
+            self.position = self.position.lerp(desired, t);
+        }
+        self.position = self.clamp_to_bounds(self.position);
+
+        self.time += dt;
+        self.trauma = (self.trauma - self.shake.decay * dt).max(0.0);
+
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.shake.frequency;
+        self.shake_offset = self.shake.max_offset.scale(vec2(noise(t, 0.0), noise(t, 17.0))) * shake;
+        self.shake_rot = self.shake.max_rot * noise(t, 43.0) * shake;
+    }
+
+    /// Returns the camera to render with, including shake.
+    pub fn camera_data(&self) -> CameraData {
+        CameraData::new(self.position + self.shake_offset, self.extents).with_rot(self.rot + self.shake_rot).with_zoom(self.zoom)
+    }
+
+    /// Returns the closest position to the current one that keeps `target` inside of the deadzone.
+    fn deadzone_target(&self, target: vec2) -> vec2 {
+        let half = self.deadzone * 0.5;
+        let diff = target - self.position;
+        let axis = |d: f32, h: f32| if d > h { d - h } else if d < -h { d + h } else { 0.0 };
+        return self.position + vec2(axis(diff.0, half.0), axis(diff.1, half.1));
+    }
+
+    fn clamp_to_bounds(&self, position: vec2) -> vec2 {
+        let Some(bounds) = self.bounds else {
+            return position;
+        };
+
+        let half = CameraData::new(vec2::ZERO, self.extents).with_rot(self.rot).with_zoom(self.zoom).rect().end;
+        let axis = |p: f32, min: f32, max: f32, h: f32| if max - min < h * 2.0 { (min + max) * 0.5 } else { p.clamp(min + h, max - h) };
+        return vec2(
+            axis(position.0, bounds.start.0, bounds.end.0, half.0),
+            axis(position.1, bounds.start.1, bounds.end.1, half.1),
+        );
+    }
+}
+
+/// Smooth pseudo-random value in the range [-1, 1].
+fn noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() + (t * 2.17 + seed * 1.3).sin() * 0.5 + (t * 4.63 + seed * 0.7).sin() * 0.25) / 1.75
+}
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::{rect::Rect, vector2::vec2};
+
+    use super::Camera2D;
+
+    #[test]
+    fn deadzone_and_bounds() {
+        let mut cam = Camera2D::new(vec2::ZERO, vec2(4.0, 2.0)).with_deadzone(vec2(2.0, 2.0));
+
+        cam.follow(Some(vec2(0.5, 0.0)));
+        cam.update(0.1);
+        assert_eq!(cam.position, vec2::ZERO);
+
+        cam.follow(Some(vec2(3.0, -2.0)));
+        cam.update(0.1);
+        assert_eq!(cam.position, vec2(2.0, -1.0));
+
+        cam.set_bounds(Some(Rect { start: vec2(-1.0, -1.0), end: vec2(3.0, 10.0) }));
+        cam.update(0.1);
+        assert_eq!(cam.position, vec2(1.0, 0.0));
+
+        // Bounds thinner than the view center it
+        cam.set_bounds(Some(Rect { start: vec2(0.0, 0.0), end: vec2(2.0, 10.0) }));
+        cam.update(0.1);
+        assert_eq!(cam.position.0, 1.0);
+    }
+
+    #[test]
+    fn trauma_decays() {
+        let mut cam = Camera2D::new(vec2::ZERO, vec2::ONE);
+        cam.add_trauma(2.0);
+        assert_eq!(cam.trauma(), 1.0);
+
+        cam.update(0.5);
+        assert!((cam.trauma() - 0.5).abs() < 1e-6);
+
+        cam.update(1.0);
+        assert_eq!(cam.trauma(), 0.0);
+        assert_eq!(cam.camera_data().center, vec2::ZERO);
+    }
+}
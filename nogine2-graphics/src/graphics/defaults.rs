@@ -2,10 +2,11 @@ use std::sync::{Arc, RwLock};
 
 use nogine2_core::crash;
 
-use super::{material::Material, shader::{Shader, SubShader, SubShaderType}};
+use super::{material::Material, shader::{Shader, SubShader, SubShaderType}, Graphics};
 
 const BATCH_VERT_SRC: &[u8] = include_bytes!("../../shaders/batch.vert");
-const BATCH_FRAG_SRC: &[u8] = include_bytes!("../../shaders/batch.frag");
+/// Template of the batch fragment shader, see `batch_frag_src`.
+const BATCH_FRAG_SRC: &str = include_str!("../../shaders/batch.frag");
 
 const BLIT_VERT_SRC: &[u8] = include_bytes!("../../shaders/blit.vert");
 const BLIT_FRAG_SRC: &[u8] = include_bytes!("../../shaders/blit.frag");
@@ -27,7 +28,7 @@ pub struct DefaultSubShaders {
 impl DefaultSubShaders {
     pub(crate) fn init() -> bool {
         let Some(batch_vert) = SubShader::new(BATCH_VERT_SRC, SubShaderType::Vertex) else { return false };
-        let Some(batch_frag) = SubShader::new(BATCH_FRAG_SRC, SubShaderType::Fragment) else { return false };
+        let Some(batch_frag) = SubShader::new(batch_frag_src(Graphics::max_texture_units()).as_bytes(), SubShaderType::Fragment) else { return false };
 
         let Some(blit_vert) = SubShader::new(BLIT_VERT_SRC, SubShaderType::Vertex) else { return false };
         let Some(blit_frag) = SubShader::new(BLIT_FRAG_SRC, SubShaderType::Fragment) else { return false };
@@ -67,6 +68,35 @@ impl DefaultSubShaders {
 }
 
 
+/// Upper limit of texture units used by the batch shader, to keep the generated switch reasonable.
+pub(crate) const MAX_BATCH_UNITS: usize = 64;
+
+/// Splits `units` texture units into `sampler2D` and `sampler2DArray` slots for the batch shader.
+fn batch_texture_slots(units: usize) -> (usize, usize) {
+    let units = units.clamp(2, MAX_BATCH_UNITS);
+    let arrays = (units / 8).max(1);
+    return (units - arrays, arrays);
+}
+
+/// Generates the batch fragment shader for `units` texture units.
+fn batch_frag_src(units: usize) -> String {
+    let (textures, arrays) = batch_texture_slots(units);
+
+    let mut cases = String::new();
+    for i in 0..textures {
+        cases += &format!("        TEX_CASE({i});\n");
+    }
+    for i in 0..arrays {
+        cases += &format!("        TEX_ARRAY_CASE({i});\n");
+    }
+
+    return BATCH_FRAG_SRC
+        .replace("$MAX_TEXTURES", &textures.to_string())
+        .replace("$MAX_TEXTURE_ARRAYS", &arrays.to_string())
+        .replace("$TEX_CASES\n", &cases);
+}
+
+
 /// Singleton containing all the default shaders.
 pub struct DefaultShaders {
     batch: Arc<Shader>,
@@ -135,3 +165,23 @@ impl DefaultMaterials {
         materials.blit.clone()
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::{batch_frag_src, batch_texture_slots};
+
+    #[test]
+    fn batch_frag_generation() {
+        assert_eq!(batch_texture_slots(16), (14, 2));
+        assert_eq!(batch_texture_slots(32), (28, 4));
+        assert_eq!(batch_texture_slots(192), (56, 8));
+
+        let src = batch_frag_src(32);
+        assert!(src.contains("#define MAX_TEXTURES 28\n"));
+        assert!(src.contains("#define MAX_TEXTURE_ARRAYS 4\n"));
+        assert!(src.contains("        TEX_CASE(27);\n        TEX_ARRAY_CASE(0);\n"));
+        assert!(!src.contains("TEX_CASE(28)") && !src.contains("TEX_ARRAY_CASE(4)"));
+        assert!(!src.contains('$'));
+    }
+}
@@ -1,6 +1,6 @@
 use nogine2_core::math::{rect::IRect, vector2::{ivec2, vec2}};
 
-use super::{pipeline::RenderStats, texture::rendertex::RenderTexture, Graphics};
+use super::{pipeline::RenderStats, rotate, texture::rendertex::RenderTexture, Graphics, Viewport};
 
 /// Transfer the data from `src` to `dst` retaining integer scaling for pixels.
 pub fn integer_scaling_blit(src: &RenderTexture, dst: &RenderTexture, stats: &mut RenderStats) {
@@ -19,20 +19,31 @@ mod integer_scaling {
     }
 }
 
-/// Converts screen space positions to world space positions.
-pub fn screen_to_world_pos(pos: vec2) -> vec2 {
-    let cam = Graphics::camera();
-    let half_res = vec2::from(Graphics::target_res()) * 0.5;
+/// Returns the index of the viewport under a screen space position, if any.
+pub fn viewport_at(pos: vec2) -> Option<usize> {
+    let norm = pos.inv_scale(vec2::from(Graphics::target_res()));
+    return Graphics::viewports().iter().rposition(|x| x.contains(norm));
+}
 
-    let unit_space = (pos.inv_scale(half_res) - vec2::ONE).scale(vec2(1.0, -1.0)); // -1 to 1
-    return unit_space.scale(cam.extents * 0.5) + cam.center;
+/// Converts screen space positions to world space positions, using the camera of the viewport under `pos`.
+pub fn screen_to_world_pos(pos: vec2) -> vec2 {
+    let norm = pos.inv_scale(vec2::from(Graphics::target_res()));
+    let viewport = match viewport_at(pos) {
+        Some(i) => Graphics::viewports().swap_remove(i),
+        None => Viewport::new(Graphics::camera(), Viewport::FULL),
+    };
+
+    let local = (norm - viewport.rect.start).inv_scale(viewport.rect.size());
+    let unit_space = (local * 2.0 - vec2::ONE).scale(vec2(1.0, -1.0)); // -1 to 1
+    return viewport.camera.view_to_world(unit_space);
 }
 
-/// Converts screen position space delta to world space position delta.
+/// Converts screen position space delta to world space position delta, using the camera of the target viewport (or the first one).
 pub fn screen_to_world_delta(delta: vec2) -> vec2 {
     let cam = Graphics::camera();
-    let res = vec2::from(Graphics::target_res());
+    let rect = Graphics::viewports().get(Graphics::target_viewport().unwrap_or(0)).map(|x| x.rect).unwrap_or(Viewport::FULL);
+    let res = vec2::from(Graphics::target_res()).scale(rect.size());
 
     let unit_space = delta.inv_scale(res).scale(vec2(1.0, -1.0)); // 0 to 1
-    return unit_space.scale(cam.extents);
+    return rotate(unit_space.scale(cam.view_extents()), cam.rot());
 }
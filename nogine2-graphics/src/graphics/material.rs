@@ -66,6 +66,10 @@ impl Material {
     pub(crate) fn sampler_count(&self) -> usize {
         self.shader.sampler_count()
     }
+
+    pub(crate) fn batch_slots(&self) -> (usize, usize) {
+        self.shader.batch_slots()
+    }
 }
 
 unsafe impl Sync for Material { }
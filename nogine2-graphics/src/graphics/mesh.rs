@@ -0,0 +1,164 @@
+use nogine2_core::{assert_expr, math::{polygon, rect::Rect, vector2::vec2}};
+
+use crate::colors::rgba::RGBA32;
+
+use super::{texture::TextureHandle, vertex::BatchVertex, WHITE_TEX};
+
+/// Retained geometry, drawn with `Graphics::draw_mesh_2d`. Vertex positions are in local space. **Must only be used on the main thread!**
+#[derive(Debug, Clone)]
+pub struct Mesh2D {
+    verts: Vec<BatchVertex>,
+    indices: Vec<u32>,
+    texture: TextureHandle,
+}
+
+impl Mesh2D {
+    /// Creates a mesh. Uses a white texture if `texture` is `None`. Will panic if the indices don't form triangles or are out of bounds.
+    pub fn new(verts: Vec<BatchVertex>, indices: Vec<u32>, texture: Option<TextureHandle>) -> Self {
+        assert_mesh(&verts, &indices);
+        return Self { verts, indices, texture: texture.unwrap_or_else(|| WHITE_TEX.get()) };
+    }
+
+    /// Triangulates a polygon into a mesh. UVs cover the bounding box of the outline.
+    pub fn from_polygon(points: &[vec2], holes: &[&[vec2]], color: RGBA32, texture: Option<TextureHandle>) -> Self {
+        let all_points = points.iter().chain(holes.iter().flat_map(|x| x.iter())).copied().collect::<Vec<_>>();
+        let indices = polygon::triangulate(points, holes);
+
+        let bb = all_points.iter().fold(
+            Rect { start: vec2::one(f32::INFINITY), end: vec2::one(f32::NEG_INFINITY) },
+            |r, p| Rect { start: r.start.min(*p), end: r.end.max(*p) }
+        );
+        let size = bb.size().max(vec2::one(f32::EPSILON));
+
+        let verts = all_points.into_iter().map(|pos| {
+            let uv = (pos - bb.start).inv_scale(size);
+            BatchVertex { pos, tint: color, uv, uv1: uv, ..Default::default() }
+        }).collect();
+
+        return Self::new(verts, indices, texture);
+    }
+
+    pub fn verts(&self) -> &[BatchVertex] {
+        &self.verts
+    }
+
+    /// Allows editing the vertices in place, for deformable meshes.
+    pub fn verts_mut(&mut self) -> &mut [BatchVertex] {
+        &mut self.verts
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Replaces the geometry. Will panic if the indices don't form triangles or are out of bounds.
+    pub fn set_geometry(&mut self, verts: Vec<BatchVertex>, indices: Vec<u32>) {
+        assert_mesh(&verts, &indices);
+        self.verts = verts;
+        self.indices = indices;
+    }
+
+    pub fn texture(&self) -> &TextureHandle {
+        &self.texture
+    }
+
+    pub fn set_texture(&mut self, texture: TextureHandle) {
+        self.texture = texture;
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+/// Most vertices a part of a mesh can have to be indexed with `u16`.
+pub(crate) const MAX_PART_VERTS: usize = u16::MAX as usize + 1;
+
+pub(crate) fn assert_mesh<I: Copy + Into<u32>>(verts: &[BatchVertex], indices: &[I]) {
+    assert_expr!(indices.len() % 3 == 0, "Mesh indices must be a multiple of 3!");
+    assert_expr!(indices.iter().all(|x| ((*x).into() as usize) < verts.len()), "Mesh indices out of bounds!");
+}
+
+/// Splits a mesh into parts with `u16` indices and at most `max_verts` vertices and `max_indices` indices each. `max_verts` is capped to `MAX_PART_VERTS`.
+pub(crate) fn split_mesh<V: Copy, I: Copy + Into<u32>>(verts: &[V], indices: &[I], max_verts: usize, max_indices: usize) -> Vec<(Vec<V>, Vec<u16>)> {
+    const UNMAPPED: u32 = u32::MAX;
+
+    let max_verts = max_verts.min(MAX_PART_VERTS);
+    if verts.len() <= max_verts && indices.len() <= max_indices {
+        return vec![(verts.to_vec(), indices.iter().map(|x| (*x).into() as u16).collect())];
+    }
+
+    let mut chunks = Vec::new();
+    let mut remap = vec![UNMAPPED; verts.len()];
+    let mut chunk_verts = Vec::new();
+    let mut chunk_indices = Vec::new();
+
+    for tri in indices.chunks_exact(3) {
+        let new_verts = tri.iter().filter(|i| remap[(**i).into() as usize] == UNMAPPED).count();
+        if chunk_verts.len() + new_verts > max_verts || chunk_indices.len() + 3 > max_indices {
+            chunks.push((std::mem::take(&mut chunk_verts), std::mem::take(&mut chunk_indices)));
+            remap.fill(UNMAPPED);
+        }
+
+        for i in tri {
+            let mapped = &mut remap[(*i).into() as usize];
+            if *mapped == UNMAPPED {
+                *mapped = chunk_verts.len() as u32;
+                chunk_verts.push(verts[(*i).into() as usize]);
+            }
+            chunk_indices.push(*mapped as u16);
+        }
+    }
+
+    if !chunk_indices.is_empty() {
+        chunks.push((chunk_verts, chunk_indices));
+    }
+    return chunks;
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::vector2::vec2;
+
+    use super::{split_mesh, MAX_PART_VERTS};
+
+    #[test]
+    fn split_big_mesh() {
+        const MAX_VERTS: usize = 1024;
+        const MAX_INDICES: usize = 1536;
+
+        // Strip of quads with twice the vertices a batch can hold
+        let quads = MAX_VERTS / 2;
+        let verts = (0..=quads).flat_map(|i| [vec2(i as f32, 0.0), vec2(i as f32, 1.0)]).collect::<Vec<_>>();
+        let indices = (0..quads as u16).flat_map(|i| [i * 2, i * 2 + 1, i * 2 + 3, i * 2 + 3, i * 2 + 2, i * 2]).collect::<Vec<_>>();
+
+        let chunks = split_mesh(&verts, &indices, MAX_VERTS, MAX_INDICES);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.iter().map(|x| x.1.len()).sum::<usize>(), indices.len());
+
+        for (chunk_verts, chunk_indices) in &chunks {
+            assert!(chunk_verts.len() <= MAX_VERTS && chunk_indices.len() <= MAX_INDICES);
+            assert!(chunk_indices.iter().all(|i| (*i as usize) < chunk_verts.len()));
+        }
+    }
+
+    #[test]
+    fn split_wide_indices() {
+        // More vertices than `u16` can index, every triangle must survive with the same positions
+        let verts = (0..MAX_PART_VERTS as u32 * 2).map(|i| vec2(i as f32, 0.0)).collect::<Vec<_>>();
+        let indices = (0..verts.len() as u32 - 2).flat_map(|i| [i, i + 1, i + 2]).collect::<Vec<_>>();
+
+        let chunks = split_mesh(&verts, &indices, usize::MAX, usize::MAX);
+        assert!(chunks.len() > 1);
+
+        let tris = chunks.iter().flat_map(|(v, i)| i.chunks_exact(3).map(move |t| [v[t[0] as usize].0, v[t[1] as usize].0, v[t[2] as usize].0]));
+        let expected = indices.chunks_exact(3).map(|t| [t[0] as f32, t[1] as f32, t[2] as f32]);
+        assert!(tris.eq(expected));
+        assert!(chunks.iter().all(|(v, _)| v.len() <= MAX_PART_VERTS));
+
+        // Small meshes are kept whole
+        assert_eq!(split_mesh(&verts[..3], &indices[..3], usize::MAX, usize::MAX).len(), 1);
+    }
+}
@@ -1,15 +1,19 @@
 use std::sync::{Arc, RwLock};
 
+use animation::Animator;
 use blending::BlendingMode;
 use material::Material;
-use nogine2_core::{crash, lazy::LazyCloner, math::{rect::Rect, vector2::{uvec2, vec2}}};
+use mesh::Mesh2D;
+use nogine2_core::{assert_expr, crash, lazy::LazyCloner, math::{rect::{Rect, URect}, vector2::{uvec2, vec2}}};
 use pipeline::{RenderPipeline, RenderStats};
-use scope::{ui::UIScope, LineSubmitCmd, PointsSubmitCmd, RectSubmitCmd, RenderScope, RenderScopeCfgFlags};
+use scope::{ui::UIScope, InstancedSubmitCmd, LineSubmitCmd, MeshSubmitCmd, PointsSubmitCmd, PolygonCfg, RectSubmitCmd, RenderScope, RenderScopeCfgFlags, ShapeSubmitCmd};
+use shapes::{ShapeMesh, StrokeStyle};
 use text::TextCfg;
 use texture::{pixels::{PixelFormat, Pixels}, rendertex::RenderTexture, sprite::Sprite, Texture2D, TextureFiltering, TextureHandle, TextureSampling, TextureWrapping};
 use ui::area::UIArea;
+use vertex::BatchVertex;
 
-use crate::{colors::{rgba::RGBA32, Color}, graphics::text::{align::{HorTextAlign, VerTextAlign}, font::Font}};
+use crate::{colors::{rgba::RGBA32, Color}, gl_wrapper::gl_max_texture_units, graphics::text::{align::{HorTextAlign, VerTextAlign}, font::Font}};
 
 pub mod vertex;
 pub mod defaults;
@@ -22,6 +26,12 @@ pub mod gfx;
 pub mod material;
 pub mod ui;
 pub mod text;
+pub mod mesh;
+pub mod shapes;
+pub mod camera;
+pub mod animation;
+pub mod particles;
+pub mod tilemap;
 
 mod batch;
 
@@ -30,7 +40,16 @@ pub(crate) static WHITE_TEX: LazyCloner<TextureHandle> = LazyCloner::new(|| Text
     TextureSampling { filtering: TextureFiltering::Nearest, wrapping: TextureWrapping::Clamp },
 ).handle());
 
+/// Checkerboard used while textures are being loaded.
+#[cfg(feature = "image-loading")]
+pub(crate) static PLACEHOLDER_TEX: LazyCloner<Texture2D> = LazyCloner::new(|| Texture2D::new(
+    Pixels::new(vec![255, 0, 255, 255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 0, 255, 255], uvec2(2, 2), PixelFormat::RGBA8),
+    TextureSampling { filtering: TextureFiltering::Nearest, wrapping: TextureWrapping::Repeat },
+));
+
 static GRAPHICS: RwLock<Graphics> = RwLock::new(Graphics::new());
+static BATCH_CAPACITY: RwLock<BatchCapacity> = RwLock::new(BatchCapacity::DEFAULT);
+static MAX_TEXTURE_UNITS: RwLock<usize> = RwLock::new(16);
 
 pub struct Graphics {
     active_scope: RenderScope,
@@ -80,6 +99,11 @@ impl Graphics {
         return Self::draw_texture_adv(pos, rot, scale, [RGBA32::WHITE; 4], sprite.handle().clone(), sprite.uv_rect());
     }
 
+    /// Draws the current frame of an animator.
+    pub fn draw_animated(pos: vec2, rot: f32, scale: vec2, animator: &Animator) {
+        return Self::draw_sprite(pos, rot, scale, animator.frame());
+    }
+
     pub fn draw_texture_adv(pos: vec2, rot: f32, scale: vec2, tint: [RGBA32; 4], texture: TextureHandle, uv_rect: Rect) {
         let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
 
@@ -87,6 +111,12 @@ impl Graphics {
         graphics.active_scope.draw_rect(RectSubmitCmd { pos, rot, extents, tint, texture, uv_rect });
     }
 
+    /// Draws many copies of a sprite with a single draw call, transforming them on the GPU. The material must use the default batch vertex subshader.
+    pub fn draw_instanced(sprite: &Sprite, instances: &[SpriteInstance]) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.draw_instanced(InstancedSubmitCmd { texture: sprite.handle().clone(), uv_rect: sprite.uv_rect(), instances });
+    }
+
     pub fn draw_points(points: &[(vec2, RGBA32)]) { 
         let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
         graphics.active_scope.draw_points(PointsSubmitCmd { points });
@@ -97,6 +127,118 @@ impl Graphics {
         graphics.active_scope.draw_line(LineSubmitCmd { verts: [from, to], cols: colors });
     }
 
+    /// Draws a line between each pair of consecutive points, such as the ones returned by `Curve::tessellate`.
+    pub fn draw_polyline(points: &[vec2], color: RGBA32) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        for segment in points.windows(2) {
+            graphics.active_scope.draw_line(LineSubmitCmd { verts: [segment[0], segment[1]], cols: [color; 2] });
+        }
+    }
+
+    /// Draws a concave polygon. `points` may be in any winding order, but must not intersect itself.
+    pub fn draw_polygon(pos: vec2, rot: f32, scale: vec2, points: &[vec2], color: RGBA32) {
+        Self::draw_polygon_ext(PolygonCfg { pos, rot, scale, points, colors: &[color], ..Default::default() });
+    }
+
+    /// Draws a concave polygon with holes, per-vertex colors and texture mapping.
+    pub fn draw_polygon_ext(cfg: PolygonCfg<'_>) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.draw_polygon(cfg);
+    }
+
+    /// Draws arbitrary triangles. Vertex positions are in world space and `tex_id` is ignored.
+    pub fn draw_mesh(verts: &[BatchVertex], indices: &[u16], texture: TextureHandle) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.draw_mesh(MeshSubmitCmd { pos: vec2::ZERO, rot: 0.0, scale: vec2::ONE, verts, indices, texture });
+    }
+
+    /// Draws a mesh. The pivot is ignored, the origin of the mesh is used instead.
+    pub fn draw_mesh_2d(pos: vec2, rot: f32, scale: vec2, mesh: &Mesh2D) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.draw_mesh(MeshSubmitCmd { pos, rot, scale, verts: mesh.verts(), indices: mesh.indices(), texture: mesh.texture().clone() });
+    }
+
+    pub fn draw_circle(center: vec2, radius: f32, color: RGBA32) {
+        Self::draw_ellipse(center, 0.0, vec2::one(radius), color);
+    }
+
+    /// The outline is centered on the radius.
+    pub fn draw_circle_outline(center: vec2, radius: f32, thickness: f32, color: RGBA32) {
+        Self::draw_ellipse_outline(center, 0.0, vec2::one(radius), thickness, color);
+    }
+
+    pub fn draw_ellipse(center: vec2, rot: f32, radii: vec2, color: RGBA32) {
+        Self::draw_shape(center, rot, color, |scope| {
+            let segments = scope.circle_segments(radii.0.max(radii.1));
+            ShapeMesh::fan(vec2::ZERO, &shapes::ellipse_points(vec2::ZERO, radii, segments), true)
+        });
+    }
+
+    /// The outline is centered on the radii.
+    pub fn draw_ellipse_outline(center: vec2, rot: f32, radii: vec2, thickness: f32, color: RGBA32) {
+        Self::draw_shape(center, rot, color, |scope| {
+            let hw = thickness * 0.5;
+            let segments = scope.circle_segments(radii.0.max(radii.1) + hw);
+            let outer = shapes::ellipse_points(vec2::ZERO, radii + vec2::one(hw), segments);
+            let inner = shapes::ellipse_points(vec2::ZERO, (radii - vec2::one(hw)).max(vec2::ZERO), segments);
+            ShapeMesh::strip(&outer, &inner, true)
+        });
+    }
+
+    /// Draws an arc from `start_angle` to `end_angle`, in radians, counter-clockwise from the positive X axis rotated by `rot`.
+    pub fn draw_arc(center: vec2, rot: f32, radius: f32, start_angle: f32, end_angle: f32, thickness: f32, color: RGBA32) {
+        Self::draw_shape(center, rot, color, |scope| {
+            let hw = thickness * 0.5;
+            let segments = arc_segments(scope.circle_segments(radius + hw), start_angle, end_angle);
+            let outer = shapes::arc_points(vec2::ZERO, vec2::one(radius + hw), start_angle, end_angle, segments);
+            let inner = shapes::arc_points(vec2::ZERO, vec2::one((radius - hw).max(0.0)), start_angle, end_angle, segments);
+            ShapeMesh::strip(&outer, &inner, false)
+        });
+    }
+
+    /// Draws a pie slice from `start_angle` to `end_angle`, in radians, counter-clockwise from the positive X axis rotated by `rot`.
+    pub fn draw_pie(center: vec2, rot: f32, radius: f32, start_angle: f32, end_angle: f32, color: RGBA32) {
+        Self::draw_shape(center, rot, color, |scope| {
+            let segments = arc_segments(scope.circle_segments(radius), start_angle, end_angle);
+            ShapeMesh::fan(vec2::ZERO, &shapes::arc_points(vec2::ZERO, vec2::one(radius), start_angle, end_angle, segments), false)
+        });
+    }
+
+    /// Draws a rect with rounded corners, placed like `draw_rect`.
+    pub fn draw_rounded_rect(pos: vec2, rot: f32, extents: vec2, radius: f32, color: RGBA32) {
+        Self::draw_shape(pos, rot, color, |scope| {
+            let rect = scope.local_rect(extents);
+            let corner_segments = (scope.circle_segments(radius) / 4).max(1);
+            ShapeMesh::fan(rect.center(), &shapes::rounded_rect_points(rect, radius, corner_segments), true)
+        });
+    }
+
+    /// Draws the outline of a rect with rounded corners, placed like `draw_rect`. The outline is centered on the edges.
+    pub fn draw_rounded_rect_outline(pos: vec2, rot: f32, extents: vec2, radius: f32, thickness: f32, color: RGBA32) {
+        Self::draw_shape(pos, rot, color, |scope| {
+            let hw = vec2::one(thickness * 0.5);
+            let rect = scope.local_rect(extents);
+            let corner_segments = (scope.circle_segments(radius + hw.0) / 4).max(1);
+            let outer = shapes::rounded_rect_points(Rect { start: rect.start - hw, end: rect.end + hw }, radius + hw.0, corner_segments);
+            let inner = shapes::rounded_rect_points(Rect { start: rect.start + hw, end: (rect.end - hw).max(rect.start + hw) }, radius - hw.0, corner_segments);
+            ShapeMesh::strip(&outer, &inner, true)
+        });
+    }
+
+    /// Draws a thick line through `points`. If `closed` is set, the last point is connected to the first one.
+    pub fn draw_stroke(points: &[vec2], closed: bool, style: StrokeStyle, color: RGBA32) {
+        Self::draw_shape(vec2::ZERO, 0.0, color, |scope| {
+            let round_segments = scope.circle_segments(style.thickness * 0.5);
+            shapes::stroke(points, closed, style, round_segments)
+        });
+    }
+
+    fn draw_shape(pos: vec2, rot: f32, color: RGBA32, f: impl FnOnce(&RenderScope) -> ShapeMesh) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        let mesh = f(&graphics.active_scope);
+        graphics.active_scope.draw_shape(ShapeSubmitCmd { pos, rot, mesh, color });
+    }
+
     pub fn draw_9_patch(pos: vec2, rot: f32, extents: vec2, sprite: &Sprite) {
         Self::draw_9_patch_ext(pos, rot, extents, RGBA32::WHITE, sprite, 1.0);
     }
@@ -108,6 +250,19 @@ impl Graphics {
         tint: RGBA32,
         sprite: &Sprite,
         corner_scaling: f32
+    ) {
+        Self::draw_9_slice(pos, rot, extents, tint, sprite, corner_scaling, None);
+    }
+
+    /// Draws a 9-patch. `center` is the stretched area in sprite pixels, like the center of an Aseprite 9-slice. Thirds of the sprite are used if `None`.
+    pub fn draw_9_slice(
+        pos: vec2,
+        rot: f32,
+        extents: vec2,
+        tint: RGBA32,
+        sprite: &Sprite,
+        corner_scaling: f32,
+        center: Option<URect>,
     ) {
         let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
         graphics.active_scope.draw_9_patch(scope::NinePatchSubmitCmd {
@@ -116,7 +271,8 @@ impl Graphics {
             extents,
             tint,
             sprite: sprite.clone(),
-            corner_scaling
+            corner_scaling,
+            center,
         });
     }
 
@@ -130,12 +286,46 @@ impl Graphics {
         graphics.active_scope.draw_text_stateless(cfg, text);
     }
 
-    /// Returns the current camera data.
+    /// Returns the current camera data. With several viewports, returns the camera of the target viewport, or of the first one.
     pub fn camera() -> CameraData {
         let Ok(graphics) = GRAPHICS.read() else { crash!("Couldn't access Graphics singleton!") };
         return graphics.active_scope.camera();
     }
 
+    /// Returns the viewports of the current frame.
+    pub fn viewports() -> Vec<Viewport> {
+        let Ok(graphics) = GRAPHICS.read() else { crash!("Couldn't access Graphics singleton!") };
+        return graphics.active_scope.viewports();
+    }
+
+    /// Sets the viewports of the active scope from its next frame on. All of them are drawn from the same submissions. If empty, a single viewport covers the whole target with the camera of the frame, which is the default.
+    pub fn set_viewports(viewports: &[Viewport]) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.set_viewports(viewports);
+    }
+
+    /// Returns the viewport new submissions are restricted to, if any.
+    pub fn target_viewport() -> Option<usize> {
+        let Ok(graphics) = GRAPHICS.read() else { crash!("Couldn't access Graphics singleton!") };
+        return graphics.active_scope.target_viewport();
+    }
+
+    /// Restricts new submissions to a single viewport, or to all of them if `None`. Will panic if the viewport doesn't exist.
+    pub fn set_target_viewport(viewport: Option<usize>) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.set_target_viewport(viewport);
+    }
+
+    /// Calls `f` once per viewport, with new submissions restricted to that viewport.
+    pub fn for_each_viewport(mut f: impl FnMut(usize, &Viewport)) {
+        let prev = Self::target_viewport();
+        for (i, viewport) in Self::viewports().iter().enumerate() {
+            Self::set_target_viewport(Some(i));
+            f(i, viewport);
+        }
+        Self::set_target_viewport(prev);
+    }
+
     /// Returns the pixels per unit for textures.
     pub fn pixels_per_unit() -> f32 {
         let Ok(graphics) = GRAPHICS.read() else { crash!("Couldn't access Graphics singleton!") };
@@ -160,6 +350,18 @@ impl Graphics {
         graphics.active_scope.set_user_data(user_data);
     }
 
+    /// Returns the depth of new submissions.
+    pub fn depth() -> f32 {
+        let Ok(graphics) = GRAPHICS.read() else { crash!("Couldn't access Graphics singleton!") };
+        return graphics.active_scope.depth();
+    }
+
+    /// Sets the depth of new submissions. Submissions with a higher depth are drawn behind, while submissions with the same depth keep their order. Defaults to `0.0`.
+    pub fn set_depth(depth: f32) {
+        let Ok(mut graphics) = GRAPHICS.write() else { crash!("Couldn't access Graphics singleton!") };
+        graphics.active_scope.set_depth(depth);
+    }
+
     /// Returns the current pivot.
     pub fn pivot() -> vec2 {
         let Ok(graphics) = GRAPHICS.read() else { crash!("Couldn't access Graphics singleton!") };
@@ -305,7 +507,30 @@ impl Graphics {
     }
 
 
+    /// Returns the maximum size of a single batch.
+    pub fn batch_capacity() -> BatchCapacity {
+        let Ok(capacity) = BATCH_CAPACITY.read() else { crash!("Couldn't access BatchCapacity singleton!") };
+        return *capacity;
+    }
+
+    /// Sets the maximum size of a single batch. Takes effect on the next frame. Will panic if any of the limits is too small to hold a single primitive.
+    pub fn set_batch_capacity(capacity: BatchCapacity) {
+        assert_expr!(capacity.tri_verts >= 3 && capacity.tri_indices >= 3 && capacity.points >= 1 && capacity.lines >= 1 && capacity.instances >= 1, "Batch capacity is too small!");
+        let Ok(mut current) = BATCH_CAPACITY.write() else { crash!("Couldn't access BatchCapacity singleton!") };
+        *current = capacity;
+    }
+
+    /// Returns the texture units available to fragment shaders (`GL_MAX_TEXTURE_IMAGE_UNITS`). The default batch shader is generated to use them.
+    pub fn max_texture_units() -> usize {
+        let Ok(units) = MAX_TEXTURE_UNITS.read() else { crash!("Couldn't access MaxTextureUnits singleton!") };
+        return *units;
+    }
+
     pub(crate) fn init() {
+        let Ok(mut units) = MAX_TEXTURE_UNITS.write() else { crash!("Couldn't access MaxTextureUnits singleton!") };
+        *units = gl_max_texture_units() as usize;
+        drop(units);
+
         _ = WHITE_TEX.get(); // Initialize WHITE_TEX because why not
     }
 
@@ -331,6 +556,7 @@ impl Graphics {
         std::mem::swap(&mut decoy_ui_scope, &mut graphics.ui_scope);
 
         let ui_data = if graphics.ui_enabled {
+            decoy_ui_scope.finish_batch();
             Some(decoy_ui_scope.get_scene_data())
         } else {
             None
@@ -352,25 +578,167 @@ impl Graphics {
 }
 
 
-/// Represents the camera in Unit Space.
+/// Represents the camera in Unit Space. Rotation and zoom are set through methods, use `new` to create it.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CameraData {
     pub center: vec2,
+    /// Size of the view at zoom `1.0`.
     pub extents: vec2,
+    rot: f32,
+    zoom: f32,
 }
 
 impl CameraData {
+    pub const fn new(center: vec2, extents: vec2) -> Self {
+        Self { center, extents, rot: 0.0, zoom: 1.0 }
+    }
+
+    /// Rotation in radians, counter-clockwise.
+    pub const fn with_rot(mut self, rot: f32) -> Self {
+        self.rot = rot;
+        return self;
+    }
+
+    /// Zoom factor. Values over `1.0` zoom in.
+    pub const fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        return self;
+    }
+
+    pub fn rot(&self) -> f32 {
+        self.rot
+    }
+
+    pub fn set_rot(&mut self, rot: f32) {
+        self.rot = rot;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+    }
+
+    /// Returns the size of the visible area, taking the zoom into account.
+    pub fn view_extents(&self) -> vec2 {
+        return self.extents / self.zoom.max(f32::EPSILON);
+    }
+
+    /// Returns the bounding box of the visible area.
     pub fn rect(&self) -> Rect {
-        return Rect {
-            start: self.center - self.extents * 0.5,
-            end: self.center + self.extents * 0.5,
-        };
+        let half = self.view_extents() * 0.5;
+        let (sin, cos) = self.rot.sin_cos();
+        let half = vec2(half.0 * cos.abs() + half.1 * sin.abs(), half.0 * sin.abs() + half.1 * cos.abs());
+        return Rect { start: self.center - half, end: self.center + half };
+    }
+
+    /// Converts a view position, from `(-1, -1)` (left down) to `(1, 1)` (right up), into world space.
+    pub fn view_to_world(&self, pos: vec2) -> vec2 {
+        return self.center + rotate(pos.scale(self.view_extents() * 0.5), self.rot);
+    }
+
+    /// Converts a world position into a view position, from `(-1, -1)` (left down) to `(1, 1)` (right up).
+    pub fn world_to_view(&self, pos: vec2) -> vec2 {
+        return rotate(pos - self.center, -self.rot).inv_scale(self.view_extents() * 0.5);
     }
 }
 
 impl Default for CameraData {
     fn default() -> Self {
-        Self { center: vec2::ZERO, extents: vec2::ONE }
+        Self::new(vec2::ZERO, vec2::ONE)
+    }
+}
+
+pub(crate) fn rotate(v: vec2, angle: f32) -> vec2 {
+    let (sin, cos) = angle.sin_cos();
+    return vec2(v.0 * cos - v.1 * sin, v.0 * sin + v.1 * cos);
+}
+
+
+/// A camera drawn into a sub-area of the render target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewport {
+    pub camera: CameraData,
+    /// Area of the target, from `(0, 0)` (left up) to `(1, 1)` (right down).
+    pub rect: Rect,
+}
+
+impl Viewport {
+    /// Area covering the whole target.
+    pub const FULL: Rect = Rect { start: vec2::ZERO, end: vec2::ONE };
+
+    pub const fn new(camera: CameraData, rect: Rect) -> Self {
+        Self { camera, rect }
+    }
+
+    /// Splits the target into a grid of `count` areas, filled left to right and top to bottom. Useful for split-screen.
+    pub fn split(count: usize) -> Vec<Rect> {
+        let cols = (count as f32).sqrt().ceil().max(1.0) as usize;
+        let rows = count.div_ceil(cols).max(1);
+        let size = vec2(1.0 / cols as f32, 1.0 / rows as f32);
+
+        return (0..count).map(|i| {
+            let start = vec2((i % cols) as f32, (i / cols) as f32).scale(size);
+            Rect { start, end: start + size }
+        }).collect();
+    }
+
+    /// Returns if `pos`, normalized like `rect`, is inside of the viewport.
+    pub fn contains(&self, pos: vec2) -> bool {
+        return pos.0 >= self.rect.start.0 && pos.0 < self.rect.end.0 && pos.1 >= self.rect.start.1 && pos.1 < self.rect.end.1;
+    }
+}
+
+
+/// Maximum size of a single batch. Bigger batches mean fewer draw calls, and since buffers grow on demand, unused capacity doesn't take memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchCapacity {
+    /// Vertices per triangle batch. Batches over 65536 vertices use 32 bit indices.
+    pub tri_verts: usize,
+    /// Indices per triangle batch.
+    pub tri_indices: usize,
+    /// Vertices per point batch.
+    pub points: usize,
+    /// Lines per line batch.
+    pub lines: usize,
+    /// Instances per instanced batch.
+    pub instances: usize,
+}
+
+impl BatchCapacity {
+    pub const DEFAULT: Self = Self { tri_verts: 65536, tri_indices: 98304, points: 4096, lines: 4096, instances: 16384 };
+}
+
+impl Default for BatchCapacity {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+
+/// A single copy of a sprite drawn with `Graphics::draw_instanced`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteInstance {
+    pub pos: vec2,
+    /// Rotation in radians.
+    pub rot: f32,
+    pub scale: vec2,
+    pub tint: RGBA32,
+    /// Part of the sprite to draw, in normalized sprite coordinates. Useful for animation frames.
+    pub uv_rect: Rect,
+}
+
+impl SpriteInstance {
+    pub const fn new(pos: vec2) -> Self {
+        Self { pos, rot: 0.0, scale: vec2::ONE, tint: RGBA32::WHITE, uv_rect: Rect::IDENT }
+    }
+}
+
+impl Default for SpriteInstance {
+    fn default() -> Self {
+        Self::new(vec2::ZERO)
     }
 }
 
@@ -398,3 +766,9 @@ impl<'a> Default for FrameSetup<'a> {
         Self { camera: CameraData::default(), target_res: uvec2::ONE, ui_res: None, clear_col: RGBA32::BLACK, pipeline: None }
     }
 }
+
+/// Scales the segments of a full circle to the sweep of an arc.
+fn arc_segments(circle_segments: usize, start_angle: f32, end_angle: f32) -> usize {
+    let sweep = (end_angle - start_angle).abs() / std::f32::consts::TAU;
+    return ((circle_segments as f32 * sweep).ceil() as usize).max(1);
+}
@@ -0,0 +1,110 @@
+use nogine2_core::math::vector2::vec2;
+
+use super::{Particle, ParticleRng, ParticleTypeId, SimFrame};
+
+/// Area new particles are spawned in, centered on the emitter and rotated with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterShape {
+    Point,
+    Circle { radius: f32 },
+    Rect { extents: vec2 },
+    /// Segment along the X axis of the emitter.
+    Line { length: f32 },
+}
+
+impl EmitterShape {
+    /// Returns a random point inside of the shape.
+    pub(super) fn sample(&self, rng: &mut ParticleRng) -> vec2 {
+        match self {
+            EmitterShape::Point => vec2::ZERO,
+            EmitterShape::Circle { radius } => {
+                let angle = rng.range((0.0, std::f32::consts::TAU));
+                let dist = radius * rng.next_f32().sqrt(); // Uniform over the area
+                vec2(angle.cos(), angle.sin()) * dist
+            },
+            EmitterShape::Rect { extents } => vec2(rng.range((-0.5, 0.5)) * extents.0, rng.range((-0.5, 0.5)) * extents.1),
+            EmitterShape::Line { length } => vec2(rng.range((-0.5, 0.5)) * length, 0.0),
+        }
+    }
+}
+
+
+/// Space particles are simulated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimulationSpace {
+    /// Particles stay where they were spawned when the emitter moves.
+    #[default]
+    World,
+    /// Particles move and rotate along with the emitter.
+    Local,
+}
+
+
+/// Spawns particles of a single type, in bursts or as a stream. Add it to a `ParticleSystem` to simulate it.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub pos: vec2,
+    /// Rotation in radians, counter-clockwise.
+    pub rot: f32,
+    pub shape: EmitterShape,
+    pub ptype: ParticleTypeId,
+    /// Particles spawned per second. `0.0` disables streaming.
+    pub rate: f32,
+
+    space: SimulationSpace,
+    accumulator: f32,
+    pub(super) particles: Vec<Particle>,
+}
+
+impl Emitter {
+    pub fn new(ptype: ParticleTypeId, shape: EmitterShape) -> Self {
+        Self { pos: vec2::ZERO, rot: 0.0, shape, ptype, rate: 0.0, space: SimulationSpace::World, accumulator: 0.0, particles: Vec::new() }
+    }
+
+    pub fn with_pos(mut self, pos: vec2) -> Self {
+        self.pos = pos;
+        return self;
+    }
+
+    /// Sets the particles spawned per second.
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = rate;
+        return self;
+    }
+
+    /// Sets the space particles are simulated in. Can't be changed after creation.
+    pub fn with_space(mut self, space: SimulationSpace) -> Self {
+        self.space = space;
+        return self;
+    }
+
+    pub fn space(&self) -> SimulationSpace {
+        self.space
+    }
+
+    /// Returns the alive particles spawned by this emitter.
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Returns how many particles the stream must spawn after `dt` seconds.
+    pub(super) fn stream(&mut self, dt: f32) -> u32 {
+        if self.rate <= 0.0 {
+            self.accumulator = 0.0;
+            return 0;
+        }
+
+        self.accumulator += self.rate * dt;
+        let count = self.accumulator.floor();
+        self.accumulator -= count;
+        return count as u32;
+    }
+
+    /// Returns the transform of the space its particles are simulated in.
+    pub(super) fn frame(&self) -> SimFrame {
+        match self.space {
+            SimulationSpace::World => SimFrame::IDENT,
+            SimulationSpace::Local => SimFrame { origin: self.pos, rot: self.rot },
+        }
+    }
+}
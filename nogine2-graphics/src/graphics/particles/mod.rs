@@ -0,0 +1,552 @@
+use emitter::Emitter;
+use nogine2_core::{assert_expr, math::{rect::Rect, vector2::{uvec2, vec2}}, slotmap::{SlotKey, SlotMap}};
+use ptype::{ParticleType, ParticleVisual, SubEmitter};
+
+use super::{rotate, texture::sprite::Sprite, Graphics, SpriteInstance, WHITE_TEX};
+
+pub mod emitter;
+pub mod ptype;
+
+/// Index of a `ParticleType` inside of a `ParticleSystem`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ParticleTypeId(u32);
+
+/// A single simulated particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub pos: vec2,
+    /// Velocity in units per second.
+    pub vel: vec2,
+    /// Rotation in radians, without the rotation over life.
+    pub rot: f32,
+    /// Angular velocity in radians per second.
+    pub spin: f32,
+    /// Scale factor, without the size over life.
+    pub size: f32,
+    /// Seconds since the particle was spawned.
+    pub age: f32,
+    pub lifetime: f32,
+    pub ptype: ParticleTypeId,
+    /// Random value picked when spawned.
+    pub seed: u32,
+}
+
+impl Particle {
+    /// Returns the age of the particle normalized to its lifetime.
+    pub fn life(&self) -> f32 {
+        return (self.age / self.lifetime.max(f32::EPSILON)).clamp(0.0, 1.0);
+    }
+}
+
+
+/// Pulls particles towards its position, or pushes them away with a negative `strength`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attractor {
+    pub pos: vec2,
+    /// Acceleration at the center, in units per second squared.
+    pub strength: f32,
+    /// Distance at which the force fades out completely.
+    pub radius: f32,
+}
+
+impl Attractor {
+    pub const fn new(pos: vec2, strength: f32, radius: f32) -> Self {
+        Self { pos, strength, radius }
+    }
+
+    /// Returns the acceleration applied to a particle at `point`.
+    pub fn accel(&self, point: vec2) -> vec2 {
+        let diff = self.pos - point;
+        let dist = diff.length();
+        if dist <= f32::EPSILON || dist >= self.radius {
+            return vec2::ZERO;
+        }
+        return diff / dist * self.strength * (1.0 - dist / self.radius);
+    }
+}
+
+
+/// Owns particle types, emitters and attractors, and simulates and draws their particles. The simulation doesn't need a GL context.
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    types: Vec<ParticleType>,
+    emitters: SlotMap<Emitter>,
+    attractors: SlotMap<Attractor>,
+    /// Particles not owned by any emitter, in world space.
+    free: Vec<Particle>,
+    rng: ParticleRng,
+    max_particles: usize,
+    instancing: bool,
+}
+
+impl ParticleSystem {
+    const DEFAULT_SEED: u32 = 0x9E3779B9;
+    const DEFAULT_MAX_PARTICLES: usize = 65536;
+
+    pub fn new() -> Self {
+        Self {
+            types: Vec::new(), emitters: SlotMap::new(), attractors: SlotMap::new(), free: Vec::new(),
+            rng: ParticleRng::new(Self::DEFAULT_SEED), max_particles: Self::DEFAULT_MAX_PARTICLES, instancing: true,
+        }
+    }
+
+    /// Sets the seed of the random values. Systems with the same seed and inputs simulate the same.
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.rng = ParticleRng::new(seed);
+        return self;
+    }
+
+    /// Sets the maximum alive particles. Particles over it aren't spawned.
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        return self;
+    }
+
+    /// Sets if particles are drawn with `Graphics::draw_instanced`, which requires the default batch vertex subshader. Otherwise, they are drawn as batched rects.
+    pub fn with_instancing(mut self, instancing: bool) -> Self {
+        self.instancing = instancing;
+        return self;
+    }
+
+    pub fn max_particles(&self) -> usize {
+        self.max_particles
+    }
+
+    pub fn instancing(&self) -> bool {
+        self.instancing
+    }
+
+    pub fn set_instancing(&mut self, instancing: bool) {
+        self.instancing = instancing;
+    }
+
+    /// Registers a particle type. Will panic if its sub emitter references a type that doesn't exist.
+    pub fn add_type(&mut self, ptype: ParticleType) -> ParticleTypeId {
+        let id = ParticleTypeId(self.types.len() as u32);
+        if let Some(sub) = &ptype.on_death {
+            assert_expr!(sub.ptype < id, "Sub emitters must reference an existing particle type!");
+        }
+
+        self.types.push(ptype);
+        return id;
+    }
+
+    pub fn particle_type(&self, id: ParticleTypeId) -> Option<&ParticleType> {
+        self.types.get(id.0 as usize)
+    }
+
+    pub fn particle_type_mut(&mut self, id: ParticleTypeId) -> Option<&mut ParticleType> {
+        self.types.get_mut(id.0 as usize)
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) -> SlotKey {
+        self.emitters.insert(emitter)
+    }
+
+    pub fn emitter(&self, key: SlotKey) -> Option<&Emitter> {
+        self.emitters.get(key)
+    }
+
+    pub fn emitter_mut(&mut self, key: SlotKey) -> Option<&mut Emitter> {
+        self.emitters.get_mut(key)
+    }
+
+    /// Removes an emitter along with its particles.
+    pub fn remove_emitter(&mut self, key: SlotKey) -> Option<Emitter> {
+        self.emitters.remove(key)
+    }
+
+    pub fn add_attractor(&mut self, attractor: Attractor) -> SlotKey {
+        self.attractors.insert(attractor)
+    }
+
+    pub fn attractor_mut(&mut self, key: SlotKey) -> Option<&mut Attractor> {
+        self.attractors.get_mut(key)
+    }
+
+    pub fn remove_attractor(&mut self, key: SlotKey) -> Option<Attractor> {
+        self.attractors.remove(key)
+    }
+
+    /// Spawns `count` particles from an emitter at once.
+    pub fn burst(&mut self, emitter: SlotKey, count: u32) {
+        let alive = self.particle_count();
+        let Self { types, emitters, rng, max_particles, .. } = self;
+        let Some(emitter) = emitters.get_mut(emitter) else { return };
+
+        let mut spawner = Spawner { types, rng, alive, max_particles: *max_particles };
+        spawner.emit(emitter, count);
+    }
+
+    /// Spawns `count` particles at `pos`, in world space and without an emitter.
+    pub fn spawn(&mut self, ptype: ParticleTypeId, pos: vec2, count: u32) {
+        let alive = self.particle_count();
+        let Self { types, free, rng, max_particles, .. } = self;
+
+        let mut spawner = Spawner { types, rng, alive, max_particles: *max_particles };
+        for _ in 0..count {
+            spawner.spawn(free, ptype, pos, 0.0, vec2::ZERO);
+        }
+    }
+
+    /// Advances the simulation by `dt` seconds, spawning the particles of streams and sub emitters.
+    pub fn update(&mut self, dt: f32) {
+        let alive = self.particle_count();
+        let Self { types, emitters, attractors, free, rng, max_particles, .. } = self;
+
+        let mut spawner = Spawner { types, rng, alive, max_particles: *max_particles };
+        let mut deaths = Vec::new();
+
+        simulate(free, SimFrame::IDENT, spawner.types, attractors, dt, &mut deaths);
+        spawner.alive -= deaths.len();
+        spawner.on_death(free, &deaths);
+
+        for emitter in emitters.values_mut() {
+            deaths.clear();
+            let frame = emitter.frame();
+            simulate(&mut emitter.particles, frame, spawner.types, attractors, dt, &mut deaths);
+            spawner.alive -= deaths.len();
+            spawner.on_death(&mut emitter.particles, &deaths);
+
+            let count = emitter.stream(dt);
+            spawner.emit(emitter, count);
+        }
+    }
+
+    /// Kills every particle.
+    pub fn clear(&mut self) {
+        self.free.clear();
+        for emitter in self.emitters.values_mut() {
+            emitter.particles.clear();
+        }
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.free.len() + self.emitters.values().iter().map(|e| e.particles.len()).sum::<usize>()
+    }
+
+    /// Returns every alive particle, in world space.
+    pub fn particles(&self) -> impl Iterator<Item = Particle> + '_ {
+        let free = self.free.iter().copied();
+        let owned = self.emitters.values().iter().flat_map(|e| {
+            let frame = e.frame();
+            e.particles.iter().map(move |p| Particle { pos: frame.to_world(p.pos), vel: rotate(p.vel, frame.rot), rot: p.rot + frame.rot, ..*p })
+        });
+        return free.chain(owned);
+    }
+
+    /// Draws every alive particle. Particles of the same type are drawn together.
+    pub fn draw(&self) {
+        if self.instancing {
+            self.draw_instanced();
+        } else {
+            self.draw_batched();
+        }
+    }
+
+    fn draw_instanced(&self) {
+        let ppu = Graphics::pixels_per_unit();
+        let mut instances = vec![Vec::new(); self.types.len()];
+        self.for_each_instance(|ptype, id, mut inst| {
+            if let ParticleVisual::Rect { extents } = ptype.visual {
+                inst.scale = inst.scale.scale(extents) * ppu; // Drawn with a 1x1 texture
+            }
+            instances[id.0 as usize].push(inst);
+        });
+
+        for (ptype, instances) in self.types.iter().zip(instances) {
+            if instances.is_empty() {
+                continue;
+            }
+
+            let sprite = match &ptype.visual {
+                ParticleVisual::Rect { .. } => Sprite::new(WHITE_TEX.get(), Rect::IDENT, uvec2::ONE),
+                ParticleVisual::Sprite(sprite) => sprite.clone(),
+                ParticleVisual::Animated(anim) => anim.sheet().clone(),
+            };
+            Graphics::draw_instanced(&sprite, &instances);
+        }
+    }
+
+    fn draw_batched(&self) {
+        self.for_each_instance(|ptype, _, inst| {
+            match &ptype.visual {
+                ParticleVisual::Rect { extents } => Graphics::draw_rect(inst.pos, inst.rot, extents.scale(inst.scale), inst.tint),
+                ParticleVisual::Sprite(sprite) => Graphics::draw_texture_adv(inst.pos, inst.rot, inst.scale, [inst.tint; 4], sprite.handle().clone(), sprite.uv_rect()),
+                ParticleVisual::Animated(anim) => Graphics::draw_texture_adv(inst.pos, inst.rot, inst.scale, [inst.tint; 4], anim.sheet().handle().clone(), inst.uv_rect),
+            }
+        });
+    }
+
+    /// Calls `f` with the world space instance of every alive particle. For animated particles, `uv_rect` is the current frame.
+    fn for_each_instance(&self, mut f: impl FnMut(&ParticleType, ParticleTypeId, SpriteInstance)) {
+        for p in self.particles() {
+            let Some(ptype) = self.types.get(p.ptype.0 as usize) else { continue };
+
+            let t = p.life();
+            let uv_rect = match &ptype.visual {
+                ParticleVisual::Animated(anim) => anim.frames()[anim.mode().frame(anim.frames().len(), p.age, p.lifetime, p.seed)],
+                _ => Rect::IDENT,
+            };
+            f(ptype, p.ptype, SpriteInstance {
+                pos: p.pos,
+                rot: p.rot + ptype.rot_over_life.sample(t),
+                scale: vec2::one(p.size * ptype.size_over_life.sample(t)),
+                tint: ptype.tint(t),
+                uv_rect,
+            });
+        }
+    }
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Spawns particles while keeping track of the particle limit.
+struct Spawner<'a> {
+    types: &'a [ParticleType],
+    rng: &'a mut ParticleRng,
+    alive: usize,
+    max_particles: usize,
+}
+
+impl Spawner<'_> {
+    /// Spawns a particle at `pos`, in the space of `particles`. `dir_offset` rotates its initial direction.
+    fn spawn(&mut self, particles: &mut Vec<Particle>, ptype: ParticleTypeId, pos: vec2, dir_offset: f32, base_vel: vec2) {
+        if self.alive >= self.max_particles {
+            return;
+        }
+        let Some(t) = self.types.get(ptype.0 as usize) else { return };
+
+        let rng = &mut *self.rng;
+        let dir = rng.range(t.direction) + dir_offset;
+        let speed = rng.range(t.speed);
+        particles.push(Particle {
+            pos,
+            vel: base_vel + vec2(dir.cos(), dir.sin()) * speed,
+            rot: rng.range(t.rot),
+            spin: rng.range(t.spin),
+            size: rng.range(t.size),
+            age: 0.0,
+            lifetime: rng.range(t.lifetime),
+            ptype,
+            seed: rng.next_u32(),
+        });
+        self.alive += 1;
+    }
+
+    fn emit(&mut self, emitter: &mut Emitter, count: u32) {
+        let frame = emitter.frame();
+        for _ in 0..count {
+            let offset = emitter.shape.sample(self.rng);
+            let pos = frame.to_sim(emitter.pos + rotate(offset, emitter.rot));
+            self.spawn(&mut emitter.particles, emitter.ptype, pos, emitter.rot - frame.rot, vec2::ZERO);
+        }
+    }
+
+    fn on_death(&mut self, particles: &mut Vec<Particle>, deaths: &[Particle]) {
+        for dead in deaths {
+            let Some(SubEmitter { ptype, count, inherit_velocity }) = self.types.get(dead.ptype.0 as usize).and_then(|t| t.on_death) else { continue };
+            for _ in 0..count {
+                self.spawn(particles, ptype, dead.pos, 0.0, dead.vel * inherit_velocity);
+            }
+        }
+    }
+}
+
+/// Moves the particles and removes the dead ones, which are pushed to `deaths`.
+fn simulate(particles: &mut Vec<Particle>, frame: SimFrame, types: &[ParticleType], attractors: &SlotMap<Attractor>, dt: f32, deaths: &mut Vec<Particle>) {
+    particles.retain_mut(|p| {
+        p.age += dt;
+        if p.age >= p.lifetime {
+            deaths.push(*p);
+            return false;
+        }
+        let Some(ptype) = types.get(p.ptype.0 as usize) else { return false };
+
+        let mut accel = frame.dir_to_sim(ptype.gravity);
+        if ptype.attraction != 0.0 {
+            let world_pos = frame.to_world(p.pos);
+            for attractor in attractors.values() {
+                accel += frame.dir_to_sim(attractor.accel(world_pos)) * ptype.attraction;
+            }
+        }
+
+        p.vel += accel * dt;
+        p.vel = p.vel * (-ptype.drag * dt).exp();
+        p.pos += p.vel * dt;
+        p.rot += p.spin * dt;
+        return true;
+    });
+}
+
+
+/// Translation and rotation of a simulation space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SimFrame {
+    origin: vec2,
+    rot: f32,
+}
+
+impl SimFrame {
+    const IDENT: Self = Self { origin: vec2::ZERO, rot: 0.0 };
+
+    fn to_world(self, pos: vec2) -> vec2 {
+        self.origin + rotate(pos, self.rot)
+    }
+
+    fn to_sim(self, pos: vec2) -> vec2 {
+        rotate(pos - self.origin, -self.rot)
+    }
+
+    fn dir_to_sim(self, dir: vec2) -> vec2 {
+        rotate(dir, -self.rot)
+    }
+}
+
+
+/// Xorshift generator, good enough for visual effects.
+#[derive(Debug, Clone)]
+struct ParticleRng(u32);
+
+impl ParticleRng {
+    fn new(seed: u32) -> Self {
+        Self(seed.max(1)) // A state of 0 would only ever return 0
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        return x;
+    }
+
+    /// Returns a value in the range [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns a value between `range.0` and `range.1`.
+    fn range(&mut self, range: (f32, f32)) -> f32 {
+        range.0 + (range.1 - range.0) * self.next_f32()
+    }
+}
+
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::vector2::vec2;
+
+    use super::{emitter::{Emitter, EmitterShape, SimulationSpace}, ptype::{FrameMode, LifeCurve, ParticleType, SubEmitter}, Attractor, ParticleSystem};
+
+    #[test]
+    fn streams_and_lifetime() {
+        let mut sys = ParticleSystem::new();
+        let ptype = sys.add_type(ParticleType::default());
+        let emitter = sys.add_emitter(Emitter::new(ptype, EmitterShape::Circle { radius: 2.0 }).with_rate(4.0));
+
+        for _ in 0..4 {
+            sys.update(0.25);
+        }
+        assert_eq!(sys.particle_count(), 4);
+        assert!(sys.particles().all(|p| p.pos.0.abs() <= 2.0 && p.pos.1.abs() <= 2.0));
+
+        // Lifetime of 1s at 4 particles per second
+        for _ in 0..8 {
+            sys.update(0.25);
+        }
+        assert_eq!(sys.particle_count(), 4);
+
+        sys.emitter_mut(emitter).unwrap().rate = 0.0;
+        sys.update(1.0);
+        assert_eq!(sys.particle_count(), 0);
+
+        sys.burst(emitter, 10);
+        assert_eq!(sys.emitter(emitter).unwrap().particle_count(), 10);
+    }
+
+    #[test]
+    fn forces() {
+        let mut sys = ParticleSystem::new();
+        let falling = sys.add_type(ParticleType { lifetime: (10.0, 10.0), gravity: vec2(0.0, -10.0), ..Default::default() });
+        let attracted = sys.add_type(ParticleType { lifetime: (10.0, 10.0), ..Default::default() });
+        let dragged = sys.add_type(ParticleType { lifetime: (10.0, 10.0), speed: (4.0, 4.0), direction: (0.0, 0.0), drag: 2.0, ..Default::default() });
+
+        sys.spawn(falling, vec2::ZERO, 3);
+        sys.update(0.5);
+        assert!(sys.particles().all(|p| p.vel == vec2(0.0, -5.0) && p.pos == vec2(0.0, -2.5)));
+
+        sys.clear();
+        sys.add_attractor(Attractor::new(vec2(1.0, 0.0), 10.0, 2.0));
+        sys.spawn(attracted, vec2::ZERO, 1);
+        sys.spawn(attracted, vec2(5.0, 0.0), 1);
+        sys.update(0.1);
+        let particles = sys.particles().collect::<Vec<_>>();
+        assert!(particles[0].vel.0 > 0.0);
+        assert_eq!(particles[1].vel, vec2::ZERO);
+
+        sys.clear();
+        sys.spawn(dragged, vec2::ZERO, 1);
+        sys.update(0.5);
+        let p = sys.particles().next().unwrap();
+        assert!(p.vel.0 < 4.0 && p.vel.0 > 0.0);
+    }
+
+    #[test]
+    fn simulation_space() {
+        let mut sys = ParticleSystem::new();
+        let ptype = sys.add_type(ParticleType { lifetime: (10.0, 10.0), ..Default::default() });
+        let world = sys.add_emitter(Emitter::new(ptype, EmitterShape::Point));
+        let local = sys.add_emitter(Emitter::new(ptype, EmitterShape::Line { length: 0.0 }).with_space(SimulationSpace::Local));
+
+        sys.burst(world, 1);
+        sys.burst(local, 1);
+        sys.emitter_mut(world).unwrap().pos = vec2(5.0, 0.0);
+        sys.emitter_mut(local).unwrap().pos = vec2(5.0, 0.0);
+        sys.update(0.1);
+
+        let particles = sys.particles().collect::<Vec<_>>();
+        assert_eq!(particles[0].pos, vec2::ZERO);
+        assert_eq!(particles[1].pos, vec2(5.0, 0.0));
+    }
+
+    #[test]
+    fn sub_emitters() {
+        let mut sys = ParticleSystem::new().with_max_particles(8);
+        let spark = sys.add_type(ParticleType { lifetime: (10.0, 10.0), ..Default::default() });
+        let rocket = sys.add_type(ParticleType { lifetime: (0.5, 0.5), on_death: Some(SubEmitter::new(spark, 3)), ..Default::default() });
+
+        sys.spawn(rocket, vec2(1.0, 1.0), 2);
+        sys.update(0.5);
+        assert_eq!(sys.particle_count(), 6);
+        assert!(sys.particles().all(|p| p.ptype == spark && p.pos == vec2(1.0, 1.0)));
+
+        // Over the limit
+        sys.spawn(rocket, vec2::ZERO, 1);
+        sys.update(0.5);
+        assert_eq!(sys.particle_count(), 8);
+    }
+
+    #[test]
+    fn curves_and_frames() {
+        let curve = LifeCurve::new(vec![(1.0, 0.0), (0.0, 1.0), (0.5, 2.0)]);
+        assert_eq!(curve.sample(-1.0), 1.0);
+        assert_eq!(curve.sample(0.25), 1.5);
+        assert_eq!(curve.sample(0.75), 1.0);
+        assert_eq!(curve.sample(2.0), 0.0);
+        assert_eq!(LifeCurve::ONE.sample(0.3), 1.0);
+
+        assert_eq!(FrameMode::OverLife.frame(4, 0.0, 2.0, 0), 0);
+        assert_eq!(FrameMode::OverLife.frame(4, 1.0, 2.0, 0), 2);
+        assert_eq!(FrameMode::OverLife.frame(4, 2.0, 2.0, 0), 3);
+        assert_eq!(FrameMode::Fps(10.0).frame(4, 0.55, 2.0, 0), 1);
+        assert_eq!(FrameMode::Random.frame(4, 1.0, 2.0, 7), 3);
+    }
+}
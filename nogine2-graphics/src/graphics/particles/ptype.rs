@@ -0,0 +1,215 @@
+use std::borrow::Cow;
+
+use nogine2_core::{assert_expr, math::{lerp::Lerp, rect::Rect, vector2::{ivec2, vec2}}};
+
+use crate::{colors::{gradient::Gradient, rgba::RGBA32, Color}, graphics::texture::sprite::{Sprite, SpriteAtlas}};
+
+use super::ParticleTypeId;
+
+/// Describes how particles look and behave. Ranges are `(min, max)` pairs, picked uniformly for every particle when it's spawned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleType {
+    /// Lifetime in seconds.
+    pub lifetime: (f32, f32),
+    /// Initial speed in units per second.
+    pub speed: (f32, f32),
+    /// Direction of the initial velocity in radians, counter-clockwise from the X axis of the emitter.
+    pub direction: (f32, f32),
+    /// Initial rotation in radians.
+    pub rot: (f32, f32),
+    /// Angular velocity in radians per second.
+    pub spin: (f32, f32),
+    /// Initial scale factor.
+    pub size: (f32, f32),
+
+    /// Color over life.
+    pub color: Gradient,
+    /// Alpha factor over life.
+    pub alpha_over_life: LifeCurve,
+    /// Scale factor over life.
+    pub size_over_life: LifeCurve,
+    /// Rotation offset over life, in radians.
+    pub rot_over_life: LifeCurve,
+
+    /// Acceleration in units per second squared, in world space.
+    pub gravity: vec2,
+    /// Fraction of the velocity lost per second. Framerate independent.
+    pub drag: f32,
+    /// Factor applied to the forces of the attractors.
+    pub attraction: f32,
+
+    pub visual: ParticleVisual,
+    /// Particles spawned when a particle of this type dies.
+    pub on_death: Option<SubEmitter>,
+}
+
+impl Default for ParticleType {
+    fn default() -> Self {
+        Self {
+            lifetime: (1.0, 1.0), speed: (0.0, 0.0), direction: (0.0, std::f32::consts::TAU), rot: (0.0, 0.0), spin: (0.0, 0.0), size: (1.0, 1.0),
+            color: Gradient::WHITE, alpha_over_life: LifeCurve::ONE, size_over_life: LifeCurve::ONE, rot_over_life: LifeCurve::ZERO,
+            gravity: vec2::ZERO, drag: 0.0, attraction: 1.0,
+            visual: ParticleVisual::Rect { extents: vec2::one(0.1) }, on_death: None,
+        }
+    }
+}
+
+impl ParticleType {
+    /// Returns the tint of a particle at `t`, its normalized age.
+    pub fn tint(&self, t: f32) -> RGBA32 {
+        let color = self.color.sample(t);
+        return color.with_alpha(color.3 * self.alpha_over_life.sample(t));
+    }
+}
+
+
+/// Particles spawned from the position of a dying particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubEmitter {
+    pub ptype: ParticleTypeId,
+    pub count: u32,
+    /// Fraction of the velocity of the dying particle added to the new ones.
+    pub inherit_velocity: f32,
+}
+
+impl SubEmitter {
+    pub const fn new(ptype: ParticleTypeId, count: u32) -> Self {
+        Self { ptype, count, inherit_velocity: 0.0 }
+    }
+}
+
+
+/// Defines what is drawn for every particle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParticleVisual {
+    /// Solid rectangle of `extents` units.
+    Rect { extents: vec2 },
+    Sprite(Sprite),
+    /// Frames of a sprite atlas.
+    Animated(ParticleAnimation),
+}
+
+
+/// Frames of a `SpriteAtlas` played by a particle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleAnimation {
+    sheet: Sprite,
+    frames: Vec<Rect>,
+    mode: FrameMode,
+}
+
+impl ParticleAnimation {
+    /// Creates an animation from the cells at `frames`. Will panic if `frames` is empty.
+    pub fn new(atlas: &SpriteAtlas, frames: &[ivec2], mode: FrameMode) -> Self {
+        assert_expr!(!frames.is_empty(), "A particle animation must have at least one frame!");
+
+        let sheet = Sprite::new(atlas.tex().handle(), Rect::IDENT, atlas.tex().dims());
+        let frames = frames.iter().map(|f| atlas.get(*f).uv_rect()).collect();
+        return Self { sheet, frames, mode };
+    }
+
+    /// Sprite of the whole atlas texture.
+    pub fn sheet(&self) -> &Sprite {
+        &self.sheet
+    }
+
+    /// UV rects of the frames, relative to `sheet`.
+    pub fn frames(&self) -> &[Rect] {
+        &self.frames
+    }
+
+    pub fn mode(&self) -> FrameMode {
+        self.mode
+    }
+}
+
+
+/// Defines which frame of an animation a particle shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameMode {
+    /// Plays the frames once over the life of the particle.
+    OverLife,
+    /// Loops the frames at a fixed rate.
+    Fps(f32),
+    /// Shows a single frame picked when the particle is spawned.
+    Random,
+}
+
+impl FrameMode {
+    /// Returns the frame to show after `age` seconds of a life of `lifetime` seconds. `seed` is the random value of the particle.
+    pub fn frame(&self, frame_count: usize, age: f32, lifetime: f32, seed: u32) -> usize {
+        if frame_count == 0 {
+            return 0;
+        }
+
+        let index = match self {
+            FrameMode::OverLife => (age / lifetime.max(f32::EPSILON) * frame_count as f32) as usize,
+            FrameMode::Fps(fps) => (age * fps).max(0.0) as usize % frame_count,
+            FrameMode::Random => seed as usize % frame_count,
+        };
+        return index.min(frame_count - 1);
+    }
+}
+
+
+/// Piecewise linear curve over the normalized life of a particle. Keys are `(t, value)` pairs sorted by `t`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifeCurve {
+    keys: Cow<'static, [(f32, f32)]>,
+}
+
+impl LifeCurve {
+    pub const ZERO: Self = Self::from_static(&[(0.0, 0.0)]);
+    pub const ONE: Self = Self::from_static(&[(0.0, 1.0)]);
+
+    /// Creates a curve. Keys are sorted by `t`. Will panic if `keys` is empty.
+    pub fn new(keys: impl Into<Cow<'static, [(f32, f32)]>>) -> Self {
+        let mut keys = keys.into();
+        assert_expr!(!keys.is_empty(), "A curve must have at least one key!");
+
+        if !keys.is_sorted_by(|a, b| a.0 <= b.0) {
+            keys.to_mut().sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        return Self { keys };
+    }
+
+    /// Creates a curve from static keys. `keys` must be sorted by `t` and not be empty.
+    pub const fn from_static(keys: &'static [(f32, f32)]) -> Self {
+        Self { keys: Cow::Borrowed(keys) }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Self::new(vec![(0.0, value)])
+    }
+
+    /// Creates a curve between two values, from `t = 0.0` to `t = 1.0`.
+    pub fn linear(from: f32, to: f32) -> Self {
+        Self::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    pub fn keys(&self) -> &[(f32, f32)] {
+        &self.keys
+    }
+
+    /// Samples the curve. Values of `t` outside the keys are clamped.
+    pub fn sample(&self, t: f32) -> f32 {
+        let index = self.keys.partition_point(|x| x.0 <= t);
+        if index == 0 {
+            return self.keys[0].1;
+        }
+        if index == self.keys.len() {
+            return self.keys[index - 1].1;
+        }
+
+        let (t0, v0) = self.keys[index - 1];
+        let (t1, v1) = self.keys[index];
+        let local_t = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+        return v0.lerp(v1, local_t);
+    }
+}
+
+impl From<f32> for LifeCurve {
+    fn from(value: f32) -> Self {
+        Self::constant(value)
+    }
+}
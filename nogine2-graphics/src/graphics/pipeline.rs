@@ -1,6 +1,6 @@
 use std::ops::Add;
 
-use nogine2_core::{bytesize::ByteSize, log_error, main_thread::test_main_thread, math::{rect::IRect, vector2::ivec2}};
+use nogine2_core::{bytesize::ByteSize, log_error, main_thread::test_main_thread, math::{rect::IRect, vector2::{ivec2, uvec2}}};
 
 use crate::{colors::rgba::RGBA32, gl_wrapper::{framebuffer::GlFramebuffer, gl_viewport}};
 
@@ -22,6 +22,11 @@ impl<'a> SceneData<'a> {
         Self { batch_data }
     }
 
+    /// Returns the resolution the scene data was built for.
+    pub fn target_res(&self) -> uvec2 {
+        self.batch_data.target_res()
+    }
+
     /// Renders the scene data to a selected render texture.
     pub fn render_to(&self, rt: &RenderTexture, stats: &mut RenderStats) {
         test_main_thread();
@@ -95,18 +100,30 @@ pub struct BatchRenderStats {
     /// Number of triangles rendered.
     pub triangles: usize,
 
+    /// Number of sprite instances rendered with `Graphics::draw_instanced`.
+    pub instances: usize,
+
     /// Allocated memory size.
     pub allocated_memory: ByteSize,
 
     /// Memory being used.
     pub on_use_memory: ByteSize,
+
+    /// Vertex and index data uploaded to the GPU.
+    pub uploaded_memory: ByteSize,
+
+    /// Submissions merged into an earlier batch than the last one, each of them saving a draw call. Only happens with `RenderScopeCfgFlags::MERGE_OUT_OF_ORDER`.
+    pub out_of_order_merges: usize,
+
+    /// Statistics of every rendered viewport, in render order. UI rendering adds its own entry.
+    pub viewports: Vec<ViewportRenderStats>,
 }
 
 impl BatchRenderStats {
     pub const fn new() -> Self {
         Self {
-            draw_calls: 0, skipped_submissions: 0, rendered_submissions: 0, verts: 0, triangles: 0,
-            allocated_memory: ByteSize::new(0), on_use_memory: ByteSize::new(0),
+            draw_calls: 0, skipped_submissions: 0, rendered_submissions: 0, verts: 0, triangles: 0, instances: 0,
+            allocated_memory: ByteSize::new(0), on_use_memory: ByteSize::new(0), uploaded_memory: ByteSize::new(0), out_of_order_merges: 0, viewports: Vec::new(),
         }
     }
 
@@ -125,12 +142,35 @@ impl Add for BatchRenderStats {
             rendered_submissions: self.rendered_submissions + rhs.rendered_submissions,
             verts: self.verts + rhs.verts,
             triangles: self.triangles + rhs.triangles,
+            instances: self.instances + rhs.instances,
             allocated_memory: self.allocated_memory + rhs.allocated_memory,
             on_use_memory: self.on_use_memory + rhs.on_use_memory,
+            uploaded_memory: self.uploaded_memory + rhs.uploaded_memory,
+            out_of_order_merges: self.out_of_order_merges + rhs.out_of_order_merges,
+            viewports: [self.viewports, rhs.viewports].concat(),
         }
     }
 }
 
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct ViewportRenderStats {
+    /// Number of batch draw calls performed for the viewport.
+    pub draw_calls: usize,
+
+    /// Submissions that were culled by the viewport's camera.
+    pub skipped_submissions: usize,
+
+    /// Submissions that were rendered in the viewport.
+    pub rendered_submissions: usize,
+}
+
+impl ViewportRenderStats {
+    pub const fn new() -> Self {
+        Self { draw_calls: 0, skipped_submissions: 0, rendered_submissions: 0 }
+    }
+}
+
 #[non_exhaustive]
 #[derive(Debug, Clone)]
 pub struct BlitRenderStats {
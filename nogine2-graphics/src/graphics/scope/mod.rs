@@ -1,11 +1,11 @@
 use std::sync::Arc;
 
 use bitflags::bitflags;
-use nogine2_core::{assert_expr, main_thread::test_main_thread, math::{lerp::Lerp, mat3x3::mat3, rect::Rect, vector2::{ivec2, uvec2, vec2}, vector3::vec3}};
+use nogine2_core::{assert_expr, main_thread::test_main_thread, math::{lerp::Lerp, mat3x3::mat3, polygon, rect::{Rect, URect}, vector2::{ivec2, uvec2, vec2}, vector3::vec3}};
 
-use crate::{colors::{rgba::RGBA32, Color}, graphics::{batch::BatchPushCmd, pipeline::SceneData, text::{align::{HorTextAlign, VerTextAlign}, font::Font}, texture::rendertex::RenderTexture, vertex::BatchVertex}, TIME_TS};
+use crate::{colors::{rgba::RGBA32, Color}, graphics::{batch::{BatchPushCmd, BatchSorting}, pipeline::SceneData, text::{align::{HorTextAlign, VerTextAlign}, font::Font}, texture::rendertex::RenderTexture, vertex::{BatchVertex, InstanceVertex}}, TIME_TS};
 
-use super::{batch::BatchData, blending::BlendingMode, defaults::DefaultMaterials, material::Material, pipeline::{DefaultPipeline, RenderPipeline, RenderStats}, text::{engine::{helpers::GraphicMetrics, TextEngine}, font::TextStyle, rich::{CharQuad, CharVert, RichTextContext}, TextCfg}, texture::{sprite::Sprite, TextureHandle}, CameraData, Graphics };
+use super::{batch::BatchData, blending::BlendingMode, defaults::DefaultMaterials, material::Material, mesh, shapes::ShapeMesh, pipeline::{DefaultPipeline, RenderPipeline, RenderStats}, text::{engine::{helpers::GraphicMetrics, TextEngine}, font::TextStyle, rich::{CharQuad, CharVert, RichTextContext}, TextCfg}, texture::{sprite::Sprite, TextureHandle}, CameraData, Graphics, SpriteInstance, Viewport, WHITE_TEX };
 
 static DEFAULT_PIPELINE: DefaultPipeline = DefaultPipeline;
 
@@ -28,6 +28,7 @@ pub struct RenderScope {
     pivot: vec2,
     user_data: i32,
     material: Option<Arc<Material>>,
+    viewport_setup: Vec<Viewport>,
 
     text_engine: TextEngine,
     
@@ -59,6 +60,7 @@ impl RenderScope {
             pivot: vec2::ZERO,
             user_data: 0,
             material: None,
+            viewport_setup: Vec::new(),
 
             text_engine: TextEngine::new(),
             
@@ -153,6 +155,42 @@ impl RenderScope {
         self.batch_data.push(BatchPushCmd::Triangles { verts, indices, texture: cmd.texture, blending, material }, culling_enabled);
     }
 
+    pub(crate) fn draw_instanced(&mut self, cmd: InstancedSubmitCmd<'_>) {
+        test_main_thread();
+        assert_pre_tick!(self);
+        let inverted_y = self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN);
+
+        let y_scaling = if inverted_y { -1.0 } else { 1.0 };
+        let tex_dims = vec2::from(cmd.texture.dims());
+        let sprite_uv_size = cmd.uv_rect.size();
+
+        let instances = cmd.instances.iter().map(|inst| {
+            let uv_rect = Rect {
+                start: cmd.uv_rect.start + inst.uv_rect.start.scale(sprite_uv_size),
+                end: cmd.uv_rect.start + inst.uv_rect.end.scale(sprite_uv_size),
+            };
+            let extents = tex_dims.scale(inst.scale).scale(uv_rect.size()) / self.tex_ppu;
+            let tf_mat = mat3::tf_matrix(inst.pos.scale(vec2(1.0, y_scaling)), inst.rot, extents.scale(vec2(1.0, -y_scaling)));
+            let corner = |c: vec2| (&tf_mat * vec3::from_xy(c - self.pivot, 1.0)).xy();
+
+            let origin = corner(vec2(0.0, 0.0));
+            let (uv_start, uv_end) = if inverted_y { (uv_rect.ld(), uv_rect.ru()) } else { (uv_rect.lu(), uv_rect.rd()) };
+            return InstanceVertex {
+                origin,
+                right: corner(vec2(1.0, 0.0)) - origin,
+                up: corner(vec2(0.0, 1.0)) - origin,
+                uv_start, uv_end,
+                tint: inst.tint,
+                user_data: self.user_data,
+            };
+        }).collect::<Vec<_>>();
+
+        let blending = self.blending;
+        let material = self.material();
+        let culling_enabled = self.cfg_flags.contains(RenderScopeCfgFlags::CULLING);
+        self.batch_data.push(BatchPushCmd::Instances { instances: &instances, texture: cmd.texture, blending, material }, culling_enabled);
+    }
+
     pub(crate) fn draw_points(&mut self, cmd: PointsSubmitCmd<'_>) {
         test_main_thread();
         assert_pre_tick!(self);
@@ -200,6 +238,80 @@ impl RenderScope {
         self.batch_data.push(BatchPushCmd::Lines { verts, blending, material }, culling_enabled);
     }
 
+    pub(crate) fn draw_mesh<I: Copy + Into<u32>>(&mut self, cmd: MeshSubmitCmd<'_, I>) {
+        test_main_thread();
+        assert_pre_tick!(self);
+        mesh::assert_mesh(cmd.verts, cmd.indices);
+
+        let y_scaling = if self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN) { -1.0 } else { 1.0 };
+        let tf_mat = mat3::tf_matrix(cmd.pos.scale(vec2(1.0, y_scaling)), cmd.rot, cmd.scale.scale(vec2(1.0, y_scaling)));
+
+        let blending = self.blending;
+        let material = self.material();
+        let culling_enabled = self.cfg_flags.contains(RenderScopeCfgFlags::CULLING);
+        for (mut verts, indices) in mesh::split_mesh(cmd.verts, cmd.indices, mesh::MAX_PART_VERTS, usize::MAX) {
+            for v in &mut verts {
+                v.pos = (&tf_mat * vec3::from_xy(v.pos, 1.0)).xy();
+            }
+
+            self.batch_data.push(BatchPushCmd::Triangles { verts: &verts, indices: &indices, texture: cmd.texture.clone(), blending, material: material.clone() }, culling_enabled);
+        }
+    }
+
+    pub(crate) fn draw_shape(&mut self, cmd: ShapeSubmitCmd) {
+        test_main_thread();
+        assert_pre_tick!(self);
+
+        if cmd.mesh.indices.is_empty() {
+            return;
+        }
+
+        let y_scaling = if self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN) { -1.0 } else { 1.0 };
+        let tf_mat = mat3::tf_matrix(cmd.pos.scale(vec2(1.0, y_scaling)), cmd.rot, vec2(1.0, y_scaling));
+
+        let user_data = self.user_data;
+        let blending = self.blending;
+        let material = self.material();
+        let culling_enabled = self.cfg_flags.contains(RenderScopeCfgFlags::CULLING);
+        for (points, indices) in cmd.mesh.parts() {
+            let verts = points.iter().map(|p| BatchVertex {
+                pos: (&tf_mat * vec3::from_xy(*p, 1.0)).xy(),
+                tint: cmd.color,
+                user_data,
+                ..Default::default()
+            }).collect::<Vec<_>>();
+
+            self.batch_data.push(BatchPushCmd::Triangles { verts: &verts, indices: &indices, texture: WHITE_TEX.get(), blending, material: material.clone() }, culling_enabled);
+        }
+    }
+
+    /// Returns the amount of segments needed for a circle of `radius` world units to look smooth on screen.
+    pub(crate) fn circle_segments(&self, radius: f32) -> usize {
+        const MAX_ERROR_PX: f32 = 0.25;
+
+        let camera = self.camera();
+        let px_per_unit = self.batch_data.target_res().1 as f32 / camera.view_extents().1.abs().max(f32::EPSILON);
+        let radius_px = radius.abs() * px_per_unit;
+        if radius_px <= MAX_ERROR_PX {
+            return 6;
+        }
+
+        let segments = std::f32::consts::PI / (1.0 - MAX_ERROR_PX / radius_px).clamp(-1.0, 1.0).acos();
+        return (segments.ceil() as usize).clamp(6, 256);
+    }
+
+    /// Returns the rect covered by a `draw_rect` call with the same extents, relative to its position.
+    pub(crate) fn local_rect(&self, extents: vec2) -> Rect {
+        let start_x = -self.pivot.0 * extents.0;
+        if self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN) {
+            let top = -self.pivot.1 * extents.1;
+            return Rect { start: vec2(start_x, top), end: vec2(start_x + extents.0, top + extents.1) };
+        } else {
+            let top = self.pivot.1 * extents.1;
+            return Rect { start: vec2(start_x, top - extents.1), end: vec2(start_x + extents.0, top) };
+        }
+    }
+
     pub(crate) fn draw_9_patch(&mut self, cmd: NinePatchSubmitCmd) {
         fn bilinear(rect: Rect, mut uv: vec2, inverted_y: bool) -> vec2 {
             if !inverted_y {
@@ -214,13 +326,24 @@ impl RenderScope {
         test_main_thread();
         assert_pre_tick!(self);
 
-        let corner_size = vec2::from(cmd.sprite.dims()) / self.tex_ppu * cmd.corner_scaling / 3.0;
-        let min_extents = corner_size * 2.0;
-        let extents = cmd.extents.max(min_extents);
-        let rel_corner = corner_size.inv_scale(extents);
-        
         let inverted_y = self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN);
 
+        // Cuts in sprite pixels, from the first to the last column and row of vertices. Rows start at the bottom of the image unless Y is inverted
+        let dims = vec2::from(cmd.sprite.dims());
+        let (center_start, center_end) = match cmd.center {
+            Some(center) => (vec2::from(center.start), vec2::from(center.end)),
+            None => (dims / 3.0, dims * 2.0 / 3.0),
+        };
+        let cols = [0.0, center_start.0, center_end.0, dims.0];
+        let rows = if inverted_y { [0.0, center_start.1, center_end.1, dims.1] } else { [dims.1, center_end.1, center_start.1, 0.0] };
+
+        let px_to_units = cmd.corner_scaling / self.tex_ppu;
+        let border_start = vec2(cols[1] - cols[0], (rows[1] - rows[0]).abs()) * px_to_units;
+        let border_end = vec2(cols[3] - cols[2], (rows[3] - rows[2]).abs()) * px_to_units;
+        let extents = cmd.extents.max(border_start + border_end);
+        let rel_start = border_start.inv_scale(extents);
+        let rel_end = vec2::ONE - border_end.inv_scale(extents);
+
         let y_scaling = if inverted_y { -1.0 } else { 1.0 };
         let tf_mat = mat3::tf_matrix(
             cmd.pos.scale(vec2(1.0, y_scaling)),
@@ -230,17 +353,20 @@ impl RenderScope {
 
         let uv_rect = cmd.sprite.uv_rect();
         let user_data = self.user_data;
-        let diag = [vec2::ZERO, rel_corner, vec2::ONE - rel_corner, vec2::ONE];
+        let diag_x = [0.0, rel_start.0, rel_end.0, 1.0];
+        let diag_y = [0.0, rel_start.1, rel_end.1, 1.0];
         let mut verts = [BatchVertex::default(); 16];
         for i in 0..16 {
-            let mut uv = vec2::from(ivec2(i as i32 % 4, i as i32 / 4)) / 3.0;
+            let (col, row) = (i % 4, i / 4);
+            let mut uv = vec2::from(ivec2(col as i32, row as i32)) / 3.0;
             uv.1 = 1.0 - uv.1;
 
-            let pos = diag[i % 4].xvec() + diag[i / 4].yvec();
+            let tex_uv = vec2(cols[col] / dims.0, 1.0 - (rows[row] - rows[0]).abs() / dims.1);
+            let pos = vec2(diag_x[col], diag_y[row]);
             verts[i] = BatchVertex {
                 pos: (&tf_mat * vec3::from_xy(pos - self.pivot, 1.0)).xy(),
                 tint: cmd.tint,
-                uv: bilinear(uv_rect, uv, inverted_y),
+                uv: bilinear(uv_rect, tex_uv, inverted_y),
                 uv1: uv,
                 tex_id: 0,
                 user_data
@@ -275,6 +401,67 @@ impl RenderScope {
         }, culling_enabled);
     }
 
+    pub(crate) fn draw_polygon(&mut self, cfg: PolygonCfg<'_>) {
+        fn bilinear(rect: Rect, t: vec2) -> vec2 {
+            let down = rect.ld().lerp(rect.rd(), t.0);
+            let up = rect.lu().lerp(rect.ru(), t.0);
+            return down.lerp(up, t.1);
+        }
+
+        test_main_thread();
+        assert_pre_tick!(self);
+
+        let vert_count = cfg.points.len() + cfg.holes.iter().map(|x| x.len()).sum::<usize>();
+        assert_expr!(cfg.colors.len() == 1 || cfg.colors.len() == vert_count, "Polygons need either one color or one color per point!");
+
+        let indices = polygon::triangulate(cfg.points, cfg.holes);
+        if indices.is_empty() {
+            return;
+        }
+
+        let inverted_y = self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN);
+        let y_scaling = if inverted_y { -1.0 } else { 1.0 };
+        let tf_mat = mat3::tf_matrix(cfg.pos.scale(vec2(1.0, y_scaling)), cfg.rot, cfg.scale.scale(vec2(1.0, y_scaling)));
+
+        // The pivot is applied over the bounding box of the outline, as if it was a rect
+        let bb = cfg.points.iter().skip(1).fold(Rect { start: cfg.points[0], end: cfg.points[0] }, |r, p| Rect { start: r.start.min(*p), end: r.end.max(*p) });
+        let pivot = if inverted_y {
+            bb.start + self.pivot.scale(bb.size())
+        } else {
+            vec2(bb.start.0 + self.pivot.0 * bb.size().0, bb.end.1 - self.pivot.1 * bb.size().1)
+        };
+
+        let uv_proj = cfg.uv_proj.unwrap_or(bb);
+        let uv_proj_size = uv_proj.size().max(vec2::one(f32::EPSILON));
+
+        let user_data = self.user_data;
+        let verts = cfg.points.iter().chain(cfg.holes.iter().flat_map(|x| x.iter())).enumerate().map(|(i, p)| {
+            let rel = (*p - uv_proj.start).inv_scale(uv_proj_size);
+            BatchVertex {
+                pos: (&tf_mat * vec3::from_xy(*p - pivot, 1.0)).xy(),
+                tint: if cfg.colors.len() == 1 { cfg.colors[0] } else { cfg.colors[i] },
+                uv: bilinear(cfg.uv_rect, rel),
+                uv1: bilinear(Rect::IDENT, rel),
+                tex_id: 0,
+                user_data
+            }
+        }).collect::<Vec<_>>();
+
+        let texture = cfg.texture.unwrap_or_else(|| WHITE_TEX.get());
+        let blending = self.blending;
+        let material = self.material();
+        let culling_enabled = self.cfg_flags.contains(RenderScopeCfgFlags::CULLING);
+        for (verts, indices) in mesh::split_mesh(&verts, &indices, mesh::MAX_PART_VERTS, usize::MAX) {
+            self.batch_data.push(BatchPushCmd::Triangles {
+                verts: &verts,
+                indices: &indices,
+                texture: texture.clone(),
+                blending,
+                material: material.clone()
+            }, culling_enabled);
+        }
+    }
+
     pub(crate) fn draw_text(
         &mut self,
         origin: vec2,
@@ -477,6 +664,27 @@ impl RenderScope {
         return self.batch_data.camera();
     }
 
+    /// Returns the viewports of the current frame.
+    pub fn viewports(&self) -> Vec<Viewport> {
+        return self.batch_data.viewports();
+    }
+
+    /// Sets the viewports from the next frame on. All of them are drawn from the same submissions. If empty, a single viewport covers the whole target with the camera of the frame, which is the default.
+    pub fn set_viewports(&mut self, viewports: &[Viewport]) {
+        self.viewport_setup = viewports.to_vec();
+    }
+
+    /// Returns the viewport new submissions are restricted to, if any.
+    pub fn target_viewport(&self) -> Option<usize> {
+        return self.batch_data.target_viewport();
+    }
+
+    /// Restricts new submissions to a single viewport, or to all of them if `None`. Will panic if the viewport doesn't exist.
+    pub fn set_target_viewport(&mut self, viewport: Option<usize>) {
+        assert_expr!(viewport.is_none_or(|x| x < self.batch_data.viewport_count()), "Viewport doesn't exist!");
+        self.batch_data.set_target_viewport(viewport);
+    }
+
     /// Returns the pixels per unit for textures.
     pub fn pixels_per_unit(&self) -> f32 {
         return self.tex_ppu;
@@ -498,6 +706,16 @@ impl RenderScope {
         self.user_data = user_data;
     }
 
+    /// Returns the depth of new submissions.
+    pub fn depth(&self) -> f32 {
+        return self.batch_data.depth();
+    }
+
+    /// Sets the depth of new submissions. Submissions with a higher depth are drawn behind.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.batch_data.set_depth(depth);
+    }
+
     /// Returns the current pivot.
     pub fn pivot(&self) -> vec2 {
         return self.pivot;
@@ -619,12 +837,16 @@ impl RenderScope {
     }
     
 
-    pub(crate) fn begin_render(&mut self, mut camera: CameraData, target_res: uvec2, clear_col: RGBA32, pipeline: *const dyn RenderPipeline) {
+    pub(crate) fn begin_render(&mut self, camera: CameraData, target_res: uvec2, clear_col: RGBA32, pipeline: *const dyn RenderPipeline) {
+        let mut viewports = if self.viewport_setup.is_empty() { vec![Viewport::new(camera, Viewport::FULL)] } else { self.viewport_setup.clone() };
         if self.cfg_flags.contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN) {
-            camera.center.1 = -camera.center.1;
+            for viewport in &mut viewports {
+                viewport.camera.center.1 = -viewport.camera.center.1;
+                viewport.camera.set_rot(-viewport.camera.rot());
+            }
         }
         
-        self.batch_data.setup_frame(camera, target_res);
+        self.batch_data.setup_frame(&viewports, target_res);
         self.render_started = true;
         self.pipeline = Some(PipelinePtr(pipeline));
         self.clear_col = clear_col;
@@ -634,6 +856,8 @@ impl RenderScope {
         assert_pre_tick!(self);
         self.render_started = false;
 
+        self.finish_batch();
+
         let mut stats = RenderStats::new();
         let render_pipeline = unsafe { self.pipeline.as_ref().unwrap().0.as_ref().unwrap() };
 
@@ -645,6 +869,15 @@ impl RenderScope {
         return stats;
     }
 
+    /// Sorts and batches all the submissions of the frame.
+    pub(crate) fn finish_batch(&mut self) {
+        self.batch_data.finish(BatchSorting {
+            y_sort: self.cfg_flags.contains(RenderScopeCfgFlags::Y_SORT),
+            material_sort: self.cfg_flags.contains(RenderScopeCfgFlags::MATERIAL_SORT),
+            merge_out_of_order: self.cfg_flags.contains(RenderScopeCfgFlags::MERGE_OUT_OF_ORDER),
+        });
+    }
+
     fn get_scene_data(&self) -> SceneData<'_> {
         SceneData::new(&self.batch_data)
     }
@@ -661,6 +894,15 @@ bitflags! {
         /// Defines positive Y as down and negative Y as up. Enabled by default on UI scopes.
         const POSITIVE_Y_IS_DOWN = 1 << 1;
 
+        /// Draws submissions whose bottom edge is higher up on screen first when they share the same depth. Useful for top-down games.
+        const Y_SORT = 1 << 2;
+
+        /// Groups submissions with the same material and texture when they share the same depth, reducing draw calls. Overrides the submission order.
+        const MATERIAL_SORT = 1 << 3;
+
+        /// Allows merging submissions into earlier compatible batches when nothing in between overlaps them, reducing draw calls while keeping the result.
+        const MERGE_OUT_OF_ORDER = 1 << 4;
+
         /// Default configuration.
         const DEFAULT = Self::CULLING.bits();
         const DEFAULT_UI = Self::DEFAULT.bits() | Self::POSITIVE_Y_IS_DOWN.bits();
@@ -682,6 +924,41 @@ pub struct ScopeRenderSetup<'a> {
 }
 
 
+/// Polygon to be drawn with `Graphics::draw_polygon_ext`.
+pub struct PolygonCfg<'a> {
+    pub pos: vec2,
+    pub rot: f32,
+    pub scale: vec2,
+    /// Outline of the polygon, in any winding order. It may be concave, but it must not intersect itself.
+    pub points: &'a [vec2],
+    pub holes: &'a [&'a [vec2]],
+    /// Either one color for the whole polygon or one color per point, holes included.
+    pub colors: &'a [RGBA32],
+    /// Texture to apply, white if `None`.
+    pub texture: Option<TextureHandle>,
+    /// Region of the texture to apply.
+    pub uv_rect: Rect,
+    /// Area of the polygon, in local space, that the texture region covers. The bounding box of the outline if `None`.
+    pub uv_proj: Option<Rect>,
+}
+
+impl Default for PolygonCfg<'_> {
+    fn default() -> Self {
+        Self {
+            pos: vec2::ZERO,
+            rot: 0.0,
+            scale: vec2::ONE,
+            points: &[],
+            holes: &[],
+            colors: &[RGBA32::WHITE],
+            texture: None,
+            uv_rect: Rect::IDENT,
+            uv_proj: None,
+        }
+    }
+}
+
+
 pub(crate) struct RectSubmitCmd {
     pub pos: vec2,
     pub rot: f32,
@@ -691,6 +968,13 @@ pub(crate) struct RectSubmitCmd {
     pub uv_rect: Rect,
 }
 
+pub(crate) struct InstancedSubmitCmd<'a> {
+    pub texture: TextureHandle,
+    /// UV rect of the sprite, which the UV rects of the instances are relative to.
+    pub uv_rect: Rect,
+    pub instances: &'a [SpriteInstance],
+}
+
 pub(crate) struct PointsSubmitCmd<'a> {
     pub points: &'a [(vec2, RGBA32)],
 }
@@ -700,6 +984,22 @@ pub(crate) struct LineSubmitCmd {
     pub cols: [RGBA32; 2],
 }
 
+pub(crate) struct MeshSubmitCmd<'a, I> {
+    pub pos: vec2,
+    pub rot: f32,
+    pub scale: vec2,
+    pub verts: &'a [BatchVertex],
+    pub indices: &'a [I],
+    pub texture: TextureHandle,
+}
+
+pub(crate) struct ShapeSubmitCmd {
+    pub pos: vec2,
+    pub rot: f32,
+    pub mesh: ShapeMesh,
+    pub color: RGBA32,
+}
+
 pub(crate) struct NinePatchSubmitCmd {
     pub pos: vec2,
     pub rot: f32,
@@ -707,6 +1007,8 @@ pub(crate) struct NinePatchSubmitCmd {
     pub tint: RGBA32,
     pub sprite: Sprite,
     pub corner_scaling: f32,
+    /// Center of the patch in sprite pixels. Thirds of the sprite if `None`.
+    pub center: Option<URect>,
 }
 
 
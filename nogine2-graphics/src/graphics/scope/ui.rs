@@ -36,13 +36,17 @@ impl UIScope {
     }
 
     pub(crate) fn begin_render(&mut self, res: uvec2, pipeline: *const dyn RenderPipeline) {
-        self.inner.begin_render(CameraData { center: vec2::from(res).scale(vec2(0.5, 0.5)), extents: vec2::from(res) }, res, RGBA32::CLEAR, pipeline);
+        self.inner.begin_render(CameraData::new(vec2::from(res).scale(vec2(0.5, 0.5)), vec2::from(res)), res, RGBA32::CLEAR, pipeline);
     }
 
     pub(crate) fn end_render(&mut self, rt: &RenderTexture) -> RenderStats {
         self.inner.end_render(rt, true, None)
     }
 
+    pub(crate) fn finish_batch(&mut self) {
+        self.inner.finish_batch();
+    }
+
     pub(crate) fn get_scene_data(&self) -> SceneData<'_> {
         self.inner.get_scene_data()
     }
@@ -2,7 +2,9 @@ use std::{ffi::CStr, sync::Arc};
 
 use nogine2_core::{assert_expr, main_thread::test_main_thread};
 
-use crate::gl_wrapper::{gl_uniform_loc, program::GlProgram, shader::{GlShader, GlShaderType}};
+use crate::gl_wrapper::{gl_uniform, gl_uniform_loc, program::GlProgram, shader::{GlShader, GlShaderType}};
+
+use super::{defaults::MAX_BATCH_UNITS, Graphics};
 
 #[repr(u32)]
 #[non_exhaustive]
@@ -50,6 +52,8 @@ impl SubShader {
 pub struct Shader {
     gl_obj: GlProgram,
     samplers: Vec<u32>,
+    /// Usable lengths of `uTextures` and `uTextureArrays`.
+    batch_slots: (usize, usize),
 }
 
 impl Shader {
@@ -60,8 +64,35 @@ impl Shader {
 
         let gl_obj = GlProgram::new(&[&vert.gl_obj, &frag.gl_obj])?;
         let samplers = gl_obj.get_samplers();
+        let batch_slots = Self::setup_batch_slots(&gl_obj);
         
-        return Some(Arc::new(Self { gl_obj, samplers }));
+        return Some(Arc::new(Self { gl_obj, samplers, batch_slots }));
+    }
+
+    /// Points `uTextures` and then `uTextureArrays` to consecutive texture units, limited by the available units. Returns how many of each are usable.
+    fn setup_batch_slots(gl_obj: &GlProgram) -> (usize, usize) {
+        const UNITS: [i32; MAX_BATCH_UNITS] = {
+            let mut units = [0; MAX_BATCH_UNITS];
+            let mut i = 0;
+            while i < MAX_BATCH_UNITS {
+                units[i] = i as i32;
+                i += 1;
+            }
+            units
+        };
+
+        let units = Graphics::max_texture_units().min(MAX_BATCH_UNITS);
+        let textures = gl_obj.uniform_array_len(c"uTextures").min(units);
+        let arrays = gl_obj.uniform_array_len(c"uTextureArrays").min(units - textures);
+
+        gl_obj.use_program();
+        if let Some(loc) = gl_uniform_loc(gl_obj, c"uTextures") {
+            gl_uniform::set_i32_arr(loc, &UNITS[..textures]);
+        }
+        if let Some(loc) = gl_uniform_loc(gl_obj, c"uTextureArrays") {
+            gl_uniform::set_i32_arr(loc, &UNITS[textures..(textures + arrays)]);
+        }
+        return (textures, arrays);
     }
 
     pub(crate) fn use_shader(&self) -> bool {
@@ -80,4 +111,9 @@ impl Shader {
     pub(crate) fn sampler_count(&self) -> usize {
         return self.samplers.len();
     }
+
+    /// Returns the usable lengths of the `uTextures` and `uTextureArrays` uniforms. Their units start at `0` and at the end of `uTextures` respectively.
+    pub(crate) fn batch_slots(&self) -> (usize, usize) {
+        return self.batch_slots;
+    }
 }
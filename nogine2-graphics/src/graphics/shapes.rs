@@ -0,0 +1,368 @@
+use std::f32::consts::{PI, TAU};
+
+use nogine2_core::math::{rect::Rect, vector2::vec2};
+
+use super::mesh::{split_mesh, MAX_PART_VERTS};
+
+/// How two segments of a stroke are connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet, falling back to `Bevel` past the miter limit.
+    #[default]
+    Miter,
+    /// Connects the outer edges with a straight edge.
+    Bevel,
+    /// Connects the outer edges with an arc.
+    Round,
+}
+
+/// How the ends of an open stroke are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// Ends exactly at the end points.
+    #[default]
+    Butt,
+    /// Extends half the thickness past the end points.
+    Square,
+    /// Ends with a half circle.
+    Round,
+}
+
+/// Defines how thick lines are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle {
+    pub thickness: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Maximum ratio between the miter length and half the thickness.
+    pub miter_limit: f32,
+}
+
+impl StrokeStyle {
+    pub const fn new(thickness: f32) -> Self {
+        Self { thickness, join: LineJoin::Miter, cap: LineCap::Butt, miter_limit: 4.0 }
+    }
+
+    pub const fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        return self;
+    }
+
+    pub const fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        return self;
+    }
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+
+/// Triangulated shape, split with `parts` before being pushed into the triangle batch.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ShapeMesh {
+    pub verts: Vec<vec2>,
+    pub indices: Vec<u32>,
+}
+
+impl ShapeMesh {
+    /// Splits the mesh into parts small enough for 16 bit indices.
+    pub fn parts(&self) -> Vec<(Vec<vec2>, Vec<u16>)> {
+        return split_mesh(&self.verts, &self.indices, MAX_PART_VERTS, usize::MAX);
+    }
+
+    fn push_vert(&mut self, v: vec2) -> u32 {
+        self.verts.push(v);
+        return (self.verts.len() - 1) as u32;
+    }
+
+    fn push_tri(&mut self, a: vec2, b: vec2, c: vec2) {
+        let i = self.push_vert(a);
+        self.push_vert(b);
+        self.push_vert(c);
+        self.indices.extend_from_slice(&[i, i + 1, i + 2]);
+    }
+
+    /// Fills the area between `center` and `ring`.
+    pub fn fan(center: vec2, ring: &[vec2], closed: bool) -> Self {
+        let mut res = Self::default();
+        res.push_fan(center, ring, closed);
+        return res;
+    }
+
+    /// Fills the area between two rings with the same amount of points.
+    pub fn strip(outer: &[vec2], inner: &[vec2], closed: bool) -> Self {
+        let mut res = Self::default();
+        let len = outer.len().min(inner.len());
+        if len < 2 {
+            return res;
+        }
+
+        res.verts.extend_from_slice(&outer[..len]);
+        res.verts.extend_from_slice(&inner[..len]);
+
+        let segments = if closed { len } else { len - 1 };
+        for i in 0..segments {
+            let j = (i + 1) % len;
+            let (o0, o1, i0, i1) = (i as u32, j as u32, (i + len) as u32, (j + len) as u32);
+            res.indices.extend_from_slice(&[o0, o1, i1, i1, i0, o0]);
+        }
+        return res;
+    }
+
+    fn push_fan(&mut self, center: vec2, ring: &[vec2], closed: bool) {
+        if ring.len() < 2 {
+            return;
+        }
+
+        let c = self.push_vert(center);
+        let start = self.verts.len() as u32;
+        self.verts.extend_from_slice(ring);
+
+        let len = ring.len() as u32;
+        let segments = if closed { len } else { len - 1 };
+        for i in 0..segments {
+            self.indices.extend_from_slice(&[c, start + i, start + (i + 1) % len]);
+        }
+    }
+}
+
+
+/// Returns the points of an elliptical arc from angle `start` to `end`, both included.
+pub(crate) fn arc_points(center: vec2, radii: vec2, start: f32, end: f32, segments: usize) -> Vec<vec2> {
+    let segments = segments.max(1);
+    return (0..=segments).map(|i| {
+        let (sin, cos) = (start + (end - start) * i as f32 / segments as f32).sin_cos();
+        center + vec2(cos * radii.0, sin * radii.1)
+    }).collect();
+}
+
+/// Returns the points of a full ellipse, without repeating the first one.
+pub(crate) fn ellipse_points(center: vec2, radii: vec2, segments: usize) -> Vec<vec2> {
+    let mut points = arc_points(center, radii, 0.0, TAU, segments.max(3));
+    points.pop();
+    return points;
+}
+
+/// Returns the counter-clockwise outline of a rounded rect. `radius` is clamped to half the smallest side. The amount of points only depends on `corner_segments`.
+pub(crate) fn rounded_rect_points(rect: Rect, radius: f32, corner_segments: usize) -> Vec<vec2> {
+    let size = rect.size();
+    let radius = radius.clamp(0.0, size.0.min(size.1).max(0.0) * 0.5);
+
+    let corners = [
+        (vec2(rect.end.0 - radius, rect.start.1 + radius), -0.5 * PI),
+        (vec2(rect.end.0 - radius, rect.end.1 - radius), 0.0),
+        (vec2(rect.start.0 + radius, rect.end.1 - radius), 0.5 * PI),
+        (vec2(rect.start.0 + radius, rect.start.1 + radius), PI),
+    ];
+
+    return corners.into_iter().flat_map(|(center, start)| arc_points(center, vec2::one(radius), start, start + 0.5 * PI, corner_segments)).collect();
+}
+
+/// Tessellates a thick line through `points`. `round_segments` is the amount of segments a full circle of the line's thickness would have.
+pub(crate) fn stroke(points: &[vec2], closed: bool, style: StrokeStyle, round_segments: usize) -> ShapeMesh {
+    let mut res = ShapeMesh::default();
+
+    let mut points = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 2 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 2 {
+        return res;
+    }
+
+    let hw = style.thickness * 0.5;
+    let len = points.len();
+    let segment_count = if closed { len } else { len - 1 };
+    let dir = |i: usize| (points[(i + 1) % len] - points[i]).normalize_or_zero();
+
+    // Inner side of every joint and how far its inner corners are pulled back, so both segments meet at the bisector instead of overlapping
+    let seg_len = |i: usize| (points[(i + 1) % len] - points[i]).length();
+    let trims = (0..len).map(|i| {
+        if !closed && (i == 0 || i == len - 1) {
+            return (0.0, 0.0);
+        }
+
+        let prev = (i + len - 1) % len;
+        let turn = cross(dir(prev), dir(i));
+        let trim = hw * (angle_between(dir(prev), dir(i)).abs() * 0.5).tan();
+        if turn.abs() <= 1e-6 || !(0.0..=seg_len(prev).min(seg_len(i)) * 0.5).contains(&trim) {
+            return (0.0, 0.0); // Straight or too sharp for the segments to be trimmed
+        }
+        return (turn.signum(), trim);
+    }).collect::<Vec<_>>();
+
+    for i in 0..segment_count {
+        let mut a = points[i];
+        let mut b = points[(i + 1) % len];
+        let d = dir(i);
+
+        if !closed && style.cap == LineCap::Square {
+            if i == 0 {
+                a = a - d * hw;
+            }
+            if i == segment_count - 1 {
+                b = b + d * hw;
+            }
+        }
+
+        let n = left(d) * hw;
+        let (start_side, start_trim) = trims[i];
+        let (end_side, end_trim) = trims[(i + 1) % len];
+        let trimmed = |side: f32, trim: f32, wanted: f32| if side == wanted { trim } else { 0.0 };
+
+        // Convex outline going through the centers of both ends, where the trimmed corners meet the neighbour segments
+        let base = res.verts.len() as u32;
+        res.verts.extend_from_slice(&[
+            a + n + d * trimmed(start_side, start_trim, 1.0),
+            a,
+            a - n + d * trimmed(start_side, start_trim, -1.0),
+            b - n - d * trimmed(end_side, end_trim, -1.0),
+            b,
+            b + n - d * trimmed(end_side, end_trim, 1.0),
+        ]);
+        res.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3, base, base + 3, base + 4, base, base + 4, base + 5]);
+    }
+
+    // Joins
+    let joints = if closed { 0..len } else { 1..len - 1 };
+    for i in joints {
+        let p = points[i];
+        let d0 = dir((i + len - 1) % len);
+        let d1 = dir(i);
+        let turn = cross(d0, d1);
+        if turn.abs() <= 1e-6 && d0.dot(d1) > 0.0 {
+            continue; // Straight
+        }
+
+        // The outer side is the opposite to the turn
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let n0 = left(d0) * side;
+        let n1 = left(d1) * side;
+        let (a, b) = (p + n0 * hw, p + n1 * hw);
+
+        match style.join {
+            LineJoin::Miter => {
+                let m = (n0 + n1).normalize_or_zero();
+                let cos = m.dot(n0);
+                if cos > 1e-4 && 1.0 / cos <= style.miter_limit {
+                    let tip = p + m * (hw / cos);
+                    res.push_tri(p, a, tip);
+                    res.push_tri(p, tip, b);
+                } else {
+                    res.push_tri(p, a, b);
+                }
+            },
+            LineJoin::Bevel => res.push_tri(p, a, b),
+            LineJoin::Round => {
+                let start = n0.1.atan2(n0.0);
+                let sweep = angle_between(n0, n1);
+                let segments = ((sweep.abs() / TAU) * round_segments as f32).ceil().max(1.0) as usize;
+                let arc = (0..=segments).map(|j| {
+                    let (sin, cos) = (start + sweep * j as f32 / segments as f32).sin_cos();
+                    p + vec2(cos, sin) * hw
+                }).collect::<Vec<_>>();
+                res.push_fan(p, &arc, false);
+            },
+        }
+    }
+
+    // Caps
+    if !closed && style.cap == LineCap::Round {
+        let cap_segments = (round_segments / 2).max(2);
+        for (p, d) in [(points[0], dir(0) * -1.0), (points[len - 1], dir(len - 2))] {
+            let n = left(d);
+            let start = (-n.1).atan2(-n.0);
+            let arc = arc_points(p, vec2::one(hw), start, start + PI, cap_segments);
+            res.push_fan(p, &arc, false);
+        }
+    }
+
+    return res;
+}
+
+fn cross(a: vec2, b: vec2) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+/// Rotates 90º counter-clockwise.
+fn left(a: vec2) -> vec2 {
+    vec2(-a.1, a.0)
+}
+
+/// Signed angle from `a` to `b`.
+fn angle_between(a: vec2, b: vec2) -> f32 {
+    cross(a, b).atan2(a.dot(b))
+}
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::{rect::Rect, vector2::vec2};
+
+    use super::{ellipse_points, rounded_rect_points, stroke, LineCap, LineJoin, ShapeMesh, StrokeStyle};
+
+    fn area(mesh: &ShapeMesh) -> f32 {
+        mesh.indices.chunks(3).map(|t| {
+            let (a, b, c) = (mesh.verts[t[0] as usize], mesh.verts[t[1] as usize], mesh.verts[t[2] as usize]);
+            ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).abs() as f64 * 0.5
+        }).sum::<f64>() as f32 // Long strokes have too many triangles to add them up in f32
+    }
+
+    #[test]
+    fn fills() {
+        let circle = ShapeMesh::fan(vec2::ZERO, &ellipse_points(vec2::ZERO, vec2::ONE, 256), true);
+        assert!((area(&circle) - std::f32::consts::PI).abs() < 1e-3);
+
+        let rect = Rect { start: vec2::ZERO, end: vec2(4.0, 2.0) };
+        let rounded = ShapeMesh::fan(vec2(2.0, 1.0), &rounded_rect_points(rect, 0.5, 64), true);
+        let expected = 8.0 - (4.0 - std::f32::consts::PI) * 0.25;
+        assert!((area(&rounded) - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn strokes() {
+        let line = stroke(&[vec2::ZERO, vec2(2.0, 0.0)], false, StrokeStyle::new(1.0), 16);
+        assert!((area(&line) - 2.0).abs() < 1e-5);
+
+        let square = stroke(&[vec2::ZERO, vec2(2.0, 0.0)], false, StrokeStyle::new(1.0).with_cap(LineCap::Square), 16);
+        assert!((area(&square) - 3.0).abs() < 1e-5);
+
+        // Miter on a right angle adds a 0.5x0.5 corner on top of the bevel triangle
+        let corner = [vec2::ZERO, vec2(2.0, 0.0), vec2(2.0, 2.0)];
+        let bevel = stroke(&corner, false, StrokeStyle::new(1.0).with_join(LineJoin::Bevel), 16);
+        let miter = stroke(&corner, false, StrokeStyle::new(1.0), 16);
+        assert!((area(&miter) - area(&bevel) - 0.125).abs() < 1e-4);
+
+        // Segments and joins don't overlap, so the triangles add up to the covered area
+        assert!((area(&miter) - 4.0).abs() < 1e-4);
+        let round = stroke(&corner, false, StrokeStyle::new(1.0).with_join(LineJoin::Round), 256);
+        assert!((area(&round) - (3.75 + std::f32::consts::PI / 16.0)).abs() < 1e-3);
+
+        let rect = [vec2::ZERO, vec2(2.0, 0.0), vec2(2.0, 2.0), vec2(0.0, 2.0)];
+        let outline = stroke(&rect, true, StrokeStyle::new(1.0), 16);
+        assert!((area(&outline) - 8.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn long_strokes_are_split() {
+        let points = (0..20000).map(|i| vec2(i as f32, (i % 2) as f32)).collect::<Vec<_>>();
+        let line = stroke(&points, false, StrokeStyle::new(0.5), 16);
+        assert!(line.verts.len() > u16::MAX as usize);
+
+        let parts = line.parts();
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|(verts, _)| verts.len() <= u16::MAX as usize + 1));
+
+        let split_area: f32 = parts.iter().map(|(verts, indices)| area(&ShapeMesh {
+            verts: verts.clone(),
+            indices: indices.iter().map(|x| *x as u32).collect(),
+        })).sum();
+        assert_eq!(parts.iter().map(|(_, indices)| indices.len()).sum::<usize>(), line.indices.len());
+        assert!((split_area / area(&line) - 1.0).abs() < 1e-4);
+    }
+}
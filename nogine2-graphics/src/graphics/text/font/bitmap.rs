@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use nogine2_core::{crash, math::{rect::IRect, vector2::{ivec2, uvec2}}};
 
 use crate::graphics::{text::rich::RichTextFunction, texture::sprite::{Sprite, SpriteAtlas}};
+#[cfg(feature = "image-loading")]
+use crate::graphics::texture::{asynctex::AsyncTexture2D, Texture2D, TextureSampling};
 
 use super::{Font, FontCfg, TextStyle};
 
@@ -24,6 +26,16 @@ impl BitmapFont {
         return res;
     }
 
+    /// Starts loading a BitmapFont in the background. The atlas texture is decoded in a worker thread and the font is built once it has been uploaded.
+    #[cfg(feature = "image-loading")]
+    pub fn load_async(path: impl AsRef<std::path::Path>, sampling: TextureSampling, cell_size: uvec2, charset: impl Into<String>, cfg: FontCfg) -> AsyncBitmapFont {
+        AsyncBitmapFont {
+            tex: Texture2D::load_async(path, sampling),
+            pending: Some((cell_size, charset.into(), cfg)),
+            font: None,
+        }
+    }
+
     /// Adds a new style.
     /// For correct usage, each `char` in `charset` must match to a cell in `atlas` in row-major
     /// order.
@@ -132,6 +144,38 @@ impl Font for BitmapFont {
     }
 }
 
+/// A `BitmapFont` being loaded in the background. **Must only be used on the main thread!**
+#[cfg(feature = "image-loading")]
+pub struct AsyncBitmapFont {
+    tex: AsyncTexture2D,
+    pending: Option<(uvec2, String, FontCfg)>,
+    font: Option<BitmapFont>,
+}
+
+#[cfg(feature = "image-loading")]
+impl AsyncBitmapFont {
+    /// Returns if the font is ready to be used.
+    pub fn is_ready(&self) -> bool {
+        self.font.is_some() || self.tex.is_ready()
+    }
+
+    /// Returns if the atlas texture couldn't be loaded.
+    pub fn is_failed(&self) -> bool {
+        self.tex.is_failed()
+    }
+
+    /// Returns the font if it is ready. Text drawn before that can be skipped, as there is no placeholder font.
+    pub fn poll(&mut self) -> Option<&mut BitmapFont> {
+        if self.font.is_none() {
+            if let Some(texture) = self.tex.take() {
+                let Some((cell_size, charset, cfg)) = self.pending.take() else { unreachable!() };
+                self.font = Some(BitmapFont::new(SpriteAtlas::new(texture, cell_size), &charset, cfg));
+            }
+        }
+        return self.font.as_mut();
+    }
+}
+
 struct StyledAtlasData {
     atlas: SpriteAtlas,
     rects: HashMap<char, IRect>,
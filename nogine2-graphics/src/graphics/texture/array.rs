@@ -0,0 +1,79 @@
+use std::{ffi::c_void, sync::Arc};
+
+use nogine2_core::{assert_expr, math::vector2::uvec2};
+
+use crate::gl_wrapper::texture::GlTexture;
+
+use super::{pixels::{PixelFormat, Pixels}, TextureHandle, TextureSampling};
+
+/// A stack of same-sized textures, such as tilesets or font pages. All of its layers can be drawn within the same batch. **Must only be used on the main thread!**
+#[derive(Debug, Clone)]
+pub struct TextureArray {
+    gl_obj: Arc<GlTexture>,
+    sampling: TextureSampling,
+    dims: uvec2,
+    layers: u32,
+    format: PixelFormat,
+}
+
+impl TextureArray {
+    /// Layers are packed in 16 bits of the batch vertex texture IDs.
+    const MAX_LAYERS: usize = 1 << 16;
+
+    /// Creates a new texture array, one layer per element of `layers`. Will panic if `layers` is empty, has more than 65536 elements or if the layers don't share the same dimensions and format.
+    pub fn new(layers: &[Pixels], sampling: TextureSampling) -> Self {
+        assert_expr!(!layers.is_empty(), "A 'TextureArray' must have at least one layer!");
+        assert_expr!(layers.len() <= Self::MAX_LAYERS, "A 'TextureArray' can't have more than 65536 layers!");
+
+        let dims = layers[0].dims();
+        let format = layers[0].format();
+        assert_expr!(layers.iter().all(|l| l.dims() == dims && l.format() == format), "All the layers of a 'TextureArray' must share dimensions and format!");
+
+        let data = layers.iter().flat_map(|l| l.data().iter().copied()).collect::<Vec<_>>();
+        let gl_obj = Arc::new(GlTexture::new_array(
+            format.into(), dims, layers.len() as u32,
+            sampling.filtering.into(), sampling.wrapping.into(),
+            data.as_ptr() as *const c_void
+        ));
+
+        return Self { gl_obj, sampling, dims, layers: layers.len() as u32, format };
+    }
+
+    /// Returns a handle to a single layer, usable anywhere a texture is. Will panic if `layer` is out of bounds.
+    pub fn handle(&self, layer: u32) -> TextureHandle {
+        assert_expr!(layer < self.layers, "Layer out of bounds!");
+        return TextureHandle { gl_obj: self.gl_obj.clone(), layer };
+    }
+
+    /// Replaces the contents of a layer. Will panic if `layer` is out of bounds or if `pixels` doesn't match the dimensions and format of the array.
+    pub fn set_layer(&self, layer: u32, pixels: &Pixels) {
+        assert_expr!(layer < self.layers, "Layer out of bounds!");
+        assert_expr!(pixels.dims() == self.dims && pixels.format() == self.format, "Pixels must match the dimensions and format of the 'TextureArray'!");
+        self.gl_obj.set_layer(layer, self.format.into(), pixels.data().as_ptr() as *const c_void);
+    }
+
+    /// Returns the resolution of every layer.
+    pub fn dims(&self) -> uvec2 {
+        self.dims
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layers
+    }
+
+    pub fn sampling(&self) -> &TextureSampling {
+        &self.sampling
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.format
+    }
+}
+
+impl PartialEq for TextureArray {
+    fn eq(&self, other: &Self) -> bool {
+        self.gl_obj == other.gl_obj
+    }
+}
+
+impl Eq for TextureArray {}
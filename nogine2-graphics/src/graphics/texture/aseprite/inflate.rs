@@ -0,0 +1,347 @@
+//! Minimal zlib (RFC 1950) and DEFLATE (RFC 1951) decoder, enough for the compressed cels of Aseprite files.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    UnexpectedEof,
+    InvalidHeader,
+    InvalidBlockType,
+    InvalidStoredLength,
+    InvalidHuffmanTable,
+    InvalidSymbol,
+    InvalidDistance,
+    OutputTooLarge,
+    ChecksumMismatch,
+}
+
+impl std::fmt::Display for InflateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InflateError::UnexpectedEof => write!(f, "Unexpected end of compressed data"),
+            InflateError::InvalidHeader => write!(f, "Invalid zlib header"),
+            InflateError::InvalidBlockType => write!(f, "Invalid deflate block type"),
+            InflateError::InvalidStoredLength => write!(f, "Invalid stored block length"),
+            InflateError::InvalidHuffmanTable => write!(f, "Invalid Huffman table"),
+            InflateError::InvalidSymbol => write!(f, "Invalid Huffman symbol"),
+            InflateError::InvalidDistance => write!(f, "Back reference out of bounds"),
+            InflateError::OutputTooLarge => write!(f, "Decompressed data is larger than expected"),
+            InflateError::ChecksumMismatch => write!(f, "Adler-32 checksum mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for InflateError { }
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Maximum expansion of DEFLATE data, reached by long runs of back references.
+const MAX_RATIO: usize = 1032;
+/// Order in which the code length code lengths are stored.
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses a zlib stream. `max_size` is the expected size of the output, going over it is an error.
+pub fn zlib_decompress(data: &[u8], max_size: usize) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 2 {
+        return Err(InflateError::UnexpectedEof);
+    }
+
+    let (cmf, flg) = (data[0], data[1]);
+    let has_dict = flg & 0x20 != 0;
+    if cmf & 0x0F != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 || has_dict {
+        return Err(InflateError::InvalidHeader);
+    }
+
+    let mut reader = BitReader { data, pos: 2, bit_buf: 0, bit_count: 0 };
+    // DEFLATE can't expand data more than ~1032:1, so the expected size is only trusted up to that
+    let mut out = Vec::with_capacity(max_size.min(data.len().saturating_mul(MAX_RATIO)));
+    inflate(&mut reader, &mut out, max_size)?;
+
+    // The checksum is optional for our purposes, but a present and wrong one means corrupted data
+    reader.align();
+    if let Some(checksum) = data.get(reader.pos..(reader.pos + 4)) {
+        if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&out) {
+            return Err(InflateError::ChecksumMismatch);
+        }
+    }
+    return Ok(out);
+}
+
+/// Decompresses raw DEFLATE data, appending it to `out` up to `limit` bytes.
+fn inflate(reader: &mut BitReader, out: &mut Vec<u8>, limit: usize) -> Result<(), InflateError> {
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(reader, out, limit)?,
+            1 => {
+                let (lit, dist) = fixed_tables();
+                huffman_block(reader, out, limit, &lit, &dist)?;
+            },
+            2 => {
+                let (lit, dist) = dynamic_tables(reader)?;
+                huffman_block(reader, out, limit, &lit, &dist)?;
+            },
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, out: &mut Vec<u8>, limit: usize) -> Result<(), InflateError> {
+    reader.align();
+    let header = reader.data.get(reader.pos..(reader.pos + 4)).ok_or(InflateError::UnexpectedEof)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err(InflateError::InvalidStoredLength);
+    }
+
+    let start = reader.pos + 4;
+    let bytes = reader.data.get(start..(start + len as usize)).ok_or(InflateError::UnexpectedEof)?;
+    if out.len() + bytes.len() > limit {
+        return Err(InflateError::OutputTooLarge);
+    }
+    out.extend_from_slice(bytes);
+    reader.pos = start + len as usize;
+    return Ok(());
+}
+
+fn huffman_block(reader: &mut BitReader, out: &mut Vec<u8>, limit: usize, lit: &Huffman, dist: &Huffman) -> Result<(), InflateError> {
+    loop {
+        let symbol = lit.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                if out.len() >= limit {
+                    return Err(InflateError::OutputTooLarge);
+                }
+                out.push(symbol as u8);
+            },
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let len = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let dist_symbol = dist.decode(reader)? as usize;
+                if dist_symbol >= DIST_BASE.len() {
+                    return Err(InflateError::InvalidSymbol);
+                }
+                let distance = DIST_BASE[dist_symbol] as usize + reader.bits(DIST_EXTRA[dist_symbol] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(InflateError::InvalidDistance);
+                }
+                if out.len() + len > limit {
+                    return Err(InflateError::OutputTooLarge);
+                }
+
+                // Byte by byte, as the source may overlap with the copied bytes
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            },
+            _ => return Err(InflateError::InvalidSymbol),
+        }
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[0..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..288].fill(8);
+
+    // Fixed tables are always valid
+    let lit = Huffman::new(&lengths).unwrap_or_default();
+    let dist = Huffman::new(&[5; 30]).unwrap_or_default();
+    return (lit, dist);
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), InflateError> {
+    let hlit = reader.bits(5)? as usize + 257;
+    let hdist = reader.bits(5)? as usize + 1;
+    let hclen = reader.bits(4)? as usize + 4;
+
+    let mut clen_lengths = [0u8; 19];
+    for i in 0..hclen {
+        clen_lengths[CLEN_ORDER[i]] = reader.bits(3)? as u8;
+    }
+    let clen = Huffman::new(&clen_lengths)?;
+
+    let mut lengths = vec![0u8; hlit + hdist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = clen.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths[..i].last().ok_or(InflateError::InvalidHuffmanTable)?;
+                (prev, 3 + reader.bits(2)? as usize)
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            18 => (0, 11 + reader.bits(7)? as usize),
+            _ => return Err(InflateError::InvalidSymbol),
+        };
+
+        if i + repeat > lengths.len() {
+            return Err(InflateError::InvalidHuffmanTable);
+        }
+        lengths[i..(i + repeat)].fill(value);
+        i += repeat;
+    }
+
+    let lit = Huffman::new(&lengths[..hlit])?;
+    let dist = Huffman::new(&lengths[hlit..])?;
+    return Ok((lit, dist));
+}
+
+
+/// Reads bits from least to most significant, as DEFLATE stores them.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, InflateError> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.pos).ok_or(InflateError::UnexpectedEof)?;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+            self.pos += 1;
+        }
+
+        let value = self.bit_buf & ((1u64 << count) - 1) as u32;
+        self.bit_buf >>= count;
+        self.bit_count -= count;
+        return Ok(value);
+    }
+
+    /// Discards the bits left in the current byte.
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+}
+
+
+/// Canonical Huffman decoding table.
+#[derive(Debug, Default)]
+struct Huffman {
+    /// Amount of codes of every length.
+    counts: [u16; 16],
+    /// Symbols sorted by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let mut counts = [0u16; 16];
+        for len in lengths {
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Over-subscribed sets of lengths can't be decoded
+        let mut left = 1i32;
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(InflateError::InvalidHuffmanTable);
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        return Ok(Self { counts, symbols });
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, InflateError> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        return Err(InflateError::InvalidSymbol);
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    return (b << 16) | a;
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{zlib_decompress, InflateError};
+
+    #[test]
+    fn stored_and_fixed() {
+        // zlib.compress(b"hello", 0)
+        let stored = [0x78, 0x01, 0x01, 0x05, 0x00, 0xFA, 0xFF, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x06, 0x2C, 0x02, 0x15];
+        assert_eq!(zlib_decompress(&stored, 5).unwrap(), b"hello");
+
+        // zlib.compress(b"abcabcabcabcabc")
+        let fixed = [0x78, 0x9C, 0x4B, 0x4C, 0x4A, 0x4E, 0x44, 0x42, 0x00, 0x2D, 0xF5, 0x05, 0xBF];
+        assert_eq!(zlib_decompress(&fixed, 15).unwrap(), b"abcabcabcabcabc");
+
+        let mut corrupted = fixed;
+        corrupted[12] ^= 1;
+        assert_eq!(zlib_decompress(&corrupted, 15), Err(InflateError::ChecksumMismatch));
+        assert_eq!(zlib_decompress(&[0x78, 0x9C], 0), Err(InflateError::UnexpectedEof));
+
+        // Output over the expected size
+        assert_eq!(zlib_decompress(&stored, 4), Err(InflateError::OutputTooLarge));
+        assert_eq!(zlib_decompress(&fixed, 14), Err(InflateError::OutputTooLarge));
+    }
+
+    #[test]
+    fn dynamic() {
+        // zlib.compress(b"".join(bytes([(i * 7) % 13, (i * i) % 5, 255, 0]) for i in range(64)) + b"nogine" * 20, 9)
+        let expected = (0..64u32).flat_map(|i| [((i * 7) % 13) as u8, ((i * i) % 5) as u8, 255, 0]).chain(b"nogine".repeat(20)).collect::<Vec<_>>();
+        assert_eq!(zlib_decompress(DYNAMIC, expected.len()).unwrap(), expected);
+    }
+
+    const DYNAMIC: &[u8] = &[
+        0x78, 0xDA, 0xBD, 0x8F, 0x41, 0x0E, 0x80, 0x20, 0x0C, 0x04, 0x5B, 0x5D, 0x54, 0xD4, 0x6F, 0x1A, 0xE3, 0x45, 0xFF, 0x7F,
+        0xC2, 0x21, 0xEE, 0x1B, 0x3C, 0xC0, 0xD2, 0x49, 0x26, 0x5B, 0x22, 0x5A, 0xCC, 0xD9, 0x22, 0xD5, 0x62, 0xE1, 0x0C, 0xBC,
+        0x2B, 0x6C, 0x24, 0x57, 0x66, 0x71, 0x36, 0xDE, 0x05, 0xB6, 0x93, 0x13, 0x73, 0xC8, 0x0E, 0x6C, 0x21, 0x07, 0xE6, 0x2A,
+        0x3B, 0x30, 0x91, 0x1B, 0x73, 0x91, 0x1D, 0x58, 0x90, 0xB3, 0xDC, 0xD3, 0x1D, 0x58, 0x25, 0x47, 0xB9, 0xA7, 0x3B, 0xB0,
+        0x42, 0xEE, 0x72, 0x4F, 0x77, 0x60, 0x99, 0xDE, 0x4D, 0x76, 0x60, 0x6B, 0x7A, 0x37, 0xD9, 0x81, 0x4D, 0xE9, 0xDD, 0x64,
+        0x27, 0xFC, 0x1F, 0xB9, 0xA7, 0x3B, 0xE1, 0xFF, 0xE8, 0xEB, 0xB9, 0x9F, 0xF3, 0xBA, 0x8F, 0xFF, 0xEF, 0x17, 0x86, 0x80,
+        0x73, 0xC2,
+    ];
+}
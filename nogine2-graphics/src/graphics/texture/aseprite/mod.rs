@@ -0,0 +1,884 @@
+use inflate::{zlib_decompress, InflateError};
+use nogine2_core::{assert_expr, math::{rect::{IRect, Rect, URect}, vector2::{ivec2, uvec2, vec2}}};
+
+use crate::{colors::rgba::RGBA8, graphics::animation::{AnimationClip, PlaybackMode}};
+
+use super::{pixels::{PixelFormat, Pixels}, sprite::{Sprite, SpriteAtlas}, Texture2D, TextureSampling};
+
+mod inflate;
+
+#[derive(Debug)]
+pub enum AsepriteError {
+    IOError(std::io::Error),
+    InvalidMagic,
+    UnexpectedEof,
+    UnsupportedColorDepth(u16),
+    InvalidPalette,
+    CompressionError(InflateError),
+}
+
+impl std::fmt::Display for AsepriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsepriteError::IOError(error) => write!(f, "{error}"),
+            AsepriteError::InvalidMagic => write!(f, "Not an Aseprite file"),
+            AsepriteError::UnexpectedEof => write!(f, "Unexpected end of file"),
+            AsepriteError::UnsupportedColorDepth(depth) => write!(f, "Unsupported color depth ({depth} bpp)"),
+            AsepriteError::InvalidPalette => write!(f, "Invalid palette"),
+            AsepriteError::CompressionError(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for AsepriteError { }
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AseLayerKind {
+    Normal,
+    Group,
+    /// Tilemap layers are parsed, but their cels are ignored.
+    Tilemap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AseLayer {
+    pub name: String,
+    pub kind: AseLayerKind,
+    pub visible: bool,
+    /// Reference layers are never composited.
+    pub reference: bool,
+    /// Depth in the layer tree, `0` for top level layers.
+    pub child_level: u16,
+    /// Aseprite blend mode. Every mode is composited as normal blending.
+    pub blend_mode: u16,
+    pub opacity: u8,
+}
+
+/// Image of a layer in a frame.
+#[derive(Debug, Clone)]
+pub struct AseCel {
+    pub layer: usize,
+    /// Position of the top left corner in the canvas.
+    pub pos: ivec2,
+    pub opacity: u8,
+    pub z_index: i16,
+    /// Always in `PixelFormat::RGBA8`.
+    pub pixels: Pixels,
+}
+
+#[derive(Debug, Clone)]
+pub struct AseFrame {
+    /// Duration in seconds.
+    pub duration: f32,
+    pub cels: Vec<AseCel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AseTagDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+/// Named range of frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AseTag {
+    pub name: String,
+    pub from: usize,
+    /// Inclusive.
+    pub to: usize,
+    pub direction: AseTagDirection,
+    /// Times the tag is played, `0` for infinite.
+    pub repeat: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AseSlice {
+    pub name: String,
+    pub keys: Vec<AseSliceKey>,
+}
+
+impl AseSlice {
+    /// Returns the key in effect at `frame`.
+    pub fn key_at(&self, frame: usize) -> Option<&AseSliceKey> {
+        self.keys.iter().rev().find(|k| k.frame <= frame)
+    }
+}
+
+/// State of a slice from `frame` onwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AseSliceKey {
+    pub frame: usize,
+    /// Bounds in canvas pixels.
+    pub bounds: IRect,
+    /// 9-slice center, relative to `bounds`.
+    pub center: Option<IRect>,
+    /// Pivot, relative to `bounds`.
+    pub pivot: Option<ivec2>,
+}
+
+
+/// Contents of an `.ase`/`.aseprite` file, parsed without the Aseprite CLI. Layers are flattened with normal blending.
+#[derive(Debug, Clone)]
+pub struct AsepriteFile {
+    dims: uvec2,
+    layers: Vec<AseLayer>,
+    /// Visibility including the one of parent groups.
+    layers_shown: Vec<bool>,
+    frames: Vec<AseFrame>,
+    tags: Vec<AseTag>,
+    slices: Vec<AseSlice>,
+    palette: Vec<RGBA8>,
+}
+
+impl AsepriteFile {
+    const FILE_MAGIC: u16 = 0xA5E0;
+    const FRAME_MAGIC: u16 = 0xF1FA;
+    const HEADER_SIZE: usize = 128;
+
+    const LAYER_OPACITY_VALID: u32 = 1;
+
+    const CHUNK_OLD_PALETTE: u16 = 0x0004;
+    const CHUNK_OLD_PALETTE_64: u16 = 0x0011;
+    const CHUNK_LAYER: u16 = 0x2004;
+    const CHUNK_CEL: u16 = 0x2005;
+    const CHUNK_TAGS: u16 = 0x2018;
+    const CHUNK_PALETTE: u16 = 0x2019;
+    const CHUNK_SLICE: u16 = 0x2022;
+
+    /// Loads and parses a file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, AsepriteError> {
+        let bytes = std::fs::read(path).map_err(AsepriteError::IOError)?;
+        return Self::parse(&bytes);
+    }
+
+    /// Parses the contents of a file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AsepriteError> {
+        let mut r = Reader { data: bytes, pos: 0 };
+        r.skip(4)?; // File size
+        if r.u16()? != Self::FILE_MAGIC {
+            return Err(AsepriteError::InvalidMagic);
+        }
+
+        let frame_count = r.u16()? as usize;
+        let dims = uvec2(r.u16()? as u32, r.u16()? as u32);
+        let depth = match r.u16()? {
+            32 => ColorDepth::Rgba,
+            16 => ColorDepth::Grayscale,
+            8 => ColorDepth::Indexed,
+            other => return Err(AsepriteError::UnsupportedColorDepth(other)),
+        };
+        let flags = r.u32()?;
+        r.skip(10)?; // Speed and reserved
+        let transparent_index = r.u8()?;
+        r.seek(Self::HEADER_SIZE)?;
+
+        let mut file = Self { dims, layers: Vec::new(), layers_shown: Vec::new(), frames: Vec::with_capacity(frame_count), tags: Vec::new(), slices: Vec::new(), palette: Vec::new() };
+        let mut raw_cels = Vec::new();
+        let mut has_new_palette = false;
+
+        for frame in 0..frame_count {
+            let frame_start = r.pos;
+            let frame_size = r.u32()? as usize;
+            if r.u16()? != Self::FRAME_MAGIC {
+                return Err(AsepriteError::InvalidMagic);
+            }
+            let old_chunks = r.u16()? as usize;
+            let duration = r.u16()? as f32 / 1000.0;
+            r.skip(2)?;
+            let chunks = match r.u32()? as usize {
+                0 => old_chunks,
+                new_chunks => new_chunks,
+            };
+
+            file.frames.push(AseFrame { duration: duration.max(0.001), cels: Vec::new() });
+            raw_cels.push(Vec::new());
+
+            for _ in 0..chunks {
+                let chunk_start = r.pos;
+                let chunk_size = r.u32()? as usize;
+                let chunk_type = r.u16()?;
+                let data = bytes.get((chunk_start + 6)..(chunk_start + chunk_size)).ok_or(AsepriteError::UnexpectedEof)?;
+                let mut c = Reader { data, pos: 0 };
+
+                match chunk_type {
+                    Self::CHUNK_LAYER => {
+                        let layer = parse_layer(&mut c, flags & Self::LAYER_OPACITY_VALID != 0)?;
+                        file.layers.push(layer);
+                    },
+                    Self::CHUNK_CEL => {
+                        if let Some(cel) = parse_cel(&mut c, depth, frame, &raw_cels)? {
+                            raw_cels[frame].push(cel);
+                        }
+                    },
+                    Self::CHUNK_TAGS => file.tags = parse_tags(&mut c)?,
+                    Self::CHUNK_PALETTE => {
+                        parse_palette(&mut c, &mut file.palette)?;
+                        has_new_palette = true;
+                    },
+                    Self::CHUNK_OLD_PALETTE | Self::CHUNK_OLD_PALETTE_64 if !has_new_palette => {
+                        parse_old_palette(&mut c, &mut file.palette, chunk_type == Self::CHUNK_OLD_PALETTE_64)?;
+                    },
+                    Self::CHUNK_SLICE => file.slices.push(parse_slice(&mut c)?),
+                    _ => { }, // Color profiles, user data, tilesets and extras
+                }
+
+                r.seek(chunk_start + chunk_size)?;
+            }
+
+            r.seek(frame_start + frame_size)?;
+        }
+
+        for (frame, cels) in file.frames.iter_mut().zip(raw_cels) {
+            frame.cels = cels.into_iter().map(|c| c.to_rgba(depth, &file.palette, transparent_index)).collect();
+        }
+
+        file.layers_shown = layers_shown(&file.layers);
+        return Ok(file);
+    }
+
+    /// Canvas size, in pixels.
+    pub fn dims(&self) -> uvec2 {
+        self.dims
+    }
+
+    pub fn layers(&self) -> &[AseLayer] {
+        &self.layers
+    }
+
+    pub fn frames(&self) -> &[AseFrame] {
+        &self.frames
+    }
+
+    pub fn tags(&self) -> &[AseTag] {
+        &self.tags
+    }
+
+    pub fn slices(&self) -> &[AseSlice] {
+        &self.slices
+    }
+
+    pub fn palette(&self) -> &[RGBA8] {
+        &self.palette
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&AseTag> {
+        self.tags.iter().find(|t| t.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&AseSlice> {
+        self.slices.iter().find(|s| s.name == name)
+    }
+
+    /// Composites the visible layers of a frame. Will panic if `frame` is out of bounds.
+    pub fn frame_pixels(&self, frame: usize) -> Pixels {
+        assert_expr!(frame < self.frames.len(), "Frame out of bounds (frame was {frame}, len was {})!", self.frames.len());
+
+        let mut canvas = Pixels::new(vec![0; (self.dims.0 * self.dims.1) as usize * 4], self.dims, PixelFormat::RGBA8);
+        self.composite(frame, &mut canvas, uvec2(0, 0));
+        return canvas;
+    }
+
+    /// Composites every frame into a grid, with a cell per frame.
+    pub fn sheet_pixels(&self) -> Pixels {
+        let cols = self.sheet_columns();
+        let rows = (self.frames.len() as u32).div_ceil(cols).max(1);
+        let dims = uvec2(self.dims.0 * cols, self.dims.1 * rows);
+
+        let mut sheet = Pixels::new(vec![0; (dims.0 * dims.1) as usize * 4], dims, PixelFormat::RGBA8);
+        for frame in 0..self.frames.len() {
+            let cell = self.frame_cell(frame);
+            self.composite(frame, &mut sheet, uvec2(cell.0 as u32 * self.dims.0, cell.1 as u32 * self.dims.1));
+        }
+        return sheet;
+    }
+
+    /// Uploads `sheet_pixels` as an atlas with a cell per frame. **Must only be used on the main thread!**
+    pub fn sprite_atlas(&self, sampling: TextureSampling) -> SpriteAtlas {
+        return SpriteAtlas::new(Texture2D::new(self.sheet_pixels(), sampling), self.dims);
+    }
+
+    /// Returns the cell of a frame in `sheet_pixels`.
+    pub fn frame_cell(&self, frame: usize) -> ivec2 {
+        let cols = self.sheet_columns() as usize;
+        return ivec2((frame % cols) as i32, (frame / cols) as i32);
+    }
+
+    /// Returns the sprite of every frame in an atlas made with `sprite_atlas`.
+    pub fn sprites(&self, atlas: &SpriteAtlas) -> Vec<Sprite> {
+        (0..self.frames.len()).map(|f| atlas.get(self.frame_cell(f))).collect()
+    }
+
+    /// Returns the sprite of a slice at `frame` in an atlas made with `sprite_atlas`, along with its 9-slice center for `Graphics::draw_9_slice`.
+    pub fn slice_sprite(&self, atlas: &SpriteAtlas, name: &str, frame: usize) -> Option<(Sprite, Option<URect>)> {
+        let key = self.slice(name)?.key_at(frame)?;
+        let cell = self.frame_cell(frame);
+        let cell_start = ivec2(cell.0 * self.dims.0 as i32, cell.1 * self.dims.1 as i32);
+
+        let tex_dims = vec2::from(atlas.tex().dims());
+        let uv_rect = Rect {
+            start: vec2::from(cell_start + key.bounds.start).inv_scale(tex_dims),
+            end: vec2::from(cell_start + key.bounds.end).inv_scale(tex_dims),
+        };
+        let dims = uvec2::from(key.bounds.size());
+        let center = key.center.map(|c| URect { start: uvec2::from(c.start), end: uvec2::from(c.end) });
+        return Some((Sprite::new(atlas.tex().handle(), uv_rect, dims), center));
+    }
+
+    /// Creates a clip from a tag, where `frames` has a value for each frame of the file, like the ones returned by `sprites`. Tags played once become `PlaybackMode::Once` clips. Will panic if `frames` doesn't have a value per frame.
+    pub fn clip<T: Clone>(&self, tag: &str, frames: &[T]) -> Option<AnimationClip<T>> {
+        assert_expr!(frames.len() == self.frames.len(), "There must be a value per frame!");
+
+        let tag = self.tag(tag)?;
+        let mut indices = (tag.from..=tag.to.min(self.frames.len() - 1)).collect::<Vec<_>>();
+        if indices.is_empty() {
+            return None;
+        }
+        if matches!(tag.direction, AseTagDirection::Reverse | AseTagDirection::PingPongReverse) {
+            indices.reverse();
+        }
+
+        let mode = match tag.direction {
+            AseTagDirection::PingPong | AseTagDirection::PingPongReverse => PlaybackMode::PingPong,
+            _ if tag.repeat == 1 => PlaybackMode::Once,
+            _ => PlaybackMode::Loop,
+        };
+        let clip = AnimationClip::new(indices.iter().map(|i| frames[*i].clone()).collect(), 1.0, mode)
+            .with_durations(indices.iter().map(|i| self.frames[*i].duration).collect());
+        return Some(clip);
+    }
+
+    /// Creates a looping clip with every frame. Will panic if `frames` doesn't have a value per frame.
+    pub fn full_clip<T: Clone>(&self, frames: &[T]) -> AnimationClip<T> {
+        assert_expr!(frames.len() == self.frames.len(), "There must be a value per frame!");
+        return AnimationClip::new(frames.to_vec(), 1.0, PlaybackMode::Loop).with_durations(self.frames.iter().map(|f| f.duration).collect());
+    }
+
+    fn sheet_columns(&self) -> u32 {
+        (self.frames.len() as f32).sqrt().ceil().max(1.0) as u32
+    }
+
+    /// Blends the visible cels of a frame over `target`, with the canvas origin at `offset`.
+    fn composite(&self, frame: usize, target: &mut Pixels, offset: uvec2) {
+        let mut cels = self.frames[frame].cels.iter()
+            .filter(|c| self.layers_shown.get(c.layer).copied().unwrap_or(false))
+            .collect::<Vec<_>>();
+        cels.sort_by_key(|c| (c.layer as i32 + c.z_index as i32, c.z_index));
+
+        for cel in cels {
+            let opacity = cel.opacity as u32 * self.layers[cel.layer].opacity as u32 / 255;
+            blend_cel(target, cel, opacity as u8, offset, self.dims);
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorDepth {
+    Rgba,
+    Grayscale,
+    Indexed,
+}
+
+impl ColorDepth {
+    fn byte_size(&self) -> usize {
+        match self {
+            ColorDepth::Rgba => 4,
+            ColorDepth::Grayscale => 2,
+            ColorDepth::Indexed => 1,
+        }
+    }
+}
+
+/// Cel in the color depth of the file, converted once the palette is known.
+#[derive(Debug, Clone)]
+struct RawCel {
+    layer: usize,
+    pos: ivec2,
+    opacity: u8,
+    z_index: i16,
+    dims: uvec2,
+    data: Vec<u8>,
+}
+
+impl RawCel {
+    fn to_rgba(&self, depth: ColorDepth, palette: &[RGBA8], transparent_index: u8) -> AseCel {
+        let data = match depth {
+            ColorDepth::Rgba => self.data.clone(),
+            ColorDepth::Grayscale => self.data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            ColorDepth::Indexed => self.data.iter().flat_map(|i| {
+                if *i == transparent_index {
+                    return [0; 4];
+                }
+                let c = palette.get(*i as usize).copied().unwrap_or(RGBA8(0, 0, 0, 0));
+                return [c.0, c.1, c.2, c.3];
+            }).collect(),
+        };
+        return AseCel { layer: self.layer, pos: self.pos, opacity: self.opacity, z_index: self.z_index, pixels: Pixels::new(data, self.dims, PixelFormat::RGBA8) };
+    }
+}
+
+fn parse_layer(c: &mut Reader, opacity_valid: bool) -> Result<AseLayer, AsepriteError> {
+    const VISIBLE: u16 = 1;
+    const REFERENCE: u16 = 64;
+
+    let flags = c.u16()?;
+    let kind = match c.u16()? {
+        1 => AseLayerKind::Group,
+        2 => AseLayerKind::Tilemap,
+        _ => AseLayerKind::Normal,
+    };
+    let child_level = c.u16()?;
+    c.skip(4)?; // Default size, ignored by Aseprite
+    let blend_mode = c.u16()?;
+    let opacity = c.u8()?;
+    c.skip(3)?;
+    let name = c.string()?;
+
+    return Ok(AseLayer {
+        name, kind, child_level, blend_mode,
+        visible: flags & VISIBLE != 0,
+        reference: flags & REFERENCE != 0,
+        opacity: if opacity_valid { opacity } else { 255 },
+    });
+}
+
+fn parse_cel(c: &mut Reader, depth: ColorDepth, frame: usize, prev_frames: &[Vec<RawCel>]) -> Result<Option<RawCel>, AsepriteError> {
+    let layer = c.u16()? as usize;
+    let pos = ivec2(c.i16()? as i32, c.i16()? as i32);
+    let opacity = c.u8()?;
+    let cel_type = c.u16()?;
+    let z_index = c.i16()?;
+    c.skip(5)?;
+
+    match cel_type {
+        // Raw and compressed images
+        0 | 2 => {
+            let dims = uvec2(c.u16()? as u32, c.u16()? as u32);
+            let size = (dims.0 * dims.1) as usize * depth.byte_size();
+            let data = if cel_type == 0 {
+                c.bytes(size)?.to_vec()
+            } else {
+                zlib_decompress(c.rest(), size).map_err(AsepriteError::CompressionError)?
+            };
+            if data.len() < size {
+                return Err(AsepriteError::UnexpectedEof);
+            }
+            return Ok(Some(RawCel { layer, pos, opacity, z_index, dims, data: data[..size].to_vec() }));
+        },
+        // Linked to the cel of the same layer in another frame
+        1 => {
+            let linked = c.u16()? as usize;
+            let source = prev_frames.get(linked).filter(|_| linked < frame).and_then(|f| f.iter().find(|x| x.layer == layer));
+            return Ok(source.map(|s| RawCel { layer, pos, opacity, z_index, dims: s.dims, data: s.data.clone() }));
+        },
+        _ => return Ok(None), // Tilemaps
+    }
+}
+
+fn parse_tags(c: &mut Reader) -> Result<Vec<AseTag>, AsepriteError> {
+    let count = c.u16()? as usize;
+    c.skip(8)?;
+
+    let mut tags = Vec::with_capacity(count);
+    for _ in 0..count {
+        let from = c.u16()? as usize;
+        let to = c.u16()? as usize;
+        let direction = match c.u8()? {
+            1 => AseTagDirection::Reverse,
+            2 => AseTagDirection::PingPong,
+            3 => AseTagDirection::PingPongReverse,
+            _ => AseTagDirection::Forward,
+        };
+        let repeat = c.u16()?;
+        c.skip(10)?; // Reserved and deprecated color
+        let name = c.string()?;
+        tags.push(AseTag { name, from, to, direction, repeat });
+    }
+    return Ok(tags);
+}
+
+/// Palettes can't have more colors than an indexed pixel can address.
+const MAX_PALETTE: usize = 256;
+
+fn parse_palette(c: &mut Reader, palette: &mut Vec<RGBA8>) -> Result<(), AsepriteError> {
+    const HAS_NAME: u16 = 1;
+
+    let size = c.u32()? as usize;
+    let first = c.u32()? as usize;
+    let last = c.u32()? as usize;
+    c.skip(8)?;
+
+    // Each entry takes at least 6 bytes
+    if first > last || last >= MAX_PALETTE || size > MAX_PALETTE || (last - first + 1) * 6 > c.remaining() {
+        return Err(AsepriteError::InvalidPalette);
+    }
+
+    palette.resize(size.max(last + 1), RGBA8(0, 0, 0, 0));
+    for color in &mut palette[first..=last] {
+        let flags = c.u16()?;
+        *color = RGBA8(c.u8()?, c.u8()?, c.u8()?, c.u8()?);
+        if flags & HAS_NAME != 0 {
+            c.string()?;
+        }
+    }
+    return Ok(());
+}
+
+fn parse_old_palette(c: &mut Reader, palette: &mut Vec<RGBA8>, six_bits: bool) -> Result<(), AsepriteError> {
+    let scale = |x: u8| if six_bits { (x as u32 * 255 / 63) as u8 } else { x };
+
+    let packets = c.u16()?;
+    let mut index = 0;
+    for _ in 0..packets {
+        index += c.u8()? as usize;
+        let count = match c.u8()? {
+            0 => 256,
+            count => count as usize,
+        };
+
+        if index + count > MAX_PALETTE {
+            return Err(AsepriteError::InvalidPalette);
+        }
+        if palette.len() < index + count {
+            palette.resize(index + count, RGBA8(0, 0, 0, 0));
+        }
+        for color in &mut palette[index..(index + count)] {
+            *color = RGBA8(scale(c.u8()?), scale(c.u8()?), scale(c.u8()?), 255);
+        }
+        index += count;
+    }
+    return Ok(());
+}
+
+fn parse_slice(c: &mut Reader) -> Result<AseSlice, AsepriteError> {
+    const NINE_PATCH: u32 = 1;
+    const PIVOT: u32 = 2;
+
+    let key_count = c.u32()? as usize;
+    let flags = c.u32()?;
+    c.skip(4)?;
+    let name = c.string()?;
+
+    let mut keys = Vec::with_capacity(key_count);
+    for _ in 0..key_count {
+        let frame = c.u32()? as usize;
+        let bounds = c.rect()?;
+        let center = if flags & NINE_PATCH != 0 { Some(c.rect()?) } else { None };
+        let pivot = if flags & PIVOT != 0 { Some(ivec2(c.i32()?, c.i32()?)) } else { None };
+        keys.push(AseSliceKey { frame, bounds, center, pivot });
+    }
+    return Ok(AseSlice { name, keys });
+}
+
+/// Returns if each layer is visible, taking into account its parent groups.
+fn layers_shown(layers: &[AseLayer]) -> Vec<bool> {
+    let mut parents = Vec::<bool>::new();
+    let mut shown = Vec::with_capacity(layers.len());
+    for layer in layers {
+        parents.truncate(layer.child_level as usize);
+        let visible = layer.visible && !layer.reference && parents.iter().all(|x| *x);
+        shown.push(visible && layer.kind == AseLayerKind::Normal);
+
+        if layer.kind == AseLayerKind::Group {
+            parents.push(visible);
+        }
+    }
+    return shown;
+}
+
+/// Blends a cel over `target` with straight alpha, clipping it to the canvas.
+fn blend_cel(target: &mut Pixels, cel: &AseCel, opacity: u8, offset: uvec2, canvas: uvec2) {
+    let target_width = target.dims().0 as usize;
+    let src = cel.pixels.data();
+    let dst = target.data_mut();
+    let cel_dims = cel.pixels.dims();
+
+    for y in 0..cel_dims.1 as i32 {
+        let cy = cel.pos.1 + y;
+        if cy < 0 || cy >= canvas.1 as i32 {
+            continue;
+        }
+
+        for x in 0..cel_dims.0 as i32 {
+            let cx = cel.pos.0 + x;
+            if cx < 0 || cx >= canvas.0 as i32 {
+                continue;
+            }
+
+            let s = (x + y * cel_dims.0 as i32) as usize * 4;
+            let d = ((offset.0 as usize + cx as usize) + (offset.1 as usize + cy as usize) * target_width) * 4;
+
+            let src_a = src[s + 3] as f32 * opacity as f32 / (255.0 * 255.0);
+            if src_a <= 0.0 {
+                continue;
+            }
+            let dst_a = dst[d + 3] as f32 / 255.0;
+            let out_a = src_a + dst_a * (1.0 - src_a);
+            for i in 0..3 {
+                let color = (src[s + i] as f32 * src_a + dst[d + i] as f32 * dst_a * (1.0 - src_a)) / out_a;
+                dst[d + i] = color.round() as u8;
+            }
+            dst[d + 3] = (out_a * 255.0).round() as u8;
+        }
+    }
+}
+
+
+/// Little endian reader.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], AsepriteError> {
+        let bytes = self.data.get(self.pos..(self.pos + len)).ok_or(AsepriteError::UnexpectedEof)?;
+        self.pos += len;
+        return Ok(bytes);
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        self.pos = self.data.len();
+        return rest;
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), AsepriteError> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn seek(&mut self, pos: usize) -> Result<(), AsepriteError> {
+        if pos > self.data.len() {
+            return Err(AsepriteError::UnexpectedEof);
+        }
+        self.pos = pos;
+        return Ok(());
+    }
+
+    fn u8(&mut self) -> Result<u8, AsepriteError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, AsepriteError> {
+        let b = self.bytes(2)?;
+        return Ok(u16::from_le_bytes([b[0], b[1]]));
+    }
+
+    fn i16(&mut self) -> Result<i16, AsepriteError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, AsepriteError> {
+        let b = self.bytes(4)?;
+        return Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    }
+
+    fn i32(&mut self) -> Result<i32, AsepriteError> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn string(&mut self) -> Result<String, AsepriteError> {
+        let len = self.u16()? as usize;
+        return Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned());
+    }
+
+    /// Reads a rect stored as position and size.
+    fn rect(&mut self) -> Result<IRect, AsepriteError> {
+        let start = ivec2(self.i32()?, self.i32()?);
+        let size = ivec2(self.u32()? as i32, self.u32()? as i32);
+        return Ok(IRect { start, end: start + size });
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::{rect::IRect, vector2::{ivec2, uvec2}};
+
+    use crate::graphics::animation::PlaybackMode;
+
+    use super::{AseTagDirection, AsepriteError, AsepriteFile};
+
+    /// Writes a chunk with its header.
+    fn chunk(out: &mut Vec<u8>, chunk_type: u16, data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32 + 6).to_le_bytes());
+        out.extend_from_slice(&chunk_type.to_le_bytes());
+        out.extend_from_slice(data);
+    }
+
+    fn frame(out: &mut Vec<u8>, duration_ms: u16, chunks: &[u8], chunk_count: u32) {
+        out.extend_from_slice(&(chunks.len() as u32 + 16).to_le_bytes());
+        out.extend_from_slice(&0xF1FAu16.to_le_bytes());
+        out.extend_from_slice(&0xFFFFu16.to_le_bytes());
+        out.extend_from_slice(&duration_ms.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&chunk_count.to_le_bytes());
+        out.extend_from_slice(chunks);
+    }
+
+    fn layer(name: &str, visible: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(visible as u16 | 2).to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 0, 0, 0]); // Normal, level 0, opacity 128
+        data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        return data;
+    }
+
+    fn cel_header(layer: u16, pos: (i16, i16), cel_type: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&layer.to_le_bytes());
+        data.extend_from_slice(&pos.0.to_le_bytes());
+        data.extend_from_slice(&pos.1.to_le_bytes());
+        data.push(255);
+        data.extend_from_slice(&cel_type.to_le_bytes());
+        data.extend_from_slice(&[0; 7]);
+        return data;
+    }
+
+    /// 2x2 RGBA file with two frames, a hidden layer, a tag and a 9-slice.
+    fn test_file() -> Vec<u8> {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+
+        let mut frame0 = Vec::new();
+        let mut layer_chunk = layer("base", true);
+        layer_chunk[12] = 255; // Opaque
+        chunk(&mut frame0, 0x2004, &layer_chunk);
+        chunk(&mut frame0, 0x2004, &layer("hidden", false));
+
+        let mut raw = cel_header(0, (1, 0), 0);
+        raw.extend_from_slice(&[1, 0, 2, 0]);
+        raw.extend_from_slice(&[red, red].concat());
+        chunk(&mut frame0, 0x2005, &raw);
+
+        let mut hidden = cel_header(1, (0, 0), 0);
+        hidden.extend_from_slice(&[1, 0, 1, 0]);
+        hidden.extend_from_slice(&blue);
+        chunk(&mut frame0, 0x2005, &hidden);
+
+        let mut tags = vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        tags.extend_from_slice(&[0, 0, 1, 0, 2, 0, 0]);
+        tags.extend_from_slice(&[0; 10]);
+        tags.extend_from_slice(&[4, 0]);
+        tags.extend_from_slice(b"walk");
+        chunk(&mut frame0, 0x2018, &tags);
+
+        let mut slice = Vec::new();
+        slice.extend_from_slice(&1u32.to_le_bytes());
+        slice.extend_from_slice(&1u32.to_le_bytes()); // 9-patch
+        slice.extend_from_slice(&0u32.to_le_bytes());
+        slice.extend_from_slice(&[5, 0]);
+        slice.extend_from_slice(b"panel");
+        for value in [0u32, 0, 0, 2, 2, 1, 1, 0, 1] {
+            slice.extend_from_slice(&value.to_le_bytes());
+        }
+        chunk(&mut frame0, 0x2022, &slice);
+
+        // Stored zlib stream, without the optional checksum
+        let mut frame1 = Vec::new();
+        let mut compressed = cel_header(0, (0, 1), 2);
+        compressed.extend_from_slice(&[2, 0, 1, 0]);
+        compressed.extend_from_slice(&[0x78, 0x01, 0x01, 8, 0, !8, !0]);
+        compressed.extend_from_slice(&[blue, red].concat());
+        chunk(&mut frame1, 0x2005, &compressed);
+
+        let mut frames = Vec::new();
+        frame(&mut frames, 100, &frame0, 6);
+        frame(&mut frames, 250, &frame1, 1);
+
+        let mut file = Vec::new();
+        file.extend_from_slice(&(128 + frames.len() as u32).to_le_bytes());
+        file.extend_from_slice(&0xA5E0u16.to_le_bytes());
+        file.extend_from_slice(&[2, 0, 2, 0, 2, 0, 32, 0]);
+        file.extend_from_slice(&1u32.to_le_bytes()); // Layer opacity is valid
+        file.resize(128, 0);
+        file.extend_from_slice(&frames);
+        return file;
+    }
+
+    #[test]
+    fn parse() {
+        let file = AsepriteFile::parse(&test_file()).unwrap();
+        assert_eq!(file.dims(), uvec2(2, 2));
+        assert_eq!(file.layers().len(), 2);
+        assert_eq!(file.layers()[1].opacity, 128);
+        assert_eq!(file.frames()[1].duration, 0.25);
+
+        let tag = file.tag("walk").unwrap();
+        assert_eq!((tag.from, tag.to, tag.direction), (0, 1, AseTagDirection::PingPong));
+
+        let key = file.slice("panel").unwrap().key_at(1).unwrap();
+        assert_eq!(key.bounds, IRect { start: ivec2(0, 0), end: ivec2(2, 2) });
+        assert_eq!(key.center, Some(IRect { start: ivec2(1, 1), end: ivec2(1, 2) }));
+
+        assert!(matches!(AsepriteFile::parse(&[0; 16]), Err(AsepriteError::InvalidMagic)));
+        assert!(matches!(AsepriteFile::parse(&test_file()[..200]), Err(AsepriteError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn malformed_palette() {
+        let parse_palette = |size: u32, first: u32, last: u32, entries: usize| {
+            let mut palette = Vec::new();
+            for value in [size, first, last, 0, 0] {
+                palette.extend_from_slice(&value.to_le_bytes());
+            }
+            for _ in 0..entries {
+                palette.extend_from_slice(&[0, 0, 255, 0, 0, 255]);
+            }
+
+            let mut chunks = Vec::new();
+            chunk(&mut chunks, 0x2019, &palette);
+            let mut frames = Vec::new();
+            frame(&mut frames, 100, &chunks, 1);
+
+            let mut file = Vec::new();
+            file.extend_from_slice(&(128 + frames.len() as u32).to_le_bytes());
+            file.extend_from_slice(&0xA5E0u16.to_le_bytes());
+            file.extend_from_slice(&[1, 0, 1, 0, 1, 0, 32, 0]);
+            file.resize(128, 0);
+            file.extend_from_slice(&frames);
+            return AsepriteFile::parse(&file);
+        };
+
+        assert!(parse_palette(2, 0, 1, 2).is_ok());
+        assert!(matches!(parse_palette(2, 2, 0, 0), Err(AsepriteError::InvalidPalette)));
+        assert!(matches!(parse_palette(u32::MAX, 0, 0, 1), Err(AsepriteError::InvalidPalette)));
+        assert!(matches!(parse_palette(1, 0, u32::MAX - 1, 1), Err(AsepriteError::InvalidPalette)));
+        assert!(matches!(parse_palette(4, 0, 3, 1), Err(AsepriteError::InvalidPalette)));
+    }
+
+    #[test]
+    fn composite() {
+        let file = AsepriteFile::parse(&test_file()).unwrap();
+
+        // The hidden layer is ignored
+        let frame0 = file.frame_pixels(0);
+        assert_eq!(frame0.data(), &[
+            0, 0, 0, 0, 255, 0, 0, 255,
+            0, 0, 0, 0, 255, 0, 0, 255,
+        ]);
+
+        let frame1 = file.frame_pixels(1);
+        assert_eq!(frame1.data(), &[
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 255, 255, 255, 0, 0, 255,
+        ]);
+
+        let sheet = file.sheet_pixels();
+        assert_eq!(sheet.dims(), uvec2(4, 2));
+        assert_eq!(file.frame_cell(1), ivec2(1, 0));
+
+        let clip = file.clip("walk", &[0, 1]).unwrap();
+        assert_eq!(clip.mode(), PlaybackMode::PingPong);
+        assert_eq!(clip.durations(), &[0.1, 0.25]);
+        assert!(file.clip("run", &[0, 1]).is_none());
+    }
+}
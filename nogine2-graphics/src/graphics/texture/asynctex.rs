@@ -0,0 +1,94 @@
+use std::{path::PathBuf, sync::{Arc, Mutex, MutexGuard}};
+
+use nogine2_core::{crash, jobs, log_error};
+
+use crate::graphics::PLACEHOLDER_TEX;
+
+use super::{pixels::{PixelLoadingError, Pixels}, Texture2D, TextureSampling};
+
+enum LoadState {
+    Loading,
+    Ready(Texture2D),
+    Failed(PixelLoadingError),
+}
+
+/// A texture that is decoded in a worker thread and uploaded on the main thread. **Must only be used on the main thread!**
+pub struct AsyncTexture2D {
+    shared: Arc<Mutex<LoadState>>,
+    texture: Option<Texture2D>,
+    error: Option<PixelLoadingError>,
+    placeholder: Texture2D,
+}
+
+impl AsyncTexture2D {
+    pub(crate) fn start(path: PathBuf, sampling: TextureSampling) -> Self {
+        let shared = Arc::new(Mutex::new(LoadState::Loading));
+
+        let job_shared = shared.clone();
+        _ = jobs::spawn(move || {
+            match Pixels::load(&path) {
+                Ok(pixels) => _ = jobs::run_on_main_thread(move || {
+                    let texture = Texture2D::new(pixels, sampling);
+                    *lock(&job_shared) = LoadState::Ready(texture);
+                }),
+                Err(e) => {
+                    log_error!("Couldn't load texture '{}': {e}", path.display());
+                    *lock(&job_shared) = LoadState::Failed(e);
+                },
+            }
+        });
+
+        return Self { shared, texture: None, error: None, placeholder: PLACEHOLDER_TEX.get() };
+    }
+
+    /// Returns if the texture has been uploaded.
+    pub fn is_ready(&self) -> bool {
+        self.texture.is_some() || matches!(*lock(&self.shared), LoadState::Ready(_))
+    }
+
+    /// Returns if the texture couldn't be loaded.
+    pub fn is_failed(&self) -> bool {
+        self.error.is_some() || matches!(*lock(&self.shared), LoadState::Failed(_))
+    }
+
+    /// Returns the loading error, if `poll` found one.
+    pub fn error(&self) -> Option<&PixelLoadingError> {
+        self.error.as_ref()
+    }
+
+    /// Returns the texture if it has been uploaded.
+    pub fn poll(&mut self) -> Option<&Texture2D> {
+        if self.texture.is_none() && self.error.is_none() {
+            let mut state = lock(&self.shared);
+            match std::mem::replace(&mut *state, LoadState::Loading) {
+                LoadState::Loading => {},
+                LoadState::Ready(texture) => self.texture = Some(texture),
+                LoadState::Failed(e) => self.error = Some(e),
+            }
+        }
+        return self.texture.as_ref();
+    }
+
+    /// Returns the texture if it has been uploaded, or a placeholder otherwise.
+    pub fn tex(&mut self) -> &Texture2D {
+        self.poll();
+        return self.texture.as_ref().unwrap_or(&self.placeholder);
+    }
+
+    /// Takes the texture out if it has been uploaded. The handle will return the placeholder afterwards.
+    pub(crate) fn take(&mut self) -> Option<Texture2D> {
+        self.poll();
+        return self.texture.take();
+    }
+
+    /// Returns the texture, consuming the handle, if it has been uploaded.
+    pub fn into_texture(mut self) -> Result<Texture2D, Self> {
+        self.poll();
+        return self.texture.take().ok_or(self);
+    }
+}
+
+fn lock(shared: &Mutex<LoadState>) -> MutexGuard<'_, LoadState> {
+    let Ok(state) = shared.lock() else { crash!("Couldn't access texture loading state!") };
+    return state;
+}
@@ -0,0 +1,324 @@
+use nogine2_core::{assert_expr, crash, log_warn, math::{rect::{Rect, URect}, vector2::{uvec2, vec2}}};
+
+use super::{pixels::{PixelFormat, Pixels}, sprite::Sprite, Texture2D, TextureSampling};
+
+/// Packs images into one or more atlas pages at runtime. Images can be inserted at any time, so it's also fit for dynamic content like glyph caches. **Must only be used on the main thread!**
+#[derive(Debug, Clone)]
+pub struct TextureAtlasBuilder {
+    page_dims: uvec2,
+    format: PixelFormat,
+    sampling: TextureSampling,
+    padding: u32,
+    extrusion: u32,
+    pages: Vec<AtlasPage>,
+}
+
+#[derive(Debug, Clone)]
+struct AtlasPage {
+    packer: SkylinePacker,
+    texture: Texture2D,
+}
+
+impl TextureAtlasBuilder {
+    /// Creates a new builder. Pages are created on demand. Will panic if `page_dims.0 == 0` or `page_dims.1 == 0`.
+    pub fn new(page_dims: uvec2, format: PixelFormat, sampling: TextureSampling) -> Self {
+        assert_expr!(page_dims.0 > 0 && page_dims.1 > 0, "'page_dims' must be greater than 0 for every axis!");
+        return Self { page_dims, format, sampling, padding: 0, extrusion: 0, pages: Vec::new() };
+    }
+
+    /// Sets the empty space between images, in pixels. Only affects later insertions.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        return self;
+    }
+
+    /// Sets how many times the border pixels of every image are repeated around it, to avoid bleeding when filtering. Only affects later insertions.
+    pub fn with_extrusion(mut self, extrusion: u32) -> Self {
+        self.extrusion = extrusion;
+        return self;
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn extrusion(&self) -> u32 {
+        self.extrusion
+    }
+
+    pub fn page_dims(&self) -> uvec2 {
+        self.page_dims
+    }
+
+    /// Packs an image and uploads it to its page. Will return `None` if the image doesn't fit in an empty page. Will panic if the image is empty or its format doesn't match the atlas.
+    pub fn insert(&mut self, pixels: &Pixels) -> Option<Sprite> {
+        assert_expr!(pixels.dims().0 > 0 && pixels.dims().1 > 0, "Can't pack an empty image!");
+        assert_expr!(pixels.format() == self.format, "The format of the image must match the format of the atlas!");
+
+        let border = uvec2::one(self.extrusion * 2);
+        let reserved = pixels.dims() + border + uvec2::one(self.padding);
+        let Some((page_index, pos)) = self.reserve(reserved) else {
+            log_warn!("Image of {} pixels doesn't fit in an atlas page of {} pixels!", pixels.dims(), self.page_dims);
+            return None;
+        };
+
+        let page = &mut self.pages[page_index];
+        let img_pos = pos + uvec2::one(self.extrusion);
+        let Some(page_pixels) = page.texture.pixels_mut() else { crash!("Atlas page lost its pixel data!") };
+        blit_extruded(page_pixels, pixels, img_pos, self.extrusion);
+        page.texture.refresh_region(URect { start: pos, end: pos + pixels.dims() + border });
+
+        let uv_rect = Rect {
+            start: vec2::from(img_pos).inv_scale(vec2::from(self.page_dims)),
+            end: vec2::from(img_pos + pixels.dims()).inv_scale(vec2::from(self.page_dims)),
+        };
+        return Some(Sprite::new(page.texture.handle(), uv_rect, pixels.dims()));
+    }
+
+    /// Packs the pixels of a texture. Will return `None` and throw a warning if the texture has no CPU access to its pixel data.
+    pub fn insert_texture(&mut self, texture: &Texture2D) -> Option<Sprite> {
+        let Some(pixels) = texture.pixels() else {
+            log_warn!("Can't pack a 'Texture2D' without CPU access to its pixel data!");
+            return None;
+        };
+        return self.insert(pixels);
+    }
+
+    /// Returns the textures of every page.
+    pub fn pages(&self) -> impl Iterator<Item = &Texture2D> {
+        self.pages.iter().map(|p| &p.texture)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Finds space in the existing pages, creating a new page if none of them has it.
+    fn reserve(&mut self, size: uvec2) -> Option<(usize, uvec2)> {
+        if size.0 > self.page_dims.0 || size.1 > self.page_dims.1 {
+            return None;
+        }
+
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if let Some(pos) = page.packer.insert(size) {
+                return Some((i, pos));
+            }
+        }
+
+        let mut packer = SkylinePacker::new(self.page_dims);
+        let pos = packer.insert(size)?;
+        let data = vec![0; (self.page_dims.0 * self.page_dims.1) as usize * self.format.byte_size()];
+        let texture = Texture2D::new(Pixels::new(data, self.page_dims, self.format), self.sampling.clone());
+        self.pages.push(AtlasPage { packer, texture });
+        return Some((self.pages.len() - 1, pos));
+    }
+}
+
+/// Copies `src` with its top left corner at `pos`, repeating its border pixels `extrusion` times around it.
+fn blit_extruded(dst: &mut Pixels, src: &Pixels, pos: uvec2, extrusion: u32) {
+    let bpp = src.format().byte_size();
+    let src_dims = (src.dims().0 as i64, src.dims().1 as i64);
+    let dst_width = dst.dims().0 as i64;
+    let ext = extrusion as i64;
+
+    let src_data = src.data();
+    let dst_data = dst.data_mut();
+    for y in -ext..(src_dims.1 + ext) {
+        let sy = y.clamp(0, src_dims.1 - 1);
+        for x in -ext..(src_dims.0 + ext) {
+            let sx = x.clamp(0, src_dims.0 - 1);
+            let s = (sx + sy * src_dims.0) as usize * bpp;
+            let d = ((pos.0 as i64 + x) + (pos.1 as i64 + y) * dst_width) as usize * bpp;
+            dst_data[d..(d + bpp)].copy_from_slice(&src_data[s..(s + bpp)]);
+        }
+    }
+}
+
+
+/// Packs rectangles inside of a fixed area using the skyline bottom-left heuristic. It doesn't need a GL context.
+#[derive(Debug, Clone)]
+pub struct SkylinePacker {
+    dims: uvec2,
+    skyline: Vec<SkylineNode>,
+    used_area: u64,
+}
+
+/// Horizontal segment of the skyline, everything under `y` is taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+impl SkylinePacker {
+    pub fn new(dims: uvec2) -> Self {
+        return Self { dims, skyline: vec![SkylineNode { x: 0, y: 0, width: dims.0 }], used_area: 0 };
+    }
+
+    pub fn dims(&self) -> uvec2 {
+        self.dims
+    }
+
+    /// Returns the fraction of the area that is taken, from `0.0` to `1.0`.
+    pub fn occupancy(&self) -> f32 {
+        let area = self.dims.0 as u64 * self.dims.1 as u64;
+        if area == 0 {
+            return 1.0;
+        }
+        return self.used_area as f32 / area as f32;
+    }
+
+    /// Reserves a rectangle of `size`. Returns its top left corner, or `None` if it doesn't fit.
+    pub fn insert(&mut self, size: uvec2) -> Option<uvec2> {
+        if size.0 == 0 || size.1 == 0 {
+            return Some(uvec2::ZERO);
+        }
+
+        let mut best = None;
+        let mut best_key = (u32::MAX, u32::MAX);
+        for i in 0..self.skyline.len() {
+            let Some(y) = self.fit(i, size) else { continue };
+
+            // Lowest top edge first, then the tightest node
+            let key = (y + size.1, self.skyline[i].width);
+            if key < best_key {
+                best_key = key;
+                best = Some((i, uvec2(self.skyline[i].x, y)));
+            }
+        }
+
+        let (index, pos) = best?;
+        self.add_level(index, pos, size);
+        self.used_area += size.0 as u64 * size.1 as u64;
+        return Some(pos);
+    }
+
+    /// Frees all the area.
+    pub fn clear(&mut self) {
+        self.skyline.clear();
+        self.skyline.push(SkylineNode { x: 0, y: 0, width: self.dims.0 });
+        self.used_area = 0;
+    }
+
+    /// Returns the height a rect of `size` would be placed at if it started at the node `index`.
+    fn fit(&self, index: usize, size: uvec2) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + size.0 > self.dims.0 {
+            return None;
+        }
+
+        let mut y = 0;
+        for node in &self.skyline[index..] {
+            if node.x >= x + size.0 {
+                break;
+            }
+
+            y = y.max(node.y);
+            if y + size.1 > self.dims.1 {
+                return None;
+            }
+        }
+        return Some(y);
+    }
+
+    fn add_level(&mut self, index: usize, pos: uvec2, size: uvec2) {
+        self.skyline.insert(index, SkylineNode { x: pos.0, y: pos.1 + size.1, width: size.0 });
+
+        // Shrink or remove the nodes under the new one
+        let end = pos.0 + size.0;
+        let i = index + 1;
+        while i < self.skyline.len() {
+            let node = &mut self.skyline[i];
+            if node.x >= end {
+                break;
+            }
+
+            let node_end = node.x + node.width;
+            if node_end <= end {
+                self.skyline.remove(i);
+            } else {
+                node.x = end;
+                node.width = node_end - end;
+                break;
+            }
+        }
+
+        // Merge neighbours at the same height
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].y == self.skyline[i + 1].y {
+                self.skyline[i].width += self.skyline[i + 1].width;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::vector2::uvec2;
+
+    use crate::graphics::texture::pixels::{PixelFormat, Pixels};
+
+    use super::{blit_extruded, SkylinePacker};
+
+    fn overlaps(a: (uvec2, uvec2), b: (uvec2, uvec2)) -> bool {
+        return a.0.0 < b.0.0 + b.1.0 && b.0.0 < a.0.0 + a.1.0
+            && a.0.1 < b.0.1 + b.1.1 && b.0.1 < a.0.1 + a.1.1;
+    }
+
+    #[test]
+    fn packer_fills_exactly() {
+        let mut packer = SkylinePacker::new(uvec2(64, 64));
+        for _ in 0..4 {
+            assert!(packer.insert(uvec2(32, 32)).is_some());
+        }
+        assert_eq!(packer.occupancy(), 1.0);
+        assert_eq!(packer.insert(uvec2(1, 1)), None);
+
+        packer.clear();
+        assert_eq!(packer.insert(uvec2(64, 64)), Some(uvec2(0, 0)));
+        assert_eq!(packer.insert(uvec2(65, 1)), None);
+    }
+
+    #[test]
+    fn packer_never_overlaps() {
+        let mut packer = SkylinePacker::new(uvec2(256, 256));
+        let mut placed = Vec::new();
+
+        let mut seed = 12345u32;
+        for _ in 0..300 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let size = uvec2(1 + (seed >> 16) % 40, 1 + (seed >> 8) % 40);
+            let Some(pos) = packer.insert(size) else { continue };
+
+            assert!(pos.0 + size.0 <= 256 && pos.1 + size.1 <= 256);
+            for other in &placed {
+                assert!(!overlaps((pos, size), *other));
+            }
+            placed.push((pos, size));
+        }
+
+        assert!(placed.len() > 50);
+        assert!(packer.occupancy() > 0.6);
+    }
+
+    #[test]
+    fn extrusion() {
+        let src = Pixels::new(vec![1, 2, 3, 4], uvec2(2, 2), PixelFormat::R8);
+        let mut dst = Pixels::new(vec![0; 25], uvec2(5, 5), PixelFormat::R8);
+        blit_extruded(&mut dst, &src, uvec2(1, 1), 1);
+
+        assert_eq!(dst.data(), &[
+            1, 1, 2, 2, 0,
+            1, 1, 2, 2, 0,
+            3, 3, 4, 4, 0,
+            3, 3, 4, 4, 0,
+            0, 0, 0, 0, 0,
+        ]);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{ffi::c_void, sync::Arc};
 
-use nogine2_core::math::vector2::uvec2;
+use nogine2_core::{assert_expr, math::{rect::URect, vector2::uvec2}};
 use pixels::{PixelFormat, Pixels};
 
 use crate::gl_wrapper::texture::{GlTexture, GlTextureFiltering, GlTextureWrapping};
@@ -8,14 +8,24 @@ use crate::gl_wrapper::texture::{GlTexture, GlTextureFiltering, GlTextureWrappin
 pub mod pixels;
 pub mod rendertex;
 pub mod sprite;
+pub mod array;
+pub mod atlas;
+pub mod aseprite;
+#[cfg(feature = "image-loading")]
+pub mod asynctex;
 
 /// The handle of a texture. **Must only be used on the main thread!**
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TextureHandle {
     gl_obj: Arc<GlTexture>,
+    layer: u32,
 }
 
 impl TextureHandle {
+    pub(crate) fn new(gl_obj: Arc<GlTexture>) -> Self {
+        Self { gl_obj, layer: 0 }
+    }
+
     pub(crate) fn bind_to(&self, target: u32) {
         self.gl_obj.bind_to(target);
     }
@@ -23,6 +33,21 @@ impl TextureHandle {
     pub(crate) fn dims(&self) -> uvec2 {
         self.gl_obj.dims()
     }
+
+    /// Identifies the underlying texture, for sorting purposes.
+    pub(crate) fn sort_id(&self) -> usize {
+        Arc::as_ptr(&self.gl_obj) as usize
+    }
+
+    /// Returns the sampled layer if the handle points to a `TextureArray`.
+    pub(crate) fn layer(&self) -> Option<u32> {
+        self.gl_obj.layers().map(|_| self.layer)
+    }
+
+    /// Returns `true` if both handles use the same GPU texture, ignoring the layer.
+    pub(crate) fn same_texture(&self, other: &TextureHandle) -> bool {
+        Arc::ptr_eq(&self.gl_obj, &other.gl_obj)
+    }
 }
 
 
@@ -44,6 +69,12 @@ impl Texture2D {
         return Ok(Self::new(pixel_data, sampling));
     }
 
+    /// Starts loading a texture in the background. The pixels are decoded in a worker thread and uploaded on the next `Window::pre_tick`.
+    #[cfg(feature = "image-loading")]
+    pub fn load_async(path: impl AsRef<std::path::Path>, sampling: TextureSampling) -> asynctex::AsyncTexture2D {
+        asynctex::AsyncTexture2D::start(path.as_ref().to_path_buf(), sampling)
+    }
+
     /// Creates a new texture.
     pub fn new(pixel_data: Pixels, sampling: TextureSampling) -> Self {
         let gl_obj = Arc::new(GlTexture::new(
@@ -67,7 +98,7 @@ impl Texture2D {
 
     /// Returns a handle to the texture.
     pub fn handle(&self) -> TextureHandle {
-        TextureHandle { gl_obj: self.gl_obj.clone() }
+        TextureHandle::new(self.gl_obj.clone())
     }
 
     /// Updates the data from the GPU to match the data from the CPU.
@@ -77,6 +108,15 @@ impl Texture2D {
         }
     }
 
+    /// Updates a region of the GPU data to match the CPU data. Will panic if `rect` is out of bounds.
+    pub fn refresh_region(&self, rect: URect) {
+        assert_expr!(rect.end.0 <= self.dims.0 && rect.end.1 <= self.dims.1, "'rect' must be inside of the texture!");
+        if let Some(pixels) = &self.pixels {
+            let region = pixels.crop(rect);
+            self.gl_obj.set(rect.start, region.dims(), region.format().into(), region.data().as_ptr() as *const c_void);
+        }
+    }
+
     /// Returns the resolution of the texture.
     pub fn dims(&self) -> uvec2 {
         self.dims
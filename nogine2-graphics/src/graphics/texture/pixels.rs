@@ -1,4 +1,4 @@
-use nogine2_core::{assert_expr, math::vector2::uvec2};
+use nogine2_core::{assert_expr, math::{rect::URect, vector2::uvec2}};
 
 use crate::{colors::rgba::RGBA8, gl_wrapper::texture::GlTextureFormat};
 
@@ -106,6 +106,20 @@ impl Pixels {
         }
     }
 
+    /// Returns a copy of the pixels inside of `rect`. Will panic if `rect` is out of bounds.
+    pub fn crop(&self, rect: URect) -> Pixels {
+        assert_expr!(rect.start.0 <= rect.end.0 && rect.start.1 <= rect.end.1 && rect.end.0 <= self.dims.0 && rect.end.1 <= self.dims.1, "Rect out of bounds (rect was {} to {}, dims were {})!", rect.start, rect.end, self.dims);
+
+        let bpp = self.format.byte_size();
+        let dims = rect.end - rect.start;
+        let mut data = Vec::with_capacity((dims.0 * dims.1) as usize * bpp);
+        for y in rect.start.1..rect.end.1 {
+            let row = (rect.start.0 + y * self.dims.0) as usize * bpp;
+            data.extend_from_slice(&self.data[row..(row + dims.0 as usize * bpp)]);
+        }
+        return Self { data, dims, format: self.format };
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data
     }
@@ -105,18 +105,25 @@ impl RenderTexture {
             return;
         }
 
-        if let Some(textures_loc) = material.uniform_loc(c"uTextures") {
-            gl_uniform::set_i32_arr(textures_loc, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-        }
-
         let Some(src) = src.handle() else {
             log_error!("'src' must not be a RenderTexture to the screen!");
             return;
         };
 
-        src.bind_to(0);
+        // `src` goes in the first unit after the uniform samplers. Only `uTextures[0]` is pointed to it, and restored
+        // afterwards to the unit assigned when linking, so batches keep their slots
+        let tex_offset = material.sampler_count();
+        let textures_loc = material.uniform_loc(c"uTextures").filter(|_| material.batch_slots().0 > 0);
+        if let Some(loc) = textures_loc {
+            gl_uniform::set_i32(loc, tex_offset as i32);
+        }
+
+        src.bind_to(tex_offset as u32);
 
         gl_render_array(GlRenderMode::GlTriangles, 3);
+        if let Some(loc) = textures_loc {
+            gl_uniform::set_i32(loc, 0);
+        }
         GlFramebuffer::to_screen().bind();
 
         stats.blit.draw_calls += 1;
@@ -125,7 +132,7 @@ impl RenderTexture {
     pub fn handle(&self) -> Option<TextureHandle> {
         match &self.gl_col_att {
             ColAtt::Screen => None,
-            ColAtt::Offscreen(arc) => Some(TextureHandle { gl_obj: arc.clone() }),
+            ColAtt::Offscreen(arc) => Some(TextureHandle::new(arc.clone())),
         }
     }
 }
@@ -69,7 +69,7 @@ impl SpriteAtlas {
 
 
 /// A segment from a `SpriteAtlas`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Sprite {
     handle: TextureHandle,
     uv_rect: Rect,
@@ -77,6 +77,10 @@ pub struct Sprite {
 }
 
 impl Sprite {
+    pub(crate) fn new(handle: TextureHandle, uv_rect: Rect, dims: uvec2) -> Self {
+        Self { handle, uv_rect, dims }
+    }
+
     pub fn handle(&self) -> &TextureHandle {
         &self.handle
     }
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+
+use bitflags::bitflags;
+use nogine2_core::math::{rect::{IRect, Rect}, vector2::{ivec2, vec2}};
+
+use crate::colors::{rgba::RGBA32, Color};
+
+use super::{animation::AnimationClip, mesh::Mesh2D, scope::RenderScopeCfgFlags, texture::sprite::SpriteAtlas, vertex::BatchVertex, CameraData, Graphics};
+
+bitflags! {
+    /// Orientation of a tile. The rotation is applied before the flips, so every orientation can be represented.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct TileFlags : u8 {
+        const FLIP_X = 1 << 0;
+        const FLIP_Y = 1 << 1;
+        /// Rotates the tile 90 degrees counter-clockwise.
+        const ROTATE_90 = 1 << 2;
+    }
+}
+
+/// A cell of a `TileLayer`. `id` is the index of a cell of the atlas, counting left to right and top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub id: u32,
+    pub flags: TileFlags,
+}
+
+impl Tile {
+    pub const fn new(id: u32) -> Self {
+        Self { id, flags: TileFlags::empty() }
+    }
+
+    pub const fn with_flags(mut self, flags: TileFlags) -> Self {
+        self.flags = flags;
+        return self;
+    }
+}
+
+
+/// Index of a `TileLayer` inside of a `Tilemap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileLayerId(u32);
+
+/// Sparse grid of tiles, split into chunks whose geometry is only rebuilt when they change.
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    /// Offset from the position of the tilemap, in units.
+    pub offset: vec2,
+    /// How much the layer follows the camera. `(1, 1)` moves with the world, `(0, 0)` stays fixed on screen.
+    pub parallax: vec2,
+    pub visible: bool,
+
+    tint: RGBA32,
+    chunks: HashMap<ivec2, Chunk>,
+}
+
+impl TileLayer {
+    fn new() -> Self {
+        Self { offset: vec2::ZERO, parallax: vec2::ONE, visible: true, tint: RGBA32::WHITE, chunks: HashMap::new() }
+    }
+
+    pub fn tint(&self) -> RGBA32 {
+        self.tint
+    }
+
+    /// Sets the tint of every tile. Rebuilds the geometry of the whole layer.
+    pub fn set_tint(&mut self, tint: RGBA32) {
+        if self.tint != tint {
+            self.tint = tint;
+            self.invalidate();
+        }
+    }
+
+    pub fn get(&self, pos: ivec2) -> Option<Tile> {
+        let (chunk, index) = chunk_index(pos);
+        return self.chunks.get(&chunk).and_then(|c| c.tiles[index]);
+    }
+
+    /// Sets or removes a tile.
+    pub fn set(&mut self, pos: ivec2, tile: Option<Tile>) {
+        let (chunk_pos, index) = chunk_index(pos);
+        let Some(chunk) = (match tile {
+            Some(_) => Some(self.chunks.entry(chunk_pos).or_insert_with(Chunk::new)),
+            None => self.chunks.get_mut(&chunk_pos),
+        }) else { return };
+
+        if chunk.tiles[index] == tile {
+            return;
+        }
+
+        match (chunk.tiles[index], tile) {
+            (None, Some(_)) => chunk.tile_count += 1,
+            (Some(_), None) => chunk.tile_count -= 1,
+            _ => (),
+        }
+        chunk.tiles[index] = tile;
+        chunk.dirty = true;
+
+        if chunk.tile_count == 0 {
+            self.chunks.remove(&chunk_pos);
+        }
+    }
+
+    /// Sets or removes every tile inside of `rect`, in tile coordinates.
+    pub fn fill(&mut self, rect: IRect, tile: Option<Tile>) {
+        for y in rect.start.1..rect.end.1 {
+            for x in rect.start.0..rect.end.0 {
+                self.set(ivec2(x, y), tile);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+
+    /// Returns the number of tiles in the layer.
+    pub fn tile_count(&self) -> usize {
+        self.chunks.values().map(|c| c.tile_count).sum()
+    }
+
+    /// Returns the number of allocated chunks.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns the position of every tile, in no particular order.
+    pub fn tiles(&self) -> impl Iterator<Item = (ivec2, Tile)> + '_ {
+        self.chunks.iter().flat_map(|(chunk, c)| c.tiles.iter().enumerate().filter_map(move |(i, t)| {
+            let tile = (*t)?;
+            let local = ivec2(i as i32 % Tilemap::CHUNK_SIZE, i as i32 / Tilemap::CHUNK_SIZE);
+            Some((ivec2(chunk.0 * Tilemap::CHUNK_SIZE + local.0, chunk.1 * Tilemap::CHUNK_SIZE + local.1), tile))
+        }))
+    }
+
+    fn invalidate(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            chunk.dirty = true;
+        }
+    }
+}
+
+
+/// Layers of tiles over a `SpriteAtlas`. Tile `(x, y)` covers `x..x + 1` and `y..y + 1` in tile space, with one tile being a cell of the atlas at `Graphics::pixels_per_unit`.
+#[derive(Debug, Clone)]
+pub struct Tilemap {
+    /// Position of the corner of tile `(0, 0)`, in units.
+    pub pos: vec2,
+
+    atlas: SpriteAtlas,
+    layers: Vec<TileLayer>,
+    animations: HashMap<u32, AnimationClip<u32>>,
+    time: f32,
+}
+
+impl Tilemap {
+    /// Width and height of a chunk, in tiles.
+    pub const CHUNK_SIZE: i32 = 16;
+
+    pub fn new(atlas: SpriteAtlas) -> Self {
+        Self { pos: vec2::ZERO, atlas, layers: Vec::new(), animations: HashMap::new(), time: 0.0 }
+    }
+
+    pub fn with_pos(mut self, pos: vec2) -> Self {
+        self.pos = pos;
+        return self;
+    }
+
+    pub fn atlas(&self) -> &SpriteAtlas {
+        &self.atlas
+    }
+
+    /// Replaces the atlas. Rebuilds the geometry of every layer.
+    pub fn set_atlas(&mut self, atlas: SpriteAtlas) {
+        self.atlas = atlas;
+        self.invalidate();
+    }
+
+    /// Adds a layer, drawn over the previous ones.
+    pub fn add_layer(&mut self) -> TileLayerId {
+        self.layers.push(TileLayer::new());
+        return TileLayerId(self.layers.len() as u32 - 1);
+    }
+
+    pub fn layer(&self, id: TileLayerId) -> Option<&TileLayer> {
+        self.layers.get(id.0 as usize)
+    }
+
+    pub fn layer_mut(&mut self, id: TileLayerId) -> Option<&mut TileLayer> {
+        self.layers.get_mut(id.0 as usize)
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Animates every tile with `id`, showing the ids of `clip` instead.
+    pub fn set_animation(&mut self, id: u32, clip: AnimationClip<u32>) {
+        self.animations.insert(id, clip);
+        self.invalidate();
+    }
+
+    pub fn remove_animation(&mut self, id: u32) {
+        if self.animations.remove(&id).is_some() {
+            self.invalidate();
+        }
+    }
+
+    /// Seconds the animations have been playing for.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Advances the animated tiles. Only chunks with animated tiles are rebuilt, and only if a frame changed.
+    pub fn update(&mut self, dt: f32) {
+        let prev = self.time;
+        self.time += dt;
+
+        let changed = self.animations.values().any(|c| c.frame_at(prev) != c.frame_at(self.time));
+        if changed {
+            for chunk in self.layers.iter_mut().flat_map(|l| l.chunks.values_mut()) {
+                chunk.dirty |= chunk.animated;
+            }
+        }
+    }
+
+    /// Returns the size of a tile in units.
+    pub fn tile_size(&self) -> vec2 {
+        return vec2::from(self.atlas.cell_size()) / Graphics::pixels_per_unit();
+    }
+
+    /// Returns the tile containing a world position. Layer offsets and parallax are ignored.
+    pub fn world_to_tile(&self, pos: vec2) -> ivec2 {
+        return world_to_tile(pos, self.pos, self.tile_size());
+    }
+
+    /// Returns the world position of the corner of a tile. Layer offsets and parallax are ignored.
+    pub fn tile_to_world(&self, tile: ivec2) -> vec2 {
+        return self.pos + vec2::from(tile).scale(self.tile_size());
+    }
+
+    /// Returns the world position of the center of a tile. Layer offsets and parallax are ignored.
+    pub fn tile_center(&self, tile: ivec2) -> vec2 {
+        return self.tile_to_world(tile) + self.tile_size() * 0.5;
+    }
+
+    /// Draws the visible layers into the target viewport, or into every viewport if there's none. Only chunks in view are rebuilt and submitted.
+    pub fn draw(&mut self) {
+        if Graphics::target_viewport().is_some() || Graphics::viewports().len() <= 1 {
+            self.draw_view(&Graphics::camera());
+            return;
+        }
+
+        Graphics::for_each_viewport(|_, viewport| self.draw_view(&viewport.camera));
+    }
+
+    /// Draws the visible layers into the target viewport, or into every viewport if there's none, culling chunks against `camera`.
+    pub fn draw_view(&mut self, camera: &CameraData) {
+        let inverted_y = Graphics::cfg().contains(RenderScopeCfgFlags::POSITIVE_Y_IS_DOWN);
+        let tile_size = self.tile_size();
+        let chunk_size = tile_size * Tilemap::CHUNK_SIZE as f32;
+        let view = camera.rect();
+
+        let atlas = &self.atlas;
+        let animations = &self.animations;
+        let time = self.time;
+        let cols = (atlas.tex().dims().0 / atlas.cell_size().0.max(1)).max(1);
+
+        for layer in self.layers.iter_mut().filter(|l| l.visible) {
+            let origin = self.pos + layer.offset + camera.center.scale(vec2::ONE - layer.parallax);
+            let tint = layer.tint;
+
+            for (chunk_pos, chunk) in layer.chunks.iter_mut() {
+                let start = origin + vec2::from(*chunk_pos).scale(chunk_size);
+                let bounds = Rect { start, end: start + chunk_size };
+                if !bounds.intersects(view) {
+                    continue;
+                }
+
+                if chunk.dirty || chunk.inverted_y != inverted_y || chunk.mesh.is_none() {
+                    let resolve = |id: u32| match animations.get(&id) {
+                        Some(clip) => clip.frames()[clip.frame_at(time)],
+                        _ => id,
+                    };
+                    let uv_rect = |id: u32| atlas.get(ivec2((id % cols) as i32, (id / cols) as i32)).uv_rect();
+
+                    let (verts, indices) = chunk_geometry(&chunk.tiles, |id| uv_rect(resolve(id)), tint, inverted_y);
+                    chunk.mesh = Some(Mesh2D::new(verts, indices, Some(atlas.tex().handle())));
+                    chunk.animated = chunk.tiles.iter().flatten().any(|t| animations.contains_key(&t.id));
+                    chunk.inverted_y = inverted_y;
+                    chunk.dirty = false;
+                }
+
+                if let Some(mesh) = &chunk.mesh {
+                    Graphics::draw_mesh_2d(start, 0.0, tile_size, mesh);
+                }
+            }
+        }
+    }
+
+    fn invalidate(&mut self) {
+        for layer in &mut self.layers {
+            layer.invalidate();
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    tiles: Vec<Option<Tile>>,
+    tile_count: usize,
+    /// Geometry in tile space, relative to the corner of the chunk.
+    mesh: Option<Mesh2D>,
+    dirty: bool,
+    animated: bool,
+    /// Y orientation the geometry was built for.
+    inverted_y: bool,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        let len = (Tilemap::CHUNK_SIZE * Tilemap::CHUNK_SIZE) as usize;
+        Self { tiles: vec![None; len], tile_count: 0, mesh: None, dirty: true, animated: false, inverted_y: false }
+    }
+}
+
+/// Returns the chunk of a tile and its index inside of it.
+fn chunk_index(pos: ivec2) -> (ivec2, usize) {
+    let chunk = ivec2(pos.0.div_euclid(Tilemap::CHUNK_SIZE), pos.1.div_euclid(Tilemap::CHUNK_SIZE));
+    let local = ivec2(pos.0.rem_euclid(Tilemap::CHUNK_SIZE), pos.1.rem_euclid(Tilemap::CHUNK_SIZE));
+    return (chunk, (local.0 + local.1 * Tilemap::CHUNK_SIZE) as usize);
+}
+
+fn world_to_tile(pos: vec2, origin: vec2, tile_size: vec2) -> ivec2 {
+    let tile = (pos - origin).inv_scale(tile_size);
+    return ivec2(tile.0.floor() as i32, tile.1.floor() as i32);
+}
+
+/// Returns the UVs of a tile for its top left, top right, bottom right and bottom left corners on screen, matching `draw_rect`.
+fn tile_uvs(uv_rect: Rect, flags: TileFlags) -> [vec2; 4] {
+    let mut uvs = [uv_rect.lu(), uv_rect.ru(), uv_rect.rd(), uv_rect.ld()];
+
+    if flags.contains(TileFlags::ROTATE_90) {
+        uvs.rotate_left(1);
+    }
+    if flags.contains(TileFlags::FLIP_X) {
+        uvs.swap(0, 1);
+        uvs.swap(2, 3);
+    }
+    if flags.contains(TileFlags::FLIP_Y) {
+        uvs.swap(0, 3);
+        uvs.swap(1, 2);
+    }
+    return uvs;
+}
+
+/// Builds the quads of a chunk in tile space.
+fn chunk_geometry(tiles: &[Option<Tile>], uv_rect: impl Fn(u32) -> Rect, tint: RGBA32, inverted_y: bool) -> (Vec<BatchVertex>, Vec<u32>) {
+    const CORNER_UV1: [vec2; 4] = [vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0)];
+
+    // Screen corner of each vertex, for left down, left up, right up and right down in tile space
+    let corners = if inverted_y { [0, 3, 2, 1] } else { [3, 0, 1, 2] };
+    let offsets = [vec2(0.0, 0.0), vec2(0.0, 1.0), vec2(1.0, 1.0), vec2(1.0, 0.0)];
+
+    let mut verts = Vec::new();
+    let mut indices = Vec::new();
+    for (i, tile) in tiles.iter().enumerate() {
+        let Some(tile) = tile else { continue };
+
+        let pos = vec2((i as i32 % Tilemap::CHUNK_SIZE) as f32, (i as i32 / Tilemap::CHUNK_SIZE) as f32);
+        let uvs = tile_uvs(uv_rect(tile.id), tile.flags);
+
+        let base = verts.len() as u32;
+        for (offset, corner) in offsets.iter().zip(corners) {
+            verts.push(BatchVertex { pos: pos + *offset, tint, uv: uvs[corner], uv1: CORNER_UV1[corner], ..Default::default() });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    return (verts, indices);
+}
+
+
+#[cfg(test)]
+mod test {
+    use nogine2_core::math::{rect::{IRect, Rect}, vector2::{ivec2, vec2}};
+
+    use crate::colors::{rgba::RGBA32, Color};
+
+    use super::{chunk_geometry, tile_uvs, world_to_tile, Tile, TileFlags, TileLayer, Tilemap};
+
+    #[test]
+    fn layer_chunks() {
+        let mut layer = TileLayer::new();
+        layer.set(ivec2(-1, -1), Some(Tile::new(3)));
+        layer.fill(IRect { start: ivec2(0, 0), end: ivec2(17, 2) }, Some(Tile::new(1)));
+
+        assert_eq!(layer.get(ivec2(-1, -1)), Some(Tile::new(3)));
+        assert_eq!(layer.get(ivec2(16, 1)), Some(Tile::new(1)));
+        assert_eq!(layer.get(ivec2(17, 1)), None);
+        assert_eq!(layer.tile_count(), 35);
+        assert_eq!(layer.chunk_count(), 3);
+
+        // Empty chunks are released
+        layer.set(ivec2(-1, -1), None);
+        assert_eq!(layer.chunk_count(), 2);
+        assert!(layer.tiles().any(|(pos, _)| pos == ivec2(16, 0)));
+    }
+
+    #[test]
+    fn coordinates() {
+        let tile_size = vec2(0.5, 0.5);
+        assert_eq!(world_to_tile(vec2(1.2, -0.1), vec2(0.0, 0.0), tile_size), ivec2(2, -1));
+        assert_eq!(world_to_tile(vec2(1.0, 1.0), vec2(1.0, 0.5), tile_size), ivec2(0, 1));
+    }
+
+    #[test]
+    fn geometry() {
+        let uv_rect = Rect { start: vec2(0.0, 0.0), end: vec2(1.0, 1.0) };
+        let [tl, tr, br, bl] = tile_uvs(uv_rect, TileFlags::empty());
+
+        assert_eq!(tile_uvs(uv_rect, TileFlags::FLIP_X), [tr, tl, bl, br]);
+        assert_eq!(tile_uvs(uv_rect, TileFlags::FLIP_X | TileFlags::FLIP_Y), [br, bl, tl, tr]);
+        assert_eq!(tile_uvs(uv_rect, TileFlags::ROTATE_90), [tr, br, bl, tl]);
+
+        let mut tiles = vec![None; (Tilemap::CHUNK_SIZE * Tilemap::CHUNK_SIZE) as usize];
+        tiles[Tilemap::CHUNK_SIZE as usize + 2] = Some(Tile::new(0));
+
+        // The top of the texture is drawn at the top of the tile in both orientations
+        let (verts, indices) = chunk_geometry(&tiles, |_| uv_rect, RGBA32::WHITE, false);
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!((verts[1].pos, verts[1].uv), (vec2(2.0, 2.0), tl));
+
+        let (verts, _) = chunk_geometry(&tiles, |_| uv_rect, RGBA32::WHITE, true);
+        assert_eq!((verts[0].pos, verts[0].uv), (vec2(2.0, 1.0), tl));
+    }
+}
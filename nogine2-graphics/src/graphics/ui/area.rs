@@ -1,6 +1,6 @@
 use std::{marker::PhantomData, num::Wrapping};
 
-use nogine2_core::math::{lerp::CompLerp, rect::Rect, vector2::{uvec2, vec2}};
+use nogine2_core::math::{lerp::CompLerp, rect::{Rect, URect}, vector2::{uvec2, vec2}};
 
 use crate::{colors::{rgba::RGBA32, Color}, graphics::{scope::{NinePatchSubmitCmd, RenderScope}, text::{align::{HorTextAlign, VerTextAlign}, font::Font, TextCfg}, texture::{sprite::Sprite, Texture2D, TextureHandle}, RectSubmitCmd, WHITE_TEX}};
 
@@ -146,6 +146,7 @@ impl<'a> UIArea<'a> {
             tint: RGBA32::WHITE,
             sprite:sprite.clone(),
             corner_scaling: 1.0,
+            center: None,
         });
     }
 
@@ -158,6 +159,21 @@ impl<'a> UIArea<'a> {
         tint: RGBA32,
         sprite: &Sprite,
         corner_scaling: f32
+    ) {
+        self.draw_9_slice(anchor, offset, rot, extents, tint, sprite, corner_scaling, None);
+    }
+
+    /// Draws a 9-patch. `center` is the stretched area in sprite pixels, like the center of an Aseprite 9-slice. Thirds of the sprite are used if `None`.
+    pub fn draw_9_slice(
+        &self,
+        anchor: Anchor,
+        offset: vec2,
+        rot: f32,
+        extents: vec2,
+        tint: RGBA32,
+        sprite: &Sprite,
+        corner_scaling: f32,
+        center: Option<URect>,
     ) {
         let scope = unsafe { self.scope.as_mut().unwrap_unchecked() };
 
@@ -172,6 +188,7 @@ impl<'a> UIArea<'a> {
             tint,
             sprite:sprite.clone(),
             corner_scaling,
+            center,
         });
     }
 
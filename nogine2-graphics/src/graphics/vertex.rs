@@ -91,3 +91,36 @@ impl BlitVertex {
         GlVertexAttribDefinition { id: 2, stride: size_of::<Self>(), offset: offset_of!(Self, uv  ), typ: GlVertexAttribType::Float, vec_len: 2 },
     ];
 }
+
+
+/// Per-instance data of instanced quads. Corners are `origin + right * x + up * y` for `x` and `y` in `[0, 1]`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct InstanceVertex {
+    pub origin: vec2,
+    pub right: vec2,
+    pub up: vec2,
+    /// UV of the `(0, 0)` corner.
+    pub uv_start: vec2,
+    /// UV of the `(1, 1)` corner.
+    pub uv_end: vec2,
+    pub tint: RGBA32,
+    pub user_data: i32,
+}
+
+impl InstanceVertex {
+    pub(crate) const VERT_ATTRIB_DEFINITIONS: &'static [GlVertexAttribDefinition] = &[
+        GlVertexAttribDefinition { id: 6,  stride: size_of::<Self>(), offset: offset_of!(Self, origin   ), typ: GlVertexAttribType::Float, vec_len: 2 },
+        GlVertexAttribDefinition { id: 7,  stride: size_of::<Self>(), offset: offset_of!(Self, right    ), typ: GlVertexAttribType::Float, vec_len: 2 },
+        GlVertexAttribDefinition { id: 8,  stride: size_of::<Self>(), offset: offset_of!(Self, up       ), typ: GlVertexAttribType::Float, vec_len: 2 },
+        GlVertexAttribDefinition { id: 9,  stride: size_of::<Self>(), offset: offset_of!(Self, uv_start ), typ: GlVertexAttribType::Float, vec_len: 2 },
+        GlVertexAttribDefinition { id: 10, stride: size_of::<Self>(), offset: offset_of!(Self, uv_end   ), typ: GlVertexAttribType::Float, vec_len: 2 },
+        GlVertexAttribDefinition { id: 11, stride: size_of::<Self>(), offset: offset_of!(Self, tint     ), typ: GlVertexAttribType::Float, vec_len: 4 },
+        GlVertexAttribDefinition { id: 12, stride: size_of::<Self>(), offset: offset_of!(Self, user_data), typ: GlVertexAttribType::Int, vec_len: 1 },
+    ];
+
+    /// Returns the four corners of the quad.
+    pub(crate) fn corners(&self) -> [vec2; 4] {
+        return [self.origin, self.origin + self.up, self.origin + self.right + self.up, self.origin + self.right];
+    }
+}
@@ -14,10 +14,10 @@ pub fn init_graphics(load_fn: impl Fn(&str) -> *const c_void) -> bool {
     gl_load(load_fn);
     gl_enable_blend();
 
+    Graphics::init();
     if !DefaultSubShaders::init() { return false };
     if !DefaultShaders::init() { return false };
     if !DefaultMaterials::init() { return false };
-    Graphics::init();
 
     log_info!("NOGINE2: Graphics initialized");
     return true;
@@ -6,3 +6,6 @@ pub mod vector2;
 pub mod mat3x3;
 pub mod rect;
 pub mod lerp;
+pub mod spline;
+pub mod transform;
+pub mod polygon;
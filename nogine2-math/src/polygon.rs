@@ -0,0 +1,194 @@
+use super::vector2::vec2;
+
+fn cross(o: vec2, a: vec2, b: vec2) -> f32 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Returns the signed area of a polygon. Positive if the points are in counter-clockwise order.
+pub fn signed_area(points: &[vec2]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    return area * 0.5;
+}
+
+/// Returns if `point` is inside a polygon, using the even-odd rule.
+pub fn contains_point(points: &[vec2], point: vec2) -> bool {
+    let mut inside = false;
+    let mut j = points.len().wrapping_sub(1);
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[j]);
+        if (a.1 > point.1) != (b.1 > point.1) && point.0 < (b.0 - a.0) * (point.1 - a.1) / (b.1 - a.1) + a.0 {
+            inside = !inside;
+        }
+        j = i;
+    }
+    return inside;
+}
+
+/// Triangulates a simple polygon with holes by ear clipping. Points can be in any winding order.
+///
+/// The returned indices refer to the points of `outline` followed by the points of each hole, in order. Every triangle is counter-clockwise.
+pub fn triangulate(outline: &[vec2], holes: &[&[vec2]]) -> Vec<u32> {
+    if outline.len() < 3 {
+        return Vec::new();
+    }
+
+    let points = outline.iter().chain(holes.iter().flat_map(|x| x.iter())).copied().collect::<Vec<_>>();
+
+    let mut ring = (0..outline.len() as u32).collect::<Vec<_>>();
+    if signed_area(outline) < 0.0 {
+        ring.reverse();
+    }
+
+    // Holes go clockwise and are merged from right to left
+    let mut hole_rings = Vec::with_capacity(holes.len());
+    let mut offset = outline.len() as u32;
+    for hole in holes {
+        if hole.len() >= 3 {
+            let mut hole_ring = (offset..offset + hole.len() as u32).collect::<Vec<_>>();
+            if signed_area(hole) > 0.0 {
+                hole_ring.reverse();
+            }
+            hole_rings.push(hole_ring);
+        }
+        offset += hole.len() as u32;
+    }
+
+    let max_x = |ring: &Vec<u32>| ring.iter().map(|i| points[*i as usize].0).fold(f32::NEG_INFINITY, f32::max);
+    hole_rings.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+
+    for i in 0..hole_rings.len() {
+        let (hole, pending) = hole_rings[i..].split_first().unwrap();
+        merge_hole(&points, &mut ring, hole, pending);
+    }
+
+    return ear_clip(&points, ring);
+}
+
+/// Connects `hole` to `ring` through a bridge between two mutually visible vertices.
+fn merge_hole(points: &[vec2], ring: &mut Vec<u32>, hole: &[u32], pending: &[Vec<u32>]) {
+    let hole_start = (0..hole.len()).max_by(|a, b| points[hole[*a] as usize].0.total_cmp(&points[hole[*b] as usize].0)).unwrap();
+    let m = points[hole[hole_start] as usize];
+
+    let mut candidates = (0..ring.len()).collect::<Vec<_>>();
+    let sort_key = |i: usize| {
+        let p = points[ring[i] as usize];
+        let d = p - m;
+        (p.0 < m.0, d.0 * d.0 + d.1 * d.1)
+    };
+    // Closest vertices to the right first, as they are the most likely to be visible
+    candidates.sort_by(|a, b| {
+        let (la, da) = sort_key(*a);
+        let (lb, db) = sort_key(*b);
+        la.cmp(&lb).then(da.total_cmp(&db))
+    });
+
+    let edges = ring_edges(ring).chain(ring_edges(hole)).chain(pending.iter().flat_map(|x| ring_edges(x)));
+    let edges = edges.map(|(a, b)| (points[a as usize], points[b as usize])).collect::<Vec<_>>();
+
+    let bridge = candidates.iter().copied().find(|i| {
+        let v = points[ring[*i] as usize];
+        edges.iter().all(|(a, b)| {
+            if *a == v || *b == v || *a == m || *b == m {
+                return true;
+            }
+            return !segments_intersect(m, v, *a, *b);
+        })
+    }).unwrap_or(candidates[0]);
+
+    let mut merged = Vec::with_capacity(ring.len() + hole.len() + 2);
+    merged.extend_from_slice(&ring[..=bridge]);
+    merged.extend((0..=hole.len()).map(|i| hole[(hole_start + i) % hole.len()]));
+    merged.extend_from_slice(&ring[bridge..]);
+    *ring = merged;
+}
+
+fn ring_edges(ring: &[u32]) -> impl Iterator<Item = (u32, u32)> + '_ {
+    (0..ring.len()).map(|i| (ring[i], ring[(i + 1) % ring.len()]))
+}
+
+fn segments_intersect(a0: vec2, a1: vec2, b0: vec2, b1: vec2) -> bool {
+    let d0 = cross(a0, a1, b0);
+    let d1 = cross(a0, a1, b1);
+    let d2 = cross(b0, b1, a0);
+    let d3 = cross(b0, b1, a1);
+    return (d0 > 0.0) != (d1 > 0.0) && (d2 > 0.0) != (d3 > 0.0) && d0 != 0.0 && d1 != 0.0;
+}
+
+/// Returns if `p` is inside or on the border of the counter-clockwise triangle `abc`.
+fn in_triangle(p: vec2, a: vec2, b: vec2, c: vec2) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+fn ear_clip(points: &[vec2], mut ring: Vec<u32>) -> Vec<u32> {
+    let mut indices = Vec::with_capacity(ring.len().saturating_sub(2) * 3);
+    let mut i = 0;
+    let mut misses = 0;
+
+    while ring.len() > 3 {
+        let len = ring.len();
+        let (ia, ib, ic) = (ring[(i + len - 1) % len], ring[i % len], ring[(i + 1) % len]);
+        let (a, b, c) = (points[ia as usize], points[ib as usize], points[ic as usize]);
+
+        let convex = cross(a, b, c) > 0.0;
+        let is_ear = convex && ring.iter().all(|j| {
+            let p = points[*j as usize];
+            p == a || p == b || p == c || !in_triangle(p, a, b, c)
+        });
+
+        // If no ear is found after a full pass, the polygon is degenerate, so the current vertex is clipped anyway
+        if is_ear || misses >= len {
+            if cross(a, b, c) > 0.0 {
+                indices.extend_from_slice(&[ia, ib, ic]);
+            }
+            ring.remove(i % len);
+            i %= ring.len();
+            misses = 0;
+        } else {
+            i = (i + 1) % len;
+            misses += 1;
+        }
+    }
+
+    let (a, b, c) = (points[ring[0] as usize], points[ring[1] as usize], points[ring[2] as usize]);
+    if cross(a, b, c) > 0.0 {
+        indices.extend_from_slice(&ring);
+    }
+    return indices;
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::vector2::vec2;
+
+    use super::{signed_area, triangulate};
+
+    fn area(points: &[vec2], indices: &[u32]) -> f32 {
+        indices.chunks(3).map(|t| signed_area(&[points[t[0] as usize], points[t[1] as usize], points[t[2] as usize]])).sum()
+    }
+
+    #[test]
+    fn concave() {
+        // Clockwise "L"
+        let points = [vec2(0.0, 0.0), vec2(0.0, 2.0), vec2(1.0, 2.0), vec2(1.0, 1.0), vec2(2.0, 1.0), vec2(2.0, 0.0)];
+        let indices = triangulate(&points, &[]);
+        assert_eq!(indices.len(), 4 * 3);
+        assert!((area(&points, &indices) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn holes() {
+        let outline = [vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 4.0), vec2(0.0, 4.0)];
+        let hole_a = [vec2(1.0, 1.0), vec2(2.0, 1.0), vec2(2.0, 2.0), vec2(1.0, 2.0)];
+        let hole_b = [vec2(2.5, 2.5), vec2(3.5, 2.5), vec2(3.5, 3.5), vec2(2.5, 3.5)];
+        let indices = triangulate(&outline, &[&hole_a, &hole_b]);
+
+        let points = outline.iter().chain(&hole_a).chain(&hole_b).copied().collect::<Vec<_>>();
+        assert!((area(&points, &indices) - 14.0).abs() < 1e-4);
+        assert!(indices.iter().all(|i| (*i as usize) < points.len()));
+    }
+}
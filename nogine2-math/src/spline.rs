@@ -0,0 +1,440 @@
+use super::{lerp::Lerp, vector2::vec2};
+
+/// Parametric 2D curve, defined for `t` in the `[0.0, 1.0]` range.
+pub trait Curve {
+    /// Returns the point at `t`.
+    fn point(&self, t: f32) -> vec2;
+    /// Returns the first derivative at `t`.
+    fn derivative(&self, t: f32) -> vec2;
+
+    /// Returns the normalized direction of the curve at `t`. Will return zero on degenerate points.
+    fn tangent(&self, t: f32) -> vec2 {
+        let d = self.derivative(t);
+        let len = d.length();
+        if len <= f32::EPSILON {
+            return vec2::ZERO;
+        }
+        return d / len;
+    }
+
+    /// Returns the tangent rotated 90º counter-clockwise.
+    fn normal(&self, t: f32) -> vec2 {
+        let tangent = self.tangent(t);
+        return vec2(-tangent.1, tangent.0);
+    }
+
+    /// Returns `segments + 1` points evenly spaced in `t`, to be drawn as a polyline.
+    fn tessellate(&self, segments: usize) -> Vec<vec2> {
+        let segments = segments.max(1);
+        return (0..=segments).map(|i| self.point(i as f32 / segments as f32)).collect();
+    }
+
+    /// Returns the `t` of the point of the curve closest to `point`. The curve is sampled `samples` times and the best sample is then refined.
+    fn nearest_t(&self, point: vec2, samples: usize) -> f32 {
+        let samples = samples.max(2);
+        let sqr_dist = |t: f32| {
+            let d = self.point(t) - point;
+            d.dot(d)
+        };
+
+        let step = 1.0 / samples as f32;
+        let mut best_t = 0.0;
+        let mut best_dist = f32::INFINITY;
+        for i in 0..=samples {
+            let t = i as f32 * step;
+            let dist = sqr_dist(t);
+            if dist < best_dist {
+                best_t = t;
+                best_dist = dist;
+            }
+        }
+
+        // Golden section search around the best sample
+        const INV_PHI: f32 = 0.618034;
+        let mut lo = (best_t - step).max(0.0);
+        let mut hi = (best_t + step).min(1.0);
+        for _ in 0..24 {
+            let a = hi - (hi - lo) * INV_PHI;
+            let b = lo + (hi - lo) * INV_PHI;
+            if sqr_dist(a) < sqr_dist(b) {
+                hi = b;
+            } else {
+                lo = a;
+            }
+        }
+
+        let t = (lo + hi) * 0.5;
+        return if sqr_dist(t) < best_dist { t } else { best_t };
+    }
+
+    /// Returns the point of the curve closest to `point`.
+    fn nearest_point(&self, point: vec2, samples: usize) -> vec2 {
+        self.point(self.nearest_t(point, samples))
+    }
+}
+
+/// Quadratic Bezier curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadBezier(pub vec2, pub vec2, pub vec2);
+
+impl Curve for QuadBezier {
+    fn point(&self, t: f32) -> vec2 {
+        let u = 1.0 - t;
+        return self.0 * (u * u) + self.1 * (2.0 * u * t) + self.2 * (t * t);
+    }
+
+    fn derivative(&self, t: f32) -> vec2 {
+        return (self.1 - self.0) * (2.0 * (1.0 - t)) + (self.2 - self.1) * (2.0 * t);
+    }
+}
+
+/// Cubic Bezier curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CubicBezier(pub vec2, pub vec2, pub vec2, pub vec2);
+
+impl Curve for CubicBezier {
+    fn point(&self, t: f32) -> vec2 {
+        let u = 1.0 - t;
+        return self.0 * (u * u * u) + self.1 * (3.0 * u * u * t) + self.2 * (3.0 * u * t * t) + self.3 * (t * t * t);
+    }
+
+    fn derivative(&self, t: f32) -> vec2 {
+        let u = 1.0 - t;
+        return (self.1 - self.0) * (3.0 * u * u) + (self.2 - self.1) * (6.0 * u * t) + (self.3 - self.2) * (3.0 * t * t);
+    }
+}
+
+/// Returns the segment and local `t` of a piecewise curve.
+fn segment_at(t: f32, segments: usize) -> (usize, f32) {
+    let scaled = t.clamp(0.0, 1.0) * segments as f32;
+    let index = (scaled as usize).min(segments - 1);
+    return (index, scaled - index as f32);
+}
+
+/// Uniform Catmull-Rom spline, which passes through every control point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatmullRom {
+    pub points: Vec<vec2>,
+    /// If the spline loops back to the first point.
+    pub closed: bool,
+}
+
+impl CatmullRom {
+    pub fn new(points: Vec<vec2>, closed: bool) -> Self {
+        Self { points, closed }
+    }
+
+    fn segment_count(&self) -> usize {
+        if self.closed { self.points.len() } else { self.points.len().saturating_sub(1) }
+    }
+
+    fn control(&self, i: isize) -> vec2 {
+        let len = self.points.len() as isize;
+        let i = if self.closed { i.rem_euclid(len) } else { i.clamp(0, len - 1) };
+        return self.points[i as usize];
+    }
+
+    /// Returns the four control points of a segment and its local `t`.
+    fn segment(&self, t: f32) -> ([vec2; 4], f32) {
+        let (i, t) = segment_at(t, self.segment_count());
+        let i = i as isize;
+        return ([self.control(i - 1), self.control(i), self.control(i + 1), self.control(i + 2)], t);
+    }
+}
+
+impl Curve for CatmullRom {
+    fn point(&self, t: f32) -> vec2 {
+        if self.segment_count() == 0 {
+            return self.points.first().copied().unwrap_or(vec2::ZERO);
+        }
+
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let (t2, t3) = (t * t, t * t * t);
+        return (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5;
+    }
+
+    fn derivative(&self, t: f32) -> vec2 {
+        let segments = self.segment_count();
+        if segments == 0 {
+            return vec2::ZERO;
+        }
+
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let local = ((p2 - p0) + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (2.0 * t) + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (3.0 * t * t)) * 0.5;
+        return local * segments as f32;
+    }
+}
+
+/// Uniform cubic B-spline. Smoother than a Catmull-Rom spline, but it doesn't pass through its control points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BSpline {
+    pub points: Vec<vec2>,
+    /// If the spline loops back to the first point.
+    pub closed: bool,
+}
+
+impl BSpline {
+    pub fn new(points: Vec<vec2>, closed: bool) -> Self {
+        Self { points, closed }
+    }
+
+    fn segment_count(&self) -> usize {
+        if self.closed { self.points.len() } else { self.points.len().saturating_sub(3) }
+    }
+
+    fn segment(&self, t: f32) -> ([vec2; 4], f32) {
+        let (i, t) = segment_at(t, self.segment_count());
+        let len = self.points.len();
+        let p = |j: usize| self.points[(i + j) % len];
+        return ([p(0), p(1), p(2), p(3)], t);
+    }
+}
+
+impl Curve for BSpline {
+    fn point(&self, t: f32) -> vec2 {
+        if self.segment_count() == 0 {
+            return self.points.first().copied().unwrap_or(vec2::ZERO);
+        }
+
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let u = 1.0 - t;
+        let (t2, t3) = (t * t, t * t * t);
+        return (p0 * (u * u * u) + p1 * (3.0 * t3 - 6.0 * t2 + 4.0) + p2 * (-3.0 * t3 + 3.0 * t2 + 3.0 * t + 1.0) + p3 * t3) / 6.0;
+    }
+
+    fn derivative(&self, t: f32) -> vec2 {
+        let segments = self.segment_count();
+        if segments == 0 {
+            return vec2::ZERO;
+        }
+
+        let ([p0, p1, p2, p3], t) = self.segment(t);
+        let u = 1.0 - t;
+        let local = (p0 * (-3.0 * u * u) + p1 * (9.0 * t * t - 12.0 * t) + p2 * (-9.0 * t * t + 6.0 * t + 3.0) + p3 * (3.0 * t * t)) / 6.0;
+        return local * segments as f32;
+    }
+}
+
+/// Table that maps distances along a curve to curve parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcLengthTable {
+    /// Accumulated length at `i / (len - 1)`.
+    lengths: Vec<f32>,
+}
+
+impl ArcLengthTable {
+    /// Builds the table by splitting the curve into `samples` segments.
+    pub fn new(curve: &impl Curve, samples: usize) -> Self {
+        let samples = samples.max(1);
+        let mut lengths = Vec::with_capacity(samples + 1);
+        lengths.push(0.0);
+
+        let mut last = curve.point(0.0);
+        let mut total = 0.0;
+        for i in 1..=samples {
+            let p = curve.point(i as f32 / samples as f32);
+            total += (p - last).length();
+            lengths.push(total);
+            last = p;
+        }
+
+        return Self { lengths };
+    }
+
+    /// Total length of the curve.
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap_or(&0.0)
+    }
+
+    /// Returns the distance along the curve at `t`.
+    pub fn distance_at(&self, t: f32) -> f32 {
+        let samples = self.lengths.len() - 1;
+        let scaled = t.clamp(0.0, 1.0) * samples as f32;
+        let i = (scaled as usize).min(samples.saturating_sub(1));
+        return self.lengths[i].lerp(self.lengths[(i + 1).min(samples)], scaled - i as f32);
+    }
+
+    /// Returns the `t` at `distance` along the curve. `distance` is clamped to the length of the curve.
+    pub fn t_at(&self, distance: f32) -> f32 {
+        let samples = self.lengths.len() - 1;
+        if samples == 0 || self.length() <= 0.0 {
+            return 0.0;
+        }
+
+        let distance = distance.clamp(0.0, self.length());
+        let i = self.lengths.partition_point(|x| *x < distance).clamp(1, samples);
+        let (d0, d1) = (self.lengths[i - 1], self.lengths[i]);
+        let local = if d1 > d0 { (distance - d0) / (d1 - d0) } else { 0.0 };
+        return ((i - 1) as f32 + local) / samples as f32;
+    }
+
+    /// Returns `segments + 1` points of `curve` evenly spaced by distance, to be drawn as a polyline.
+    pub fn tessellate(&self, curve: &impl Curve, segments: usize) -> Vec<vec2> {
+        let segments = segments.max(1);
+        let length = self.length();
+        return (0..=segments).map(|i| curve.point(self.t_at(length * i as f32 / segments as f32))).collect();
+    }
+}
+
+/// How a `PathFollower` behaves when it reaches the end of its curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FollowMode {
+    /// Stops at the end.
+    #[default]
+    Once,
+    /// Jumps back to the start.
+    Loop,
+    /// Goes back and forth.
+    PingPong,
+}
+
+/// Moves along a curve at constant speed.
+#[derive(Debug, Clone)]
+pub struct PathFollower<C: Curve> {
+    curve: C,
+    table: ArcLengthTable,
+    distance: f32,
+    direction: f32,
+    pub speed: f32,
+    pub mode: FollowMode,
+}
+
+impl<C: Curve> PathFollower<C> {
+    /// Samples used to build the arc length table.
+    pub const DEFAULT_SAMPLES: usize = 128;
+
+    /// Creates a follower at the start of the curve, moving at `speed` units per second.
+    pub fn new(curve: C, speed: f32, mode: FollowMode) -> Self {
+        let table = ArcLengthTable::new(&curve, Self::DEFAULT_SAMPLES);
+        return Self { curve, table, distance: 0.0, direction: 1.0, speed, mode };
+    }
+
+    pub fn curve(&self) -> &C {
+        &self.curve
+    }
+
+    pub fn table(&self) -> &ArcLengthTable {
+        &self.table
+    }
+
+    /// Replaces the curve, keeping the travelled distance.
+    pub fn set_curve(&mut self, curve: C) {
+        self.table = ArcLengthTable::new(&curve, Self::DEFAULT_SAMPLES);
+        self.curve = curve;
+        self.distance = self.distance.min(self.table.length());
+    }
+
+    /// Distance travelled along the curve.
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.clamp(0.0, self.table.length());
+    }
+
+    /// Returns the travelled fraction of the curve, in the `[0.0, 1.0]` range.
+    pub fn progress(&self) -> f32 {
+        let length = self.table.length();
+        return if length > 0.0 { self.distance / length } else { 1.0 };
+    }
+
+    /// Returns if the follower reached the end. Only possible with `FollowMode::Once`.
+    pub fn is_finished(&self) -> bool {
+        self.mode == FollowMode::Once && self.distance >= self.table.length()
+    }
+
+    /// Moves the follower `speed * dt` units and returns the new position.
+    pub fn advance(&mut self, dt: f32) -> vec2 {
+        let length = self.table.length();
+        if length <= 0.0 {
+            return self.position();
+        }
+
+        let distance = self.distance + self.speed * self.direction * dt;
+        self.distance = match self.mode {
+            FollowMode::Once => distance.clamp(0.0, length),
+            FollowMode::Loop => distance.rem_euclid(length),
+            FollowMode::PingPong => {
+                // Unfolds the back and forth movement into a loop twice as long
+                let unfolded = if self.direction > 0.0 { self.distance } else { length * 2.0 - self.distance };
+                let unfolded = (unfolded + self.speed * dt).rem_euclid(length * 2.0);
+                if unfolded <= length {
+                    self.direction = 1.0;
+                    unfolded
+                } else {
+                    self.direction = -1.0;
+                    length * 2.0 - unfolded
+                }
+            },
+        };
+
+        return self.position();
+    }
+
+    /// Current position.
+    pub fn position(&self) -> vec2 {
+        self.curve.point(self.t())
+    }
+
+    /// Current direction of movement.
+    pub fn tangent(&self) -> vec2 {
+        self.curve.tangent(self.t()) * self.direction
+    }
+
+    /// Current curve parameter.
+    pub fn t(&self) -> f32 {
+        self.table.t_at(self.distance)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::vector2::vec2;
+
+    use super::{ArcLengthTable, CatmullRom, Curve, CubicBezier, FollowMode, PathFollower, QuadBezier};
+
+    fn approx(a: vec2, b: vec2) -> bool {
+        (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn evaluation() {
+        let quad = QuadBezier(vec2(0.0, 0.0), vec2(1.0, 2.0), vec2(2.0, 0.0));
+        assert!(approx(quad.point(0.5), vec2(1.0, 1.0)));
+        assert!(approx(quad.tangent(0.5), vec2(1.0, 0.0)));
+        assert!(approx(quad.normal(0.5), vec2(0.0, 1.0)));
+
+        let cubic = CubicBezier(vec2(0.0, 0.0), vec2(0.0, 1.0), vec2(1.0, 1.0), vec2(1.0, 0.0));
+        assert!(approx(cubic.point(0.0), vec2(0.0, 0.0)));
+        assert!(approx(cubic.point(1.0), vec2(1.0, 0.0)));
+
+        let cr = CatmullRom::new(vec![vec2(0.0, 0.0), vec2(1.0, 1.0), vec2(2.0, 0.0)], false);
+        assert!(approx(cr.point(0.5), vec2(1.0, 1.0)));
+        assert!(approx(cr.point(1.0), vec2(2.0, 0.0)));
+        assert_eq!(cr.tessellate(4).len(), 5);
+
+        assert!(approx(quad.nearest_point(vec2(1.0, 3.0), 16), vec2(1.0, 1.0)));
+    }
+
+    #[test]
+    fn arc_length_and_follower() {
+        let line = QuadBezier(vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(4.0, 0.0));
+        let table = ArcLengthTable::new(&line, 256);
+        assert!((table.length() - 4.0).abs() < 1e-3);
+        assert!(approx(line.point(table.t_at(2.0)), vec2(2.0, 0.0)));
+
+        let mut follower = PathFollower::new(line, 1.0, FollowMode::Once);
+        assert!(approx(follower.advance(1.0), vec2(1.0, 0.0)));
+        assert!(approx(follower.advance(1.0), vec2(2.0, 0.0)));
+        follower.advance(10.0);
+        assert!(follower.is_finished());
+
+        let mut follower = PathFollower::new(line, 1.0, FollowMode::PingPong);
+        assert!(approx(follower.advance(5.0), vec2(3.0, 0.0)));
+        assert!(follower.tangent().0 < 0.0);
+        assert!(approx(follower.advance(4.0), vec2(1.0, 0.0)));
+        assert!(follower.tangent().0 > 0.0);
+    }
+}
@@ -0,0 +1,208 @@
+use super::{mat3x3::mat3, vector2::vec2, vector3::vec3};
+
+/// 2D transform. Applied as scale, then skew, then rotation, then translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform2D {
+    pub pos: vec2,
+    /// Rotation in radians, counter-clockwise.
+    pub rot: f32,
+    pub scale: vec2,
+    /// Horizontal shear angle in radians.
+    pub skew: f32,
+}
+
+impl Transform2D {
+    pub const IDENTITY: Self = Self { pos: vec2::ZERO, rot: 0.0, scale: vec2::ONE, skew: 0.0 };
+
+    pub const fn new(pos: vec2, rot: f32, scale: vec2) -> Self {
+        Self { pos, rot, scale, skew: 0.0 }
+    }
+
+    pub const fn from_pos(pos: vec2) -> Self {
+        Self { pos, ..Self::IDENTITY }
+    }
+
+    pub const fn with_skew(mut self, skew: f32) -> Self {
+        self.skew = skew;
+        return self;
+    }
+
+    /// Returns `self * child`, the transform that applies `child` first and then `self`.
+    pub fn compose(&self, child: &Self) -> Self {
+        Affine::from(*self).mul(&Affine::from(*child)).decompose()
+    }
+
+    /// Returns the transform that undoes this one. Will return `None` if any scale component is zero.
+    pub fn inverse(&self) -> Option<Self> {
+        Affine::from(*self).inverse().map(|x| x.decompose())
+    }
+
+    /// Returns the transform relative to `parent`, so that `parent.compose(&res) == self`.
+    pub fn relative_to(&self, parent: &Self) -> Option<Self> {
+        let inv = Affine::from(*parent).inverse()?;
+        return Some(inv.mul(&Affine::from(*self)).decompose());
+    }
+
+    pub fn transform_point(&self, point: vec2) -> vec2 {
+        Affine::from(*self).apply(point)
+    }
+
+    /// Transforms a direction, ignoring translation.
+    pub fn transform_vector(&self, vector: vec2) -> vec2 {
+        Affine::from(*self).apply_linear(vector)
+    }
+
+    /// Will return `None` if any scale component is zero.
+    pub fn inverse_transform_point(&self, point: vec2) -> Option<vec2> {
+        Affine::from(*self).inverse().map(|x| x.apply(point))
+    }
+
+    /// Decomposes an affine matrix. Projective components are ignored.
+    pub fn from_mat3(mat: &mat3) -> Self {
+        let [r0, r1, _] = mat.0; // Rows
+        return Affine { a: r0.0, b: r1.0, c: r0.1, d: r1.1, tx: r0.2, ty: r1.2 }.decompose();
+    }
+
+    pub fn to_mat3(&self) -> mat3 {
+        let m = Affine::from(*self);
+        return mat3([
+            vec3(m.a, m.c, m.tx),
+            vec3(m.b, m.d, m.ty),
+            vec3(0.0, 0.0, 1.0),
+        ]);
+    }
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Transform2D> for mat3 {
+    fn from(value: Transform2D) -> Self {
+        value.to_mat3()
+    }
+}
+
+impl From<&mat3> for Transform2D {
+    fn from(value: &mat3) -> Self {
+        Self::from_mat3(value)
+    }
+}
+
+impl std::ops::Mul for Transform2D {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.compose(&rhs)
+    }
+}
+
+/// Affine matrix `[a c tx; b d ty]`.
+#[derive(Debug, Clone, Copy)]
+struct Affine {
+    a: f32, b: f32,
+    c: f32, d: f32,
+    tx: f32, ty: f32,
+}
+
+impl From<Transform2D> for Affine {
+    fn from(value: Transform2D) -> Self {
+        let (sin, cos) = value.rot.sin_cos();
+        let shear = value.skew.tan();
+
+        // R * K * S
+        let (a, b) = (cos * value.scale.0, sin * value.scale.0);
+        let (c, d) = ((cos * shear - sin) * value.scale.1, (sin * shear + cos) * value.scale.1);
+        return Self { a, b, c, d, tx: value.pos.0, ty: value.pos.1 };
+    }
+}
+
+impl Affine {
+    fn mul(&self, rhs: &Self) -> Self {
+        Self {
+            a: self.a * rhs.a + self.c * rhs.b,
+            b: self.b * rhs.a + self.d * rhs.b,
+            c: self.a * rhs.c + self.c * rhs.d,
+            d: self.b * rhs.c + self.d * rhs.d,
+            tx: self.a * rhs.tx + self.c * rhs.ty + self.tx,
+            ty: self.b * rhs.tx + self.d * rhs.ty + self.ty,
+        }
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let (a, b, c, d) = (self.d * inv_det, -self.b * inv_det, -self.c * inv_det, self.a * inv_det);
+        return Some(Self { a, b, c, d, tx: -(a * self.tx + c * self.ty), ty: -(b * self.tx + d * self.ty) });
+    }
+
+    fn apply(&self, p: vec2) -> vec2 {
+        self.apply_linear(p) + vec2(self.tx, self.ty)
+    }
+
+    fn apply_linear(&self, v: vec2) -> vec2 {
+        vec2(self.a * v.0 + self.c * v.1, self.b * v.0 + self.d * v.1)
+    }
+
+    fn decompose(&self) -> Transform2D {
+        let sx = (self.a * self.a + self.b * self.b).sqrt();
+        if sx <= f32::EPSILON {
+            return Transform2D { pos: vec2(self.tx, self.ty), rot: 0.0, scale: vec2(0.0, self.d), skew: 0.0 };
+        }
+
+        let rot = self.b.atan2(self.a);
+        let (sin, cos) = rot.sin_cos();
+
+        // Undo the rotation on the second column to get K * S
+        let c = cos * self.c + sin * self.d;
+        let sy = (self.a * self.d - self.b * self.c) / sx;
+        let skew = if sy.abs() <= f32::EPSILON { 0.0 } else { (c / sy).atan() };
+
+        return Transform2D { pos: vec2(self.tx, self.ty), rot, scale: vec2(sx, sy), skew };
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::vector2::vec2;
+
+    use super::Transform2D;
+
+    fn approx(a: vec2, b: vec2) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4
+    }
+
+    fn approx_tf(a: Transform2D, b: Transform2D) -> bool {
+        approx(a.pos, b.pos) && approx(a.scale, b.scale) && (a.rot - b.rot).abs() < 1e-4 && (a.skew - b.skew).abs() < 1e-4
+    }
+
+    #[test]
+    fn compose_and_inverse() {
+        let parent = Transform2D::new(vec2(2.0, 0.0), std::f32::consts::FRAC_PI_2, vec2(2.0, 2.0));
+        let child = Transform2D::from_pos(vec2(1.0, 0.0));
+
+        let world = parent.compose(&child);
+        assert!(approx(world.pos, vec2(2.0, 2.0)));
+        assert!(approx(world.transform_point(vec2(1.0, 0.0)), vec2(2.0, 4.0)));
+
+        let tf = Transform2D::new(vec2(3.0, -1.0), 0.7, vec2(1.5, 0.5)).with_skew(0.3);
+        let inv = tf.inverse().unwrap();
+        assert!(approx_tf(tf.compose(&inv), Transform2D::IDENTITY));
+        assert!(approx(inv.transform_point(tf.transform_point(vec2(4.0, 5.0))), vec2(4.0, 5.0)));
+        assert!(approx_tf(world.relative_to(&parent).unwrap(), child));
+        assert!(Transform2D::new(vec2::ZERO, 0.0, vec2(0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn decompose() {
+        let tf = Transform2D::new(vec2(3.0, -1.0), -2.0, vec2(1.5, -0.5)).with_skew(0.3);
+        assert!(approx_tf(Transform2D::from_mat3(&tf.to_mat3()), tf));
+    }
+}
@@ -75,6 +75,20 @@ impl vec2 {
     pub fn abs(&self) -> Self {
         return Self(self.0.abs(), self.1.abs());
     }
+
+    pub fn dot(self, other: Self) -> f32 {
+        return self.0 * other.0 + self.1 * other.1;
+    }
+
+    pub fn length(self) -> f32 {
+        return self.dot(self).sqrt();
+    }
+
+    /// Returns the vector with a length of `1.0`, or zero if it has no direction.
+    pub fn normalize_or_zero(self) -> Self {
+        let len = self.length();
+        return if len > 0.0 { self / len } else { Self::ZERO };
+    }
 }
 
 gen_vec2!(dvec2, f64, 0.0, 1.0);
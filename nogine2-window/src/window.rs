@@ -1,6 +1,6 @@
 use std::{ffi::CString, sync::{atomic::{AtomicBool, Ordering}, RwLock}, thread::ThreadId, time::{Duration, Instant}};
 
-use nogine2_core::{assert_expr, crash, event::Event, log_info, math::vector2::{ivec2, uvec2, vec2}};
+use nogine2_core::{assert_expr, crash, event::Event, jobs::run_main_thread_jobs, log_info, math::vector2::{ivec2, uvec2, vec2}};
 use nogine2_graphics::{global_begin_render, global_end_render, graphics::{pipeline::{DefaultPipeline, RenderPipeline, RenderStats}, FrameSetup}, init_graphics};
 
 use crate::{deinit_glfw, glfw::{glfwCreateWindow, glfwDestroyWindow, glfwGetFramebufferSize, glfwGetPrimaryMonitor, glfwGetProcAddress, glfwGetVideoMode, glfwGetWindowMonitor, glfwGetWindowSize, glfwIconifyWindow, glfwMakeContextCurrent, glfwMaximizeWindow, glfwPollEvents, glfwRequestWindowAttention, glfwRestoreWindow, glfwSetCursorPosCallback, glfwSetKeyCallback, glfwSetMouseButtonCallback, glfwSetScrollCallback, glfwSetWindowMonitor, glfwSetWindowSize, glfwSetWindowTitle, glfwSwapBuffers, glfwSwapInterval, glfwWindowShouldClose, GLFWbool, GLFWwindow}, glfw_callbacks, init_glfw, input::Input};
@@ -93,6 +93,7 @@ impl Window {
         unsafe {
             glfwPollEvents();
         }
+        run_main_thread_jobs();
 
         let pipeline = if let Some(pipeline) = setup.pipeline {
             unsafe { std::mem::transmute::<_, *const dyn RenderPipeline>(pipeline) } // Hack to stop misdiagnosis from rust (?)
@@ -40,10 +40,7 @@ fn main() {
 
     while window.is_open() {
         window.pre_tick(FrameSetup {
-            camera: CameraData {
-                center: vec2::ZERO,
-                extents: vec2(window.aspect_ratio() * 6.0, 6.0)
-            },
+            camera: CameraData::new(vec2::ZERO, vec2(window.aspect_ratio() * 6.0, 6.0)),
             target_res: window.res(),
             clear_col: RGBA32::BLACK,
             ui_res: Some(window.res()),
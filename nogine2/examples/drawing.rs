@@ -14,7 +14,7 @@ fn main() {
     while window.is_open() {
         center += vec2::from(Input::keyboard().axis2((Key::A, Key::S), (Key::D, Key::W))) * window.ts();
         window.pre_tick(FrameSetup {
-            camera: CameraData { center, extents: vec2(window.aspect_ratio(), 1.0) * 5.0 },
+            camera: CameraData::new(center, vec2(window.aspect_ratio(), 1.0) * 5.0),
             target_res: window.res(), ..Default::default()
         });
 
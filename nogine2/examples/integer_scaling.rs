@@ -27,7 +27,7 @@ fn main() {
     let pipeline = CustomPipeline;
     while window.is_open() {
         window.pre_tick(FrameSetup {
-            camera: CameraData { center: vec2::ZERO, extents: vec2(16.0, 9.0) },
+            camera: CameraData::new(vec2::ZERO, vec2(16.0, 9.0)),
             target_res: uvec2(320, 180),
             clear_col: RGBA32(0.1, 0.2, 0.3, 1.0),
             pipeline: Some(&pipeline),
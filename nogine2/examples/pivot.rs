@@ -8,7 +8,7 @@ fn main() {
 
     while window.is_open() {
         window.pre_tick(FrameSetup {
-            camera: CameraData { center: vec2::ZERO, extents: vec2(window.aspect_ratio(), 1.0) * 5.0 },
+            camera: CameraData::new(vec2::ZERO, vec2(window.aspect_ratio(), 1.0) * 5.0),
             target_res: window.res(), ..Default::default()
         });
 
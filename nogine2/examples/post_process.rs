@@ -22,7 +22,7 @@ fn main() {
         pp_enabled ^= Input::keyboard().key_pressed(Key::Enter);
 
         window.pre_tick(FrameSetup {
-            camera: CameraData { center, extents: vec2(window.aspect_ratio(), 1.0) * 5.0 },
+            camera: CameraData::new(center, vec2(window.aspect_ratio(), 1.0) * 5.0),
             target_res: window.res(), pipeline: if pp_enabled { Some(&pipeline) } else { None },
             ..Default::default()
         });
@@ -1,4 +1,4 @@
-use nogine2::{colors::{rgba::RGBA32, Color}, graphics::{blending::BlendingMode, texture::{sprite::SpriteAtlas, Texture2D, TextureFiltering, TextureSampling, TextureWrapping}, CameraData, FrameSetup, Graphics}, input::{keyboard::Key, Input}, math::{rect::IRect, vector2::{ivec2, uvec2, vec2}}, prelude::init_nogine2, window::{Window, WindowCfg}};
+use nogine2::{colors::{rgba::RGBA32, Color}, graphics::{animation::{AnimationClip, PlaybackMode}, texture::{sprite::SpriteAtlas, Texture2D, TextureFiltering, TextureSampling, TextureWrapping}, tilemap::{Tile, TileFlags, Tilemap}, CameraData, FrameSetup, Graphics}, input::{keyboard::Key, Input}, math::{rect::IRect, vector2::{ivec2, uvec2, vec2}}, prelude::init_nogine2, window::{Window, WindowCfg}};
 
 fn main() {
     init_nogine2();
@@ -11,19 +11,41 @@ fn main() {
 
     Graphics::set_pixels_per_unit(16.0);
 
+    let mut tilemap = Tilemap::new(atlas).with_pos(vec2(-32.0, -4.0));
+    tilemap.set_animation(5, AnimationClip::new(vec![4, 5], 0.5, PlaybackMode::Loop));
+
+    let background = tilemap.add_layer();
+    let background_layer = tilemap.layer_mut(background).unwrap();
+    background_layer.parallax = vec2(0.5, 1.0);
+    background_layer.set_tint(RGBA32(0.6, 0.6, 0.8, 1.0));
+    for x in (0..64).step_by(3) {
+        background_layer.set(ivec2(x, 3), Some(Tile::new(5)));
+    }
+
+    let ground = tilemap.add_layer();
+    let ground_layer = tilemap.layer_mut(ground).unwrap();
+    ground_layer.fill(IRect { start: ivec2(0, 0), end: ivec2(64, 2) }, Some(Tile::new(1)));
+    ground_layer.fill(IRect { start: ivec2(0, 2), end: ivec2(64, 3) }, Some(Tile::new(0)));
+    ground_layer.set(ivec2(10, 3), Some(Tile::new(2)));
+    ground_layer.set(ivec2(11, 3), Some(Tile::new(2).with_flags(TileFlags::FLIP_X)));
+
+    let mut cam_pos = vec2::ZERO;
     while window.is_open() {
+        let dt = window.ts();
+        cam_pos.0 += (Input::keyboard().axis1(Key::A, Key::D) as f32) * 8.0 * dt;
+
         window.pre_tick(FrameSetup {
-            camera: CameraData { center: vec2::ZERO, extents: vec2(window.aspect_ratio(), 1.0) * 5.0 },
+            camera: CameraData::new(cam_pos, vec2(window.aspect_ratio(), 1.0) * 5.0),
             target_res: window.res(), ..Default::default()
         });
 
-        Graphics::draw_sprite(vec2(-2.3, 0.1), 0.0, vec2::ONE, &atlas.get(IRect { start: ivec2::ZERO, end: ivec2::ONE }));
-        Graphics::draw_sprite(vec2(-1.1, 0.1), 0.0, vec2::ONE, &atlas.get(IRect { start: ivec2(1, 0), end: ivec2(2, 1) }));
-        Graphics::draw_sprite(vec2(0.1, 0.1), 0.0, vec2::ONE, &atlas.get(IRect { start: ivec2(2, 0), end: ivec2(3, 1) }));
-        Graphics::draw_sprite(vec2(1.3, 0.1), 0.0, vec2::ONE, &atlas.get(IRect { start: ivec2(3, 0), end: ivec2(4, 1) }));
+        tilemap.update(dt);
+        tilemap.draw();
 
-        Graphics::draw_sprite(vec2(-1.1, -1.2), 0.0, vec2::ONE, &atlas.get(IRect { start: ivec2(0, 1), end: ivec2(1, 2) }));
-        Graphics::draw_sprite(vec2(0.1, -1.2), 0.0, vec2::ONE, &atlas.get(IRect { start: ivec2(1, 1), end: ivec2(2, 2) }));
+        let hovered = tilemap.world_to_tile(cam_pos);
+        Graphics::set_pivot(vec2::one(0.5));
+        Graphics::draw_rect(tilemap.tile_center(hovered), 0.0, tilemap.tile_size(), RGBA32::WHITE.with_alpha(0.25));
+        Graphics::set_pivot(vec2::ZERO);
 
         dbg!(window.post_tick());
     }
@@ -0,0 +1,341 @@
+use std::{any::{Any, TypeId}, cmp::Reverse, ops::Range, sync::{atomic::{AtomicU64, Ordering}, Mutex}};
+
+use nogine2_core::crash;
+use nogine2_graphics::graphics::{ui::area::UIArea, Graphics};
+use nogine2_window::window::Window;
+
+static INSTANCES: Mutex<Instances> = Mutex::new(Instances::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Unique identifier of an instance. Ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstanceId(u64);
+
+impl InstanceId {
+    /// Id that never belongs to an instance.
+    pub const NONE: Self = Self(0);
+}
+
+/// An object type, whose instances receive events every frame.
+///
+/// Events are dispatched in creation order, except for `draw` and `draw_gui`, which are dispatched from highest to lowest `depth`.
+#[allow(unused_variables)]
+pub trait Object: Any + Send {
+    /// Instances with higher depth are drawn first (behind). Read before every draw.
+    fn depth(&self) -> i32 { 0 }
+
+    /// Executes once, at the end of the frame the instance was created in.
+    fn create(&mut self, ctx: &mut InstanceCtx<'_>) {}
+    /// Executes at the start of every frame.
+    fn step(&mut self, ctx: &mut InstanceCtx<'_>) {}
+    /// Executes at the end of every frame, after rendering.
+    fn end_step(&mut self, ctx: &mut InstanceCtx<'_>) {}
+    /// Executes at the start of every frame, right after `step`.
+    fn draw(&mut self, ctx: &mut InstanceCtx<'_>) {}
+    /// Executes after `draw`, only if UI is enabled. `Graphics` is locked during this event, so all drawing must go through `ui`.
+    fn draw_gui(&mut self, ctx: &mut InstanceCtx<'_>, ui: &UIArea<'_>) {}
+    /// Executes once, at the end of the frame the instance was destroyed in.
+    fn destroy(&mut self, ctx: &mut InstanceCtx<'_>) {}
+}
+
+struct Instance {
+    id: InstanceId,
+    type_id: TypeId,
+    obj: Option<Box<dyn Object>>, // None while the instance is running an event
+}
+
+impl Instance {
+    fn downcast_ref<T: Object>(&self) -> Option<&T> {
+        let obj: &dyn Any = self.obj.as_deref()?;
+        return obj.downcast_ref();
+    }
+
+    fn downcast_mut<T: Object>(&mut self) -> Option<&mut T> {
+        let obj: &mut dyn Any = self.obj.as_deref_mut()?;
+        return obj.downcast_mut();
+    }
+}
+
+/// Instance manager. Creation and destruction are deferred to the end of the frame.
+pub struct Instances {
+    list: Vec<Instance>, // Sorted by id
+    pending_create: Vec<Instance>,
+    pending_destroy: Vec<InstanceId>,
+}
+
+impl Instances {
+    const fn new() -> Self {
+        Self { list: Vec::new(), pending_create: Vec::new(), pending_destroy: Vec::new() }
+    }
+
+    /// Queues an instance for creation and returns its id.
+    pub fn create(obj: impl Object) -> InstanceId {
+        let Ok(mut instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        return instances.queue_create(obj);
+    }
+
+    /// Queues an instance for destruction.
+    pub fn destroy(id: InstanceId) {
+        let Ok(mut instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        instances.queue_destroy(id);
+    }
+
+    /// Queues every instance for destruction.
+    pub fn destroy_all() {
+        let Ok(mut instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        let ids = instances.list.iter().chain(&instances.pending_create).map(|x| x.id).collect::<Vec<_>>();
+        for id in ids {
+            instances.queue_destroy(id);
+        }
+    }
+
+    /// Returns if an instance with this id exists or is queued for creation. Not reliable inside events, use `InstanceCtx::exists` instead.
+    pub fn exists(id: InstanceId) -> bool {
+        let Ok(instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        return instances.exists_internal(id);
+    }
+
+    /// Returns the number of instances of type `T`. Not reliable inside events, use `InstanceCtx::count` instead.
+    pub fn count<T: Object>() -> usize {
+        let Ok(instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        return instances.count_internal::<T>();
+    }
+
+    /// Returns the ids of every instance of type `T`. Not reliable inside events, use `InstanceCtx::find` instead.
+    pub fn ids_of<T: Object>() -> Vec<InstanceId> {
+        let Ok(instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        return instances.list.iter().filter(|x| x.type_id == TypeId::of::<T>()).map(|x| x.id).collect();
+    }
+
+    /// Runs `f` with the instance, if it exists and is of type `T`. Not reliable inside events, use `InstanceCtx::get_mut` instead.
+    pub fn with<T: Object, R>(id: InstanceId, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let Ok(mut instances) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        return instances.get_mut_internal::<T>(id).map(f);
+    }
+
+    pub(crate) fn pre_tick(window: &Window) {
+        Self::dispatch(window, |instances, window| {
+            instances.flush(window);
+
+            let order = (0..instances.list.len()).collect::<Vec<_>>();
+            instances.run_event(window, &order, |obj, ctx| obj.step(ctx));
+
+            let order = instances.depth_order();
+            instances.run_event(window, &order, |obj, ctx| obj.draw(ctx));
+
+            // Depth may change during draw, so it is read again
+            let order = instances.depth_order();
+            Graphics::try_ui(|area| {
+                instances.run_event(window, &order, |obj, ctx| obj.draw_gui(ctx, &area));
+            });
+        });
+    }
+
+    pub(crate) fn post_tick(window: &Window) {
+        Self::dispatch(window, |instances, window| {
+            let order = (0..instances.list.len()).collect::<Vec<_>>();
+            instances.run_event(window, &order, |obj, ctx| obj.end_step(ctx));
+
+            instances.flush(window);
+        });
+    }
+
+    /// Takes the singleton out, so the static API can be used from inside events without deadlocking.
+    fn dispatch(window: &Window, f: impl FnOnce(&mut Instances, &Window)) {
+        let mut instances = {
+            let Ok(mut singleton) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+            std::mem::replace(&mut *singleton, Instances::new())
+        };
+
+        f(&mut instances, window);
+
+        let Ok(mut singleton) = INSTANCES.lock() else { crash!("Couldn't access Instances singleton!") };
+        instances.merge_pending(&mut singleton);
+        *singleton = instances;
+    }
+
+    /// Moves the queued creations and destructions of `other` into `self`.
+    fn merge_pending(&mut self, other: &mut Instances) {
+        self.pending_create.append(&mut other.pending_create);
+        self.pending_destroy.append(&mut other.pending_destroy);
+    }
+
+    fn queue_create<T: Object>(&mut self, obj: T) -> InstanceId {
+        let id = InstanceId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        self.pending_create.push(Instance { id, type_id: TypeId::of::<T>(), obj: Some(Box::new(obj)) });
+        return id;
+    }
+
+    fn queue_destroy(&mut self, id: InstanceId) {
+        if !self.pending_destroy.contains(&id) {
+            self.pending_destroy.push(id);
+        }
+    }
+
+    /// Runs the pending destroy and create events until nothing is left queued.
+    fn flush(&mut self, window: &Window) {
+        while !self.pending_destroy.is_empty() || !self.pending_create.is_empty() {
+            for id in std::mem::take(&mut self.pending_destroy) {
+                // Never created, so it doesn't get a destroy event
+                if let Some(index) = self.pending_create.iter().position(|x| x.id == id) {
+                    self.pending_create.remove(index);
+                    continue;
+                }
+
+                let Some(index) = self.index_of(id) else { continue };
+                if let Some(mut obj) = self.list[index].obj.take() {
+                    obj.destroy(&mut InstanceCtx { instances: self, window, current: id });
+                }
+                self.list.remove(index);
+            }
+
+            let order = self.insert_created().collect::<Vec<_>>();
+            self.run_event(window, &order, |obj, ctx| obj.create(ctx));
+        }
+    }
+
+    /// Moves the queued creations into the list and returns their indices.
+    fn insert_created(&mut self) -> Range<usize> {
+        // Creations queued through the singleton are merged after the local ones, but ids are handed out globally
+        // and always newer than the ones in the list, so sorting keeps it ordered
+        let mut created = std::mem::take(&mut self.pending_create);
+        created.sort_by_key(|x| x.id);
+
+        let start = self.list.len();
+        self.list.extend(created);
+        return start..self.list.len();
+    }
+
+    fn run_event(&mut self, window: &Window, order: &[usize], mut f: impl FnMut(&mut dyn Object, &mut InstanceCtx<'_>)) {
+        for &i in order {
+            let id = self.list[i].id;
+            let Some(mut obj) = self.list[i].obj.take() else { continue };
+            f(obj.as_mut(), &mut InstanceCtx { instances: self, window, current: id });
+            self.list[i].obj = Some(obj);
+        }
+    }
+
+    fn depth_order(&self) -> Vec<usize> {
+        let mut order = (0..self.list.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| Reverse(self.list[i].obj.as_ref().map(|x| x.depth()).unwrap_or(0)));
+        return order;
+    }
+
+    fn index_of(&self, id: InstanceId) -> Option<usize> {
+        return self.list.binary_search_by_key(&id, |x| x.id).ok();
+    }
+
+    fn exists_internal(&self, id: InstanceId) -> bool {
+        return self.index_of(id).is_some() || self.pending_create.iter().any(|x| x.id == id);
+    }
+
+    fn count_internal<T: Object>(&self) -> usize {
+        return self.list.iter().filter(|x| x.type_id == TypeId::of::<T>()).count();
+    }
+
+    fn get_mut_internal<T: Object>(&mut self, id: InstanceId) -> Option<&mut T> {
+        let index = self.index_of(id)?;
+        return self.list[index].downcast_mut();
+    }
+}
+
+/// Access to the instance system from inside an event.
+pub struct InstanceCtx<'a> {
+    instances: &'a mut Instances,
+    window: &'a Window,
+    current: InstanceId,
+}
+
+impl<'a> InstanceCtx<'a> {
+    /// Returns the id of the instance running the event.
+    pub fn id(&self) -> InstanceId {
+        self.current
+    }
+
+    /// Returns the window that is being ticked.
+    pub fn window(&self) -> &Window {
+        self.window
+    }
+
+    /// Returns the time elapsed since last frame.
+    pub fn ts(&self) -> f32 {
+        self.window.ts()
+    }
+
+    /// Queues an instance for creation and returns its id.
+    pub fn create(&mut self, obj: impl Object) -> InstanceId {
+        self.instances.queue_create(obj)
+    }
+
+    /// Queues an instance for destruction.
+    pub fn destroy(&mut self, id: InstanceId) {
+        self.instances.queue_destroy(id);
+    }
+
+    /// Queues the instance running the event for destruction.
+    pub fn destroy_self(&mut self) {
+        self.instances.queue_destroy(self.current);
+    }
+
+    /// Returns if an instance with this id exists or is queued for creation.
+    pub fn exists(&self, id: InstanceId) -> bool {
+        self.instances.exists_internal(id)
+    }
+
+    /// Returns the number of instances of type `T`, the current one included.
+    pub fn count<T: Object>(&self) -> usize {
+        self.instances.count_internal::<T>()
+    }
+
+    /// Returns another instance, if it exists and is of type `T`. The instance running the event can't be accessed.
+    pub fn get<T: Object>(&self, id: InstanceId) -> Option<&T> {
+        let index = self.instances.index_of(id)?;
+        return self.instances.list[index].downcast_ref();
+    }
+
+    /// Returns another instance, if it exists and is of type `T`. The instance running the event can't be accessed.
+    pub fn get_mut<T: Object>(&mut self, id: InstanceId) -> Option<&mut T> {
+        self.instances.get_mut_internal(id)
+    }
+
+    /// Returns every other instance of type `T`.
+    pub fn find<T: Object>(&self) -> impl Iterator<Item = (InstanceId, &T)> {
+        self.instances.list.iter().filter_map(|x| Some((x.id, x.downcast_ref::<T>()?)))
+    }
+
+    /// Returns every other instance of type `T`.
+    pub fn find_mut<T: Object>(&mut self) -> impl Iterator<Item = (InstanceId, &mut T)> {
+        self.instances.list.iter_mut().filter_map(|x| Some((x.id, x.downcast_mut::<T>()?)))
+    }
+
+    /// Returns the first other instance of type `T`.
+    pub fn find_first<T: Object>(&self) -> Option<(InstanceId, &T)> {
+        self.find::<T>().next()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Instances, Object, INSTANCES};
+
+    struct Dummy(u32);
+
+    impl Object for Dummy { }
+
+    #[test]
+    fn local_and_singleton_creations() {
+        let mut local = Instances::new();
+        let a = local.queue_create(Dummy(0));
+        let b = Instances::create(Dummy(1));
+        let c = local.queue_create(Dummy(2));
+
+        local.merge_pending(&mut INSTANCES.lock().unwrap());
+        assert_eq!(local.insert_created(), 0..3);
+
+        for (id, value) in [(a, 0), (b, 1), (c, 2)] {
+            assert!(local.exists_internal(id));
+            assert_eq!(local.get_mut_internal::<Dummy>(id).map(|x| x.0), Some(value));
+        }
+    }
+}
@@ -12,8 +12,8 @@
 
 // Re-exports from nogine2-core
 pub use nogine2_core::{
-    bytesize, crash, heap, log_error, log_info, log_warn, math, native_dialog, unwrap_opt,
-    unwrap_res,
+    bytesize, crash, heap, hierarchy, jobs, log_error, log_info, log_warn, math, native_dialog, slotmap,
+    spatial, unwrap_opt, unwrap_res,
 };
 
 // Re-exports from nogine2-window
@@ -23,3 +23,5 @@ pub use nogine2_window::{input, window};
 pub use nogine2_graphics::{colors, graphics};
 
 pub mod prelude;
+pub mod instance;
+pub mod room;
@@ -0,0 +1,336 @@
+use std::{any::{Any, TypeId}, collections::HashMap};
+
+use nogine2_core::{crash, log_warn};
+use nogine2_graphics::graphics::{pipeline::RenderStats, scope::{RenderScope, ScopeRenderSetup}, texture::rendertex::RenderTexture, ui::area::UIArea, FrameSetup, Graphics};
+use nogine2_window::window::Window;
+use transition::{new_rt, TransitionPipeline};
+
+pub use transition::{Transition, TransitionKind};
+
+mod transition;
+
+/// A game scene. Only the room on top of the stack gets updated.
+#[allow(unused_variables)]
+pub trait Room: Any {
+    /// Frame setup used while this room is on top of the stack. The pipeline is overriden during transitions.
+    fn frame_setup(&self, window: &Window) -> FrameSetup<'static> {
+        FrameSetup { target_res: window.fb_size(), ui_res: Some(window.fb_size()), ..Default::default() }
+    }
+
+    /// Persistent rooms keep their state when they are left, and are reused by `push_persistent`/`replace_persistent`.
+    fn persistent(&self) -> bool { false }
+
+    /// Transparent rooms also draw the room below them, which is useful for pause menus.
+    fn transparent(&self) -> bool { false }
+
+    /// Executes when the room is pushed or replaces another room.
+    fn enter(&mut self, ctx: &mut RoomCtx<'_>) {}
+    /// Executes when the room is popped or replaced, after its outgoing transition.
+    fn exit(&mut self, ctx: &mut RoomCtx<'_>) {}
+    /// Executes every frame while the room is on top of the stack.
+    fn update(&mut self, ctx: &mut RoomCtx<'_>) {}
+    /// Executes every frame while the room is visible.
+    fn draw(&mut self, ctx: &mut RoomCtx<'_>) {}
+    /// Executes after `draw`, only if UI is enabled. Not called on rooms that are transitioning out. `Graphics` is locked during this event, so all drawing must go through `ui`.
+    fn draw_gui(&mut self, ctx: &mut RoomCtx<'_>, ui: &UIArea<'_>) {}
+}
+
+enum RoomSource {
+    Room(Box<dyn Room>),
+    Persistent(TypeId, fn() -> Box<dyn Room>),
+}
+
+enum RoomCmd {
+    Push(RoomSource, Option<Transition>),
+    Pop(Option<Transition>),
+    Replace(RoomSource, Option<Transition>),
+}
+
+enum TransitionFrom {
+    /// The outgoing room was popped or replaced.
+    Owned(Box<dyn Room>),
+    /// The outgoing room is right below the top of the stack.
+    Below,
+}
+
+struct ActiveTransition {
+    transition: Transition,
+    elapsed: f32,
+    from: TransitionFrom,
+}
+
+/// Gives access to the room stack from inside room events. Stack changes are applied before the next frame starts.
+pub struct RoomCtx<'a> {
+    window: &'a Window,
+    cmds: &'a mut Vec<RoomCmd>,
+    transitioning: bool,
+}
+
+impl<'a> RoomCtx<'a> {
+    /// Returns the window that is being ticked.
+    pub fn window(&self) -> &Window {
+        self.window
+    }
+
+    /// Returns the time elapsed since last frame.
+    pub fn ts(&self) -> f32 {
+        self.window.ts()
+    }
+
+    /// Returns if a transition is running.
+    pub fn is_transitioning(&self) -> bool {
+        self.transitioning
+    }
+
+    /// Pushes a room on top of the stack. The room below is kept, but stops being updated.
+    pub fn push(&mut self, room: impl Room, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Push(RoomSource::Room(Box::new(room)), transition));
+    }
+
+    /// Pushes the stored instance of a persistent room, or a new one if there is none. Ignored if the room is already in the stack.
+    pub fn push_persistent<R: Room + Default>(&mut self, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Push(RoomSource::Persistent(TypeId::of::<R>(), make_room::<R>), transition));
+    }
+
+    /// Pops the room on top of the stack.
+    pub fn pop(&mut self, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Pop(transition));
+    }
+
+    /// Replaces the room on top of the stack.
+    pub fn replace(&mut self, room: impl Room, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Replace(RoomSource::Room(Box::new(room)), transition));
+    }
+
+    /// Replaces the room on top of the stack with the stored instance of a persistent room, or a new one if there is none. Ignored if the room is already in the stack.
+    pub fn replace_persistent<R: Room + Default>(&mut self, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Replace(RoomSource::Persistent(TypeId::of::<R>(), make_room::<R>), transition));
+    }
+}
+
+fn make_room<R: Room + Default>() -> Box<dyn Room> {
+    Box::new(R::default())
+}
+
+
+/// Room stack manager. Drives the window ticks with the frame setup of the room on top.
+pub struct Rooms {
+    stack: Vec<Box<dyn Room>>,
+    persistent: HashMap<TypeId, Box<dyn Room>>,
+    cmds: Vec<RoomCmd>,
+
+    transition: Option<ActiveTransition>,
+    pipeline: Option<Box<TransitionPipeline>>,
+    from_scope: RenderScope,
+    from_rt: Option<RenderTexture>,
+}
+
+impl Rooms {
+    /// Creates a room stack. `first` will be entered on the first tick.
+    pub fn new(first: impl Room) -> Self {
+        Self {
+            stack: Vec::new(),
+            persistent: HashMap::new(),
+            cmds: vec![RoomCmd::Push(RoomSource::Room(Box::new(first)), None)],
+            transition: None,
+            pipeline: None,
+            from_scope: RenderScope::new(),
+            from_rt: None,
+        }
+    }
+
+    /// Returns the number of rooms in the stack.
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Returns the room on top of the stack.
+    pub fn top(&self) -> Option<&dyn Room> {
+        self.stack.last().map(|x| x.as_ref())
+    }
+
+    /// Returns if a transition is running.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Pushes a room on top of the stack before the next frame starts.
+    pub fn push(&mut self, room: impl Room, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Push(RoomSource::Room(Box::new(room)), transition));
+    }
+
+    /// Pops the room on top of the stack before the next frame starts.
+    pub fn pop(&mut self, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Pop(transition));
+    }
+
+    /// Replaces the room on top of the stack before the next frame starts.
+    pub fn replace(&mut self, room: impl Room, transition: Option<Transition>) {
+        self.cmds.push(RoomCmd::Replace(RoomSource::Room(Box::new(room)), transition));
+    }
+
+    /// Runs a whole frame: `Window::pre_tick`, room events and `Window::post_tick`. Will panic if the stack is empty.
+    pub fn tick(&mut self, window: &mut Window) -> RenderStats {
+        self.apply_cmds(window);
+
+        let Some(top) = self.stack.last() else { crash!("Room stack is empty!") };
+        let mut setup = top.frame_setup(window);
+        let target_res = setup.target_res;
+
+        if let Some(transition) = &self.transition {
+            let pipeline = self.pipeline.get_or_insert_with(|| Box::new(TransitionPipeline::new()));
+            let progress = (transition.elapsed / transition.transition.duration).clamp(0.0, 1.0);
+            let from_rt = match self.from_rt.take() {
+                Some(rt) if rt.dims() == target_res => rt,
+                _ => new_rt(target_res),
+            };
+
+            pipeline.prepare(&from_rt, &transition.transition, progress);
+            self.from_rt = Some(from_rt);
+            setup.pipeline = Some(pipeline.as_ref());
+        }
+
+        window.pre_tick(setup);
+        let win = &*window;
+        let transitioning = self.transition.is_some();
+
+        // Update
+        let top = self.stack.last_mut().unwrap();
+        top.update(&mut RoomCtx { window: win, cmds: &mut self.cmds, transitioning });
+
+        // Draw every room from the first opaque one
+        let first_visible = self.stack.iter().rposition(|x| !x.transparent()).unwrap_or(0);
+        for room in &mut self.stack[first_visible..] {
+            room.draw(&mut RoomCtx { window: win, cmds: &mut self.cmds, transitioning });
+        }
+        Graphics::try_ui(|area| {
+            for room in &mut self.stack[first_visible..] {
+                room.draw_gui(&mut RoomCtx { window: win, cmds: &mut self.cmds, transitioning }, &area);
+            }
+        });
+
+        self.draw_outgoing(win);
+
+        let stats = window.post_tick();
+
+        if let Some(transition) = &mut self.transition {
+            transition.elapsed += window.ts();
+            if transition.elapsed >= transition.transition.duration {
+                self.end_transition(window);
+            }
+        }
+
+        return stats;
+    }
+
+    /// Renders the outgoing room of the running transition into `from_rt`.
+    fn draw_outgoing(&mut self, window: &Window) {
+        let Some(transition) = &mut self.transition else { return };
+        let Some(from_rt) = &self.from_rt else { return };
+
+        let len = self.stack.len();
+        let from = match &mut transition.from {
+            TransitionFrom::Owned(room) => room.as_mut(),
+            TransitionFrom::Below if len >= 2 => self.stack[len - 2].as_mut(),
+            TransitionFrom::Below => return,
+        };
+
+        // Commands from outgoing rooms are ignored
+        let mut cmds = Vec::new();
+        let setup = from.frame_setup(window);
+        self.from_scope.run(from_rt, ScopeRenderSetup { camera: setup.camera, clear_col: setup.clear_col, pipeline: None }, || {
+            from.draw(&mut RoomCtx { window, cmds: &mut cmds, transitioning: true });
+        });
+    }
+
+    fn apply_cmds(&mut self, window: &Window) {
+        while !self.cmds.is_empty() {
+            for cmd in std::mem::take(&mut self.cmds) {
+                // Only one transition can run at a time
+                if self.transition.is_some() {
+                    self.end_transition(window);
+                }
+
+                match cmd {
+                    RoomCmd::Push(src, transition) => {
+                        if self.in_stack(&src) {
+                            log_warn!("Tried to push a persistent room that is already in the room stack!");
+                            continue;
+                        }
+
+                        let mut room = self.resolve(src);
+                        room.enter(&mut RoomCtx { window, cmds: &mut self.cmds, transitioning: transition.is_some() });
+                        if let Some(transition) = transition.filter(|x| x.duration > 0.0 && !self.stack.is_empty()) {
+                            self.transition = Some(ActiveTransition { transition, elapsed: 0.0, from: TransitionFrom::Below });
+                        }
+                        self.stack.push(room);
+                    },
+                    RoomCmd::Pop(transition) => {
+                        let Some(room) = self.stack.pop() else {
+                            log_warn!("Tried to pop a room from an empty room stack!");
+                            continue;
+                        };
+                        self.leave(window, room, transition);
+                    },
+                    RoomCmd::Replace(src, transition) => {
+                        if self.in_stack(&src) {
+                            log_warn!("Tried to replace with a persistent room that is already in the room stack!");
+                            continue;
+                        }
+
+                        let old = self.stack.pop();
+                        let mut room = self.resolve(src);
+                        room.enter(&mut RoomCtx { window, cmds: &mut self.cmds, transitioning: transition.is_some() });
+                        self.stack.push(room);
+                        if let Some(old) = old {
+                            self.leave(window, old, transition);
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    /// Exits a room, or keeps it alive until its outgoing transition ends.
+    fn leave(&mut self, window: &Window, mut room: Box<dyn Room>, transition: Option<Transition>) {
+        if let Some(transition) = transition.filter(|x| x.duration > 0.0 && !self.stack.is_empty()) {
+            self.transition = Some(ActiveTransition { transition, elapsed: 0.0, from: TransitionFrom::Owned(room) });
+            return;
+        }
+
+        room.exit(&mut RoomCtx { window, cmds: &mut self.cmds, transitioning: false });
+        self.store(room);
+    }
+
+    fn end_transition(&mut self, window: &Window) {
+        let Some(transition) = self.transition.take() else { return };
+        if let TransitionFrom::Owned(mut room) = transition.from {
+            room.exit(&mut RoomCtx { window, cmds: &mut self.cmds, transitioning: false });
+            self.store(room);
+        }
+    }
+
+    /// Returns if `src` is a persistent room that is already in the stack, so it can't be entered again.
+    fn in_stack(&self, src: &RoomSource) -> bool {
+        let RoomSource::Persistent(type_id, _) = src else { return false };
+        return self.stack.iter().any(|x| (x.as_ref() as &dyn Any).type_id() == *type_id);
+    }
+
+    fn resolve(&mut self, src: RoomSource) -> Box<dyn Room> {
+        match src {
+            RoomSource::Room(room) => room,
+            RoomSource::Persistent(type_id, make) => self.persistent.remove(&type_id).unwrap_or_else(make),
+        }
+    }
+
+    fn store(&mut self, room: Box<dyn Room>) {
+        if room.persistent() {
+            let type_id = (room.as_ref() as &dyn Any).type_id();
+            self.persistent.insert(type_id, room);
+        }
+    }
+}
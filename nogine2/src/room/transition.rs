@@ -0,0 +1,140 @@
+use std::{cell::RefCell, sync::Arc};
+
+use nogine2_core::math::{vector2::{uvec2, vec2}, vector4::vec4};
+use nogine2_graphics::{colors::rgba::RGBA32, graphics::{defaults::DefaultSubShaders, material::{Material, Uniform}, pipeline::{DefaultPipeline, RenderPipeline, RenderStats, SceneData}, shader::{Shader, SubShader, SubShaderType}, texture::{rendertex::RenderTexture, TextureFiltering, TextureSampling, TextureWrapping}}};
+
+/// Timed effect between an outgoing and an incoming room.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    /// Duration in seconds.
+    pub duration: f32,
+}
+
+impl Transition {
+    /// Fades out to `color` and then fades in.
+    pub const fn fade(color: RGBA32, duration: f32) -> Self {
+        Self { kind: TransitionKind::Fade(color), duration }
+    }
+
+    /// Reveals the incoming room with an edge moving towards `dir`.
+    pub const fn wipe(dir: vec2, duration: f32) -> Self {
+        Self { kind: TransitionKind::Wipe(dir), duration }
+    }
+
+    /// Reveals the incoming room with a growing circle, centered at `center` in normalized screen coordinates.
+    pub const fn iris(center: vec2, duration: f32) -> Self {
+        Self { kind: TransitionKind::Iris(center), duration }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionKind {
+    /// Fades through a color.
+    Fade(RGBA32),
+    /// Wipes towards a direction.
+    Wipe(vec2),
+    /// Opens a circle from a point in normalized screen coordinates.
+    Iris(vec2),
+}
+
+
+/// Pipeline that renders the incoming scene blended with a snapshot of the outgoing one.
+pub(crate) struct TransitionPipeline {
+    material: Arc<Material>,
+    to_rt: RefCell<Option<RenderTexture>>,
+}
+
+impl TransitionPipeline {
+    pub fn new() -> Self {
+        let material = Material::new(Shader::new(
+            &DefaultSubShaders::blit_vert(),
+            &SubShader::new(TRANSITION_FRAG_SRC, SubShaderType::Fragment).unwrap(),
+        ).unwrap());
+
+        return Self { material, to_rt: RefCell::new(None) };
+    }
+
+    /// Sets up the material for the next render. `progress` must be in the range `[0.0, 1.0]`.
+    pub fn prepare(&self, from: &RenderTexture, transition: &Transition, progress: f32) {
+        let aspect = from.dims().0 as f32 / from.dims().1.max(1) as f32;
+        let (kind, color, param) = match transition.kind {
+            TransitionKind::Fade(color) => (0, color, vec2::ZERO),
+            TransitionKind::Wipe(dir) => (1, RGBA32::CLEAR, dir),
+            TransitionKind::Iris(center) => (2, RGBA32::CLEAR, center),
+        };
+
+        if let Some(handle) = from.handle() {
+            self.material.set_sampler(c"uFrom", handle);
+        }
+        self.material.set_uniform(c"uProgress", Uniform::Float(progress));
+        self.material.set_uniform(c"uKind", Uniform::Int(kind));
+        self.material.set_uniform(c"uColor", Uniform::Vec4(vec4(color.0, color.1, color.2, color.3)));
+        self.material.set_uniform(c"uParam", Uniform::Vec2(param));
+        self.material.set_uniform(c"uAspect", Uniform::Float(aspect));
+    }
+}
+
+impl RenderPipeline for TransitionPipeline {
+    fn render(&self, target_rt: &RenderTexture, scene_data: Option<SceneData<'_>>, ui_data: Option<SceneData<'_>>, clear_col: RGBA32, stats: &mut RenderStats) {
+        let Some(scene_data) = scene_data else {
+            DefaultPipeline.render(target_rt, None, ui_data, clear_col, stats);
+            return;
+        };
+
+        let mut to_rt = self.to_rt.borrow_mut();
+        if to_rt.as_ref().map(|x| x.dims() != scene_data.target_res()).unwrap_or(true) {
+            *to_rt = Some(new_rt(scene_data.target_res()));
+        }
+        let to_rt = to_rt.as_ref().unwrap();
+
+        to_rt.clear(clear_col);
+        scene_data.render_to(to_rt, stats);
+
+        target_rt.clear(clear_col);
+        target_rt.combine_with_material(to_rt, self.material.clone(), stats);
+        if let Some(ui_data) = ui_data {
+            ui_data.render_to(target_rt, stats);
+        }
+    }
+}
+
+pub(crate) fn new_rt(dims: uvec2) -> RenderTexture {
+    RenderTexture::new(dims, TextureSampling { filtering: TextureFiltering::Linear, wrapping: TextureWrapping::Clamp })
+}
+
+const TRANSITION_FRAG_SRC: &[u8] = br#"
+#version 330 core
+
+layout(location = 0) out vec4 fCol;
+
+in vec4 vTint;
+in vec2 vUV;
+
+uniform sampler2D uTextures[1]; // Only the incoming room, bound by `combine_with_material`
+uniform sampler2D uFrom;
+
+uniform float uProgress;
+uniform int uKind;
+uniform vec4 uColor;
+uniform vec2 uParam;
+uniform float uAspect;
+
+void main() {
+    vec4 to = texture(uTextures[0], vUV);
+    vec4 from = texture(uFrom, vUV);
+
+    if (uKind == 0) { // Fade
+        fCol = uProgress < 0.5 ? mix(from, uColor, uProgress * 2.0) : mix(uColor, to, uProgress * 2.0 - 1.0);
+    } else if (uKind == 1) { // Wipe
+        float extent = 0.5 * (abs(uParam.x) + abs(uParam.y));
+        float t = extent > 0.0 ? 0.5 + 0.5 * dot(vUV - 0.5, uParam) / extent : 0.0;
+        fCol = t < uProgress ? to : from;
+    } else { // Iris
+        vec2 scale = vec2(uAspect, 1.0);
+        float max_radius = length(max(uParam, 1.0 - uParam) * scale);
+        fCol = length((vUV - uParam) * scale) < uProgress * max_radius ? to : from;
+    }
+    fCol *= vTint;
+}
+"#;